@@ -1,18 +1,37 @@
-use std::io::IsTerminal;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::io::{IsTerminal, Write};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 use anyhow::{Context, Result, anyhow, bail};
-use clap::{ArgAction, Parser};
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
 
 use crate::DEFAULT_LIMIT;
-use crate::discovery::{self, DiscoveryOptions};
-use crate::search::search_sessions;
-use crate::session::Session;
-use crate::tui::{self, TuiConfig};
-use crate::util::{format_relative, format_time_of_day, format_timestamp};
+use crate::config::{Config, Defaults};
+use crate::discovery::{self, DiscoveryOptions, FileStatus};
+use crate::keys::KeyMap;
+use crate::search::{
+    MatcherKind, MessageCache, RoleFilter, Scorer, SearchOptions, SortMode, compile_regex,
+    normalize_query, search_messages, search_projects, search_sessions,
+};
+use crate::session::{Message, SearchResult, Session, TimeBasis};
+use crate::state::{self, StateKind, StatePaths};
+use crate::tui::{self, Theme, TuiConfig};
+use crate::util::{
+    Glyphs, format_duration, format_relative, format_time_of_day, format_timestamp, local_date,
+    normalize_path, paths_related,
+};
 
-fn snippet_to_cli_line(snippet: &crate::session::Snippet) -> String {
+/// The snippet with highlights in bold, or as plain text when `color` is off.
+fn snippet_to_cli_line(snippet: &crate::session::Snippet, color: bool) -> String {
+    if !color {
+        return snippet_to_plain_line(snippet);
+    }
     let mut out = String::new();
     for segment in &snippet.segments {
         if segment.highlighted {
@@ -26,14 +45,73 @@ fn snippet_to_cli_line(snippet: &crate::session::Snippet) -> String {
     out
 }
 
+/// When list output may use terminal escapes (`--color`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Only on a terminal, and not when NO_COLOR is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to emit escapes when writing to stdout (`to_stdout`) or a file.
+    fn enabled(self, to_stdout: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                to_stdout
+                    && std::io::stdout().is_terminal()
+                    && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+            }
+        }
+    }
+}
+
+/// Where the resume command runs once a session is selected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ResumeTarget {
+    /// Replace the search UI in the current terminal
+    #[default]
+    Current,
+    /// Open a new tmux window
+    TmuxWindow,
+    /// Split the current tmux window
+    TmuxPane,
+}
+
+/// Scan window used up front for `--cwd`, and for the one-off retry when a
+/// non-interactive query finds nothing in the default window.
+const WIDENED_SCAN_LIMIT: usize = 1000;
+
 #[derive(Debug, Parser)]
 #[command(author, version, about = "Lightning fast Codex session search", long_about = None)]
+// Saved searches put their flags before the command line's, so later values must win.
+#[command(args_override_self = true)]
 pub struct Args {
-    /// Search query terms (fuzzy matched against conversations)
+    /// Search query terms (fuzzy matched against conversations); when omitted, the
+    /// CODEX_SEARCH_QUERY env var is used
     #[arg(trailing_var_arg = true)]
     pub query: Vec<String>,
 
-    /// Number of results to return
+    /// Read settings from this config file instead of ~/.config/codex-search/config.toml
+    /// (or the CODEX_SEARCH_CONFIG env var); unlike the default file, it must exist
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Print the effective settings (after flags, env vars and the config file) as a
+    /// config [defaults] table, then exit
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub print_config: bool,
+
+    /// Run the saved search NAME from the config's [searches] table; flags and query
+    /// terms given on the command line override the saved ones
+    #[arg(long, value_name = "NAME")]
+    pub search: Option<String>,
+
+    /// Number of results to return, counted after all filtering and dedup
     #[arg(short, long, default_value_t = DEFAULT_LIMIT)]
     pub limit: usize,
 
@@ -45,56 +123,379 @@ pub struct Args {
     #[arg(long, action = ArgAction::SetTrue)]
     pub json: bool,
 
+    /// Print only the number of matching sessions (or messages with --in-session);
+    /// counts over the scanned window without widening it (disables TUI)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub count: bool,
+
+    /// Emit results as newline-delimited JSON, one compact object per line (disables TUI);
+    /// results are ranked first, so lines are written once the search finishes
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub ndjson: bool,
+
+    /// With --json, wrap search results as `{"meta": …, "results": […]}`, recording the
+    /// resolved query, filters, sessions root and version
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub json_meta: bool,
+
+    /// Append each session's first and last message times and their span to list lines
+    /// (JSON always carries `earliest_message_time` / `latest_message_time`)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub span: bool,
+
+    /// Write --json/--list/--bench/--validate output to this file instead of stdout
+    #[arg(long, value_name = "PATH")]
+    pub output_file: Option<PathBuf>,
+
+    /// Highlight list output with terminal escapes: auto (only on a terminal without
+    /// NO_COLOR set), always, or never
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
     /// Disable the interactive TUI even without other output flags
     #[arg(long, action = ArgAction::SetTrue)]
     pub no_tui: bool,
 
+    /// Pick a result with an external selector instead of the built-in TUI, then resume it
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub interactive: bool,
+
+    /// Selector command for --interactive; reads candidate lines on stdin and prints the
+    /// chosen one (defaults to `fzf` or the CODEX_SEARCH_SELECTOR env var)
+    #[arg(long, value_name = "CMD")]
+    pub selector: Option<String>,
+
     /// Restrict results to sessions tied to the current working directory (when available)
     #[arg(long, action = ArgAction::SetTrue)]
     pub cwd: bool,
 
+    /// Also search the archive directory (see --archive-dir)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_archive: bool,
+
+    /// Archive directory searched with --include-archive (defaults to `archived_sessions`
+    /// next to the sessions directory, or the CODEX_SEARCH_ARCHIVE_DIR env var)
+    #[arg(long, value_name = "PATH")]
+    pub archive_dir: Option<PathBuf>,
+
+    /// Regex for session file stems with named groups `uuid` (required), `label` and
+    /// `datetime` (YYYY-MM-DDTHH-MM-SS), for forks that name files differently
+    #[arg(long, value_name = "REGEX")]
+    pub filename_regex: Option<String>,
+
+    /// Only rank sessions with these uuids: a file with one uuid per line (extra
+    /// whitespace-separated columns are ignored) or a comma-separated list
+    #[arg(long, value_name = "FILE|LIST")]
+    pub restrict_uuids: Option<String>,
+
+    /// Skip session files whose path, and sessions whose cwd, matches GLOB (or sits in a
+    /// directory that does), e.g. `**/scratch`; repeatable. Defaults to the
+    /// colon-separated CODEX_SEARCH_EXCLUDE env var
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
     /// Maximum number of session files to scan
     #[arg(long)]
     pub scan_limit: Option<usize>,
 
-    /// Override the sessions directory (defaults to ~/.codex/sessions)
-    #[arg(long)]
-    pub sessions_dir: Option<PathBuf>,
+    /// Override the sessions directory (defaults to ~/.codex/sessions, falling back to
+    /// ~/.config/codex/sessions, $CODEX_HOME/sessions, or ~/Library/Application Support/codex/sessions);
+    /// repeat to search several directories together
+    #[arg(long, value_name = "DIR")]
+    pub sessions_dir: Vec<PathBuf>,
 
-    /// Command template executed when a session is selected (use {uuid}).
+    /// Command template executed when a session is selected (use {uuid}, or {path} and
+    /// {label} for the session file and label as one shell-quoted word each; {match} is the
+    /// matched message as one shell-quoted word and {match_role} its role, both empty
+    /// without a match; `{prompt:label}` or `{prompt:label=default}` asks for a value
+    /// on selection).
     /// Defaults to the Codex CLI unless overridden by the CODEX_SEARCH_RESUME env var.
     #[arg(long, default_value = "")]
     pub resume_command: String,
 
+    /// Where to run the resume command: in this terminal or a new tmux window/pane
+    #[arg(long, value_enum, default_value_t = ResumeTarget::Current)]
+    pub resume_in: ResumeTarget,
+
+    /// Use ASCII `...` and `-` instead of Unicode ellipsis/box-drawing glyphs
+    /// (picked automatically when the locale is not UTF-8)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub ascii: bool,
+
+    /// Override the truncation marker shown in snippets
+    #[arg(long, value_name = "TEXT")]
+    pub ellipsis: Option<String>,
+
+    /// Override the character used for row separators in the TUI
+    #[arg(long, value_name = "CHAR")]
+    pub separator: Option<char>,
+
     /// Preview character limit (affects in-memory message snippets)
     #[arg(long)]
     pub preview_limit: Option<usize>,
 
+    /// Result order: `recent` (newest match first, recency boosts score) or `score`
+    /// (textual relevance only, recency ignored entirely)
+    #[arg(long, value_enum, default_value_t = SortMode::Recent)]
+    pub sort: SortMode,
+
+    /// Bonus points for matches in later messages of a session, scaled from 0 (first
+    /// message) to N (latest); 0 (default) disables it
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub message_recency_weight: i64,
+
+    /// Drop results scoring below N, trading recall for precision. Text matches score
+    /// 10,000 and up, fuzzy-only label/uuid hits a few hundred, and `--sort recent`
+    /// adds up to 50,000 for brand-new sessions; 0 (default) keeps everything
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        allow_negative_numbers = true
+    )]
+    pub min_score: i64,
+
+    /// Timestamp shown for each result and used for recency ordering: `match` (matched or
+    /// latest message), `created` (from the filename), or `modified` (file mtime)
+    #[arg(long, value_enum, default_value_t = TimeBasis::Match)]
+    pub time_basis: TimeBasis,
+
+    /// Text matcher used for scoring: `skim` (fuzzy) or `literal` (substring)
+    #[arg(long, value_enum, default_value_t = MatcherKind::Skim)]
+    pub matcher: MatcherKind,
+
+    /// Only match (and preview) messages from this speaker; with `user` or `assistant`
+    /// a session needs a matching message of that role
+    #[arg(long, value_enum, default_value_t = RoleFilter::Any)]
+    pub role: RoleFilter,
+
+    /// Only match (and preview) each session's first user message, the prompt that
+    /// opened the conversation
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub first_message: bool,
+
+    /// Only match sessions containing every query term (case-insensitive substring)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub all_terms: bool,
+
+    /// Treat the query as a regular expression over message text (case-sensitive;
+    /// prefix `(?i)` to ignore case)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub regex: bool,
+
+    /// Keep sessions without user/assistant messages so they can still be found by label
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub keep_empty: bool,
+
+    /// Parse at most N messages per session to bound memory and scoring cost;
+    /// matches after the cap are not found
+    #[arg(long, value_name = "N")]
+    pub max_messages_per_session: Option<usize>,
+
+    /// Merge consecutive messages of the same role into one turn before scoring, so
+    /// phrases split across adjacent messages still match
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub merge_turns: bool,
+
+    /// Label sessions by their filename even when Codex metadata sets a title (the
+    /// title is still searched)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub filename_labels: bool,
+
+    /// Fail with every unparseable session file and the reason instead of skipping
+    /// them (invalid JSON lines count; an unterminated last line does not)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub strict: bool,
+
+    /// Parse every session file instead of reusing the on-disk index of parsed
+    /// sessions (the index is neither read nor written)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub no_cache: bool,
+
+    /// Discard the on-disk session index, re-parse every file and write a fresh one
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub rebuild_index: bool,
+
+    /// Normalize session text and the query to Unicode NFC so composed and decomposed
+    /// accents match each other (worth it for non-ASCII content; costs load time)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub unicode_normalize: bool,
+
+    /// Match the query against raw session file lines instead of extracted messages
+    /// (finds tool names, URLs and metadata the extractor drops; disables the TUI)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub raw: bool,
+
+    /// Search each cwd as one unit (all its sessions combined) and print one result
+    /// per cwd, resuming the session with the best match (disables the TUI)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub merge_cwd: bool,
+
+    /// Read session JSONL from stdin instead of the sessions directory; each
+    /// `session_meta` record starts a new session (disables the TUI)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub stdin: bool,
+
+    /// Also search assistant reasoning/thinking text (shown as the snippet only when
+    /// nothing else matches)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_reasoning: bool,
+
+    /// Also search tool calls (shell commands, arguments) and their output, as "tool"
+    /// messages
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_tools: bool,
+
+    /// Keep only message previews in memory; full text is reloaded for displayed results
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub low_memory: bool,
+
+    /// Threads used to load and scan session files (defaults to the CODEX_SEARCH_JOBS
+    /// env var, then the config's `jobs`, then all cores; 0 also means all cores)
+    #[arg(long, value_name = "N")]
+    pub jobs: Option<usize>,
+
+    /// Age at which the recency boost halves, e.g. `7d`, `36h`
+    /// (defaults to 7 days or the CODEX_SEARCH_RECENCY_HALF_LIFE env var)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub recency_half_life: Option<time::Duration>,
+
     /// Do not execute the resume command, just print it (useful for scripting)
     #[arg(long, action = ArgAction::SetTrue)]
     pub dry_run: bool,
 
+    /// File the TUI writes the current results to when pressing Ctrl-E
+    #[arg(long, value_name = "PATH", default_value = "codex-search-results.json")]
+    pub export_path: PathBuf,
+
+    /// Use a high-contrast TUI palette (white on black, no gray-on-gray; also enabled
+    /// by a non-empty CODEX_SEARCH_HIGH_CONTRAST other than `0`)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub high_contrast: bool,
+
+    /// With an empty query, open the TUI as a date-grouped journal of sessions
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub browse: bool,
+
+    /// Prefix TUI previews with a colored marker for who wrote the matched message
+    /// (▸ you, ◂ codex)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub role_glyphs: bool,
+
+    /// Show a progress bar in the TUI results title while sessions are indexing
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub progress_bar: bool,
+
+    /// Build an inverted word index as sessions load so each TUI query only scores
+    /// sessions that can match (for very large collections; one-shot searches scan)
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub index: bool,
+
+    /// Keep watching the sessions directories while the TUI is open, adding new
+    /// sessions and reloading changed ones
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub watch: bool,
+
+    /// Print diagnostics (such as the chosen sessions directory) to stderr
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub verbose: bool,
+
+    /// Search the messages of a single session (by UUID) instead of across sessions (no TUI)
+    #[arg(long, value_name = "UUID")]
+    pub in_session: Option<String>,
+
+    /// Parse every session file (ignoring --scan-limit) and report empty or malformed ones
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub validate: bool,
+
+    /// Print the session files that would be scanned, in scan order, without parsing them
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub list_files: bool,
+
+    /// Summarize every session (ignoring --scan-limit): totals, date range, sessions
+    /// per day and the most common cwds; with --json, as one object
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub stats: bool,
+
     /// Run a headless benchmark and emit JSON metrics (no TUI)
     #[arg(long, action = ArgAction::SetTrue)]
     pub bench: bool,
     /// Benchmark iterations per query
     #[arg(long, default_value_t = 5)]
     pub bench_iters: usize,
+
+    /// Delete persisted state (`--clear-state cache`, or all without a value) and report
+    /// what was removed
+    #[arg(
+        long,
+        value_enum,
+        value_name = "KIND",
+        num_args = 0..=1,
+        default_missing_value = "all"
+    )]
+    pub clear_state: Option<StateKind>,
+
+    /// Print where persisted state (the cache) is stored
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub state_paths: bool,
+
+    /// Print a completion script for SHELL and exit, e.g.
+    /// `cdxs --completions zsh > _cdxs`
+    #[arg(long, value_name = "SHELL", hide = true)]
+    pub completions: Option<clap_complete::Shell>,
 }
 
 pub fn run() -> Result<()> {
-    let args = Args::parse();
-    let query = args.query.join(" ").trim().to_owned();
+    let (args, config) = parse_args()?;
+    run_with(args, &config)
+}
+
+fn run_with(args: Args, config: &Config) -> Result<()> {
+    if let Some(shell) = args.completions {
+        print_completions(shell);
+        return Ok(());
+    }
+    let query = resolve_query(&args, std::env::var("CODEX_SEARCH_QUERY").ok());
+
+    if args.state_paths || args.clear_state.is_some() {
+        if !args.query.is_empty() {
+            bail!(
+                "--state-paths and --clear-state take no query terms (got `{}`)",
+                args.query.join(" ")
+            );
+        }
+        let paths = StatePaths::resolve()?;
+        if args.state_paths {
+            state::print_paths(&paths);
+        }
+        if let Some(kind) = args.clear_state {
+            state::clear(&paths, kind)?;
+        }
+        return Ok(());
+    }
+
+    configure_jobs(args.jobs)?;
 
     let mut discovery = DiscoveryOptions::with_defaults()?;
     // Allow env override for scan limit; CLI flag still wins.
-    if let Ok(val) = std::env::var("CODEX_SEARCH_SCAN_LIMIT") {
-        if let Ok(n) = val.parse::<usize>() {
-            discovery.scan_limit = n;
-        }
+    if let Ok(val) = std::env::var("CODEX_SEARCH_SCAN_LIMIT")
+        && let Ok(n) = val.parse::<usize>()
+    {
+        discovery.scan_limit = n;
     }
-    if let Some(dir) = &args.sessions_dir {
-        discovery.root = dir.clone();
+    if !args.sessions_dir.is_empty() {
+        discovery.roots = args.sessions_dir.clone();
+    } else if args.verbose {
+        let standard = discovery::sessions_dir_candidates()?.remove(0);
+        if discovery.roots == [standard.clone()] {
+            eprintln!("using sessions directory {}", discovery.roots_display());
+        } else {
+            eprintln!(
+                "{} not found; using sessions directory {}",
+                standard.display(),
+                discovery.roots_display()
+            );
+        }
     }
     if let Some(limit) = args.scan_limit {
         discovery.scan_limit = limit;
@@ -102,166 +503,868 @@ pub fn run() -> Result<()> {
     if let Some(preview) = args.preview_limit {
         discovery.preview_char_limit = preview;
     }
+    discovery.keep_empty = args.keep_empty;
+    discovery.low_memory = args.low_memory;
+    discovery.include_reasoning = args.include_reasoning;
+    discovery.include_tools = args.include_tools;
+    discovery.max_messages = args.max_messages_per_session;
+    discovery.merge_turns = args.merge_turns;
+    discovery.filename_labels = args.filename_labels;
+    discovery.strict = args.strict;
+    discovery.unicode_normalize = args.unicode_normalize;
+    if args.rebuild_index && args.no_cache {
+        bail!("--rebuild-index cannot be combined with --no-cache");
+    }
+    // Strict mode has to see every file's parse errors, so it never reuses the index.
+    if !args.no_cache && !args.strict {
+        discovery.index_path = Some(StatePaths::resolve()?.cache.join("index.bin"));
+    }
+    discovery.rebuild_index = args.rebuild_index;
+    if let Some(pattern) = &args.filename_regex {
+        discovery.filename_regex = Some(discovery::compile_filename_regex(pattern)?);
+    }
+    if let Some(spec) = &args.restrict_uuids {
+        discovery.restrict_uuids = Some(parse_uuid_list(spec)?);
+    }
+    let exclude = if args.exclude.is_empty() {
+        std::env::var("CODEX_SEARCH_EXCLUDE")
+            .map(|val| {
+                val.split(':')
+                    .filter(|pattern| !pattern.is_empty())
+                    .map(str::to_owned)
+                    .collect()
+            })
+            .unwrap_or_default()
+    } else {
+        args.exclude.clone()
+    };
+    if !exclude.is_empty() {
+        discovery.exclude = Some(discovery::ExcludeGlobs::new(&exclude)?);
+    }
+    if args.include_archive {
+        let archive = args
+            .archive_dir
+            .clone()
+            .or_else(|| std::env::var_os("CODEX_SEARCH_ARCHIVE_DIR").map(PathBuf::from))
+            .unwrap_or_else(|| discovery::default_archive_dir(&discovery.roots[0]));
+        discovery.archive_root = Some(archive);
+    }
+    // --cwd drops most sessions, so widen the scan window once, up front; this is the
+    // effective scan limit for the whole run.
+    if args.cwd {
+        discovery.scan_limit = discovery.scan_limit.max(WIDENED_SCAN_LIMIT);
+    }
+    if args.verbose {
+        eprintln!("scanning up to {} session files", discovery.scan_limit);
+    }
+
+    let mut glyphs = if args.ascii {
+        Glyphs::ascii()
+    } else {
+        Glyphs::detect()
+    };
+    if let Some(ellipsis) = &args.ellipsis {
+        glyphs.ellipsis = ellipsis.clone();
+    }
+    if let Some(separator) = args.separator {
+        glyphs.separator = separator;
+    }
+
+    let mut search = SearchOptions {
+        ellipsis: glyphs.ellipsis.clone(),
+        all_terms: args.all_terms,
+        sort: args.sort,
+        message_recency_weight: args.message_recency_weight,
+        min_score: args.min_score,
+        time_basis: args.time_basis,
+        matcher: args.matcher,
+        unicode_normalize: args.unicode_normalize,
+        role: args.role,
+        first_message: args.first_message,
+        regex: args.regex,
+        ..SearchOptions::default()
+    };
+    if let Ok(val) = std::env::var("CODEX_SEARCH_RECENCY_HALF_LIFE")
+        && let Ok(half_life) = parse_duration(&val)
+    {
+        search.recency_half_life = half_life;
+    }
+    if let Some(half_life) = args.recency_half_life {
+        search.recency_half_life = half_life;
+    }
+
+    if args.regex && !normalize_query(&query).is_empty() {
+        // Fail before scanning rather than silently matching nothing.
+        compile_regex(&query)?;
+    }
+    let root_exists = discovery.any_root_exists();
+    if args.json_meta && !args.json {
+        bail!("--json-meta needs --json");
+    }
+    if args.ndjson && args.json {
+        bail!("--ndjson and --json are mutually exclusive; pick one");
+    }
+    if args.count && (args.json || args.ndjson) {
+        bail!("--count prints a plain number; drop --json/--ndjson");
+    }
+    if args.first_message && matches!(args.role, RoleFilter::Assistant | RoleFilter::Tool) {
+        bail!(
+            "--first-message matches only the opening user message; drop --role or use --role user"
+        );
+    }
+    if args.ndjson && (args.bench || args.stats) {
+        bail!("--bench and --stats print one JSON object; use --json instead of --ndjson");
+    }
+    // A count covers every match, not just the first `--limit`.
+    let limit = if args.count { usize::MAX } else { args.limit };
+    let meta = if args.json_meta {
+        Some(SearchMeta::new(&args, &query, &discovery, &search)?)
+    } else {
+        None
+    };
+    let output = Output {
+        json: args.json,
+        ndjson: args.ndjson,
+        count: args.count,
+        file: args.output_file.clone(),
+        color: args.color.enabled(args.output_file.is_none()),
+        meta,
+        span: args.span,
+    };
+
+    if args.print_config {
+        return print_config(&args, &discovery, &search, &output);
+    }
+
+    if args.validate {
+        return run_validate(&discovery, &output);
+    }
 
-    let root_exists = discovery.root.exists();
+    if args.stats {
+        return run_stats(&discovery, args.cwd, &glyphs, &output);
+    }
+
+    if args.list_files {
+        let paths = discovery::collect_session_paths(&discovery)?;
+        if output.json {
+            return output.write_results(&paths);
+        }
+        if output.ndjson {
+            return output.write_ndjson(&paths);
+        }
+        let mut out = output.writer()?;
+        for path in paths {
+            writeln!(out, "{}", path.display())?;
+        }
+        out.flush()?;
+        return Ok(());
+    }
+
+    if args.raw {
+        if normalize_query(&query).is_empty() {
+            bail!("--raw needs a query");
+        }
+        if args.regex {
+            bail!("--raw matches plain substrings; drop --regex");
+        }
+        let results = discovery::search_raw(&discovery, &query, limit, &search)?;
+        return print_results(results, search.time_basis, &output);
+    }
+
+    if let Some(uuid) = &args.in_session {
+        return run_in_session(&discovery, &search, uuid, &query, limit, &output);
+    }
+
+    if args.merge_cwd || args.stdin {
+        let mut sessions = if args.stdin {
+            discovery::sessions_from_reader(std::io::stdin().lock(), &discovery)?
+        } else {
+            discovery::collect_sessions(&discovery)?
+        };
+        if args.cwd {
+            let cwd = std::env::current_dir().context("reading current directory")?;
+            sessions = filter_sessions_by_cwd(sessions, &cwd);
+        }
+        let results = if args.merge_cwd {
+            search_projects(&sessions, &query, limit, &search)?
+        } else {
+            search_sessions(&sessions, &query, limit, &search)?
+        };
+        return print_results(results, search.time_basis, &output);
+    }
 
-    let wants_tui = !(args.json || args.list || args.no_tui);
+    let resume_template = resume_template(&args);
+
+    let wants_tui = !(args.json
+        || args.ndjson
+        || args.count
+        || args.list
+        || args.no_tui
+        || args.interactive
+        || args.output_file.is_some());
     let is_tty = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
 
     if args.bench || !wants_tui || !is_tty {
-        if wants_tui && !is_tty {
+        if wants_tui && !is_tty && !args.interactive {
             eprintln!(
                 "Interactive TUI disabled: standard streams are not attached to a TTY. Falling back to list output."
             );
         }
-        // When filtering by CWD, widen the scan window so recent matches aren't missed.
-        if args.cwd {
-            discovery.scan_limit = discovery.scan_limit.max(1000);
+        let cwd_filter = if args.cwd {
+            Some(std::env::current_dir().context("reading current directory")?)
+        } else {
+            None
+        };
+        if args.bench {
+            return run_bench(
+                &query,
+                args.bench_iters,
+                args.limit,
+                cwd_filter.as_deref(),
+                &discovery,
+                &search,
+                &output,
+            );
         }
         let mut sessions = if root_exists {
             discovery::collect_sessions(&discovery)?
         } else {
             Vec::new()
         };
-        if args.cwd {
-            let cwd = std::env::current_dir().context("reading current directory")?;
-            sessions = filter_sessions_by_cwd(sessions, &cwd);
+        if let Some(cwd) = &cwd_filter {
+            sessions = filter_sessions_by_cwd(sessions, cwd);
         }
-        if args.bench {
-            return run_bench(&sessions, &query, args.bench_iters, args.limit, &discovery.root, root_exists);
+        if args.interactive {
+            ensure_resume_target_available(args.resume_in)?;
+            let selector = args
+                .selector
+                .clone()
+                .or_else(|| std::env::var("CODEX_SEARCH_SELECTOR").ok())
+                .unwrap_or_else(|| "fzf".to_string());
+            let results = search_sessions(&sessions, &query, args.limit, &search)?;
+            let Some(result) = select_with_command(&selector, &results, search.time_basis)? else {
+                return Ok(());
+            };
+            let selection = Selection::from_result(result);
+            let prompts = resume_prompts(&resume_template);
+            let values = read_prompt_values(&prompts)?;
+            let template = fill_resume_prompts(&resume_template, &prompts, &values);
+            return resume_session(&template, &selection, args.resume_in, args.dry_run);
         }
         // Keep a copy of cwd filter for potential auto-expand
-        let cwd_opt = if args.cwd { Some(std::env::current_dir()?) } else { None };
+        let cwd_opt = if args.cwd {
+            Some(std::env::current_dir()?)
+        } else {
+            None
+        };
         run_cli_mode(
             &sessions,
             &query,
-            args.limit,
-            args.json,
-            &discovery.root,
-            root_exists,
+            limit,
+            &discovery,
+            &search,
             cwd_opt.as_deref(),
+            &output,
         )?;
         return Ok(());
     }
 
-    // TUI path: widen the scan window when filtering by CWD so older matching
-    // sessions don’t disappear as the query gets more specific.
-    if args.cwd {
-        discovery.scan_limit = discovery.scan_limit.max(1000);
-    }
     let session_paths = if root_exists {
         discovery::collect_session_paths(&discovery)?
     } else {
         Vec::new()
     };
 
-    let empty_status = (!root_exists)
-        .then(|| {
-            format!(
-                "Sessions directory {} does not exist",
-                discovery.root.display()
-            )
-        })
-        .or_else(|| {
-            if session_paths.is_empty() {
-                Some(format!(
+    let empty_status = session_paths.is_empty().then(|| {
+        let missing = missing_roots(&discovery);
+        match (root_exists, missing) {
+            (false, Some(missing)) => format!("Sessions {missing}"),
+            (_, missing) => {
+                let found = format!(
                     "No Codex sessions found under {}",
-                    discovery.root.display()
-                ))
-            } else {
-                None
+                    discovery.roots_display()
+                );
+                match missing {
+                    Some(missing) => format!("{found}; {missing}"),
+                    None => found,
+                }
             }
-        });
+        }
+    });
 
-    let stream = discovery::stream_sessions(session_paths, discovery.preview_char_limit);
-    let resume_template = if args.resume_command.is_empty() {
-        std::env::var("CODEX_SEARCH_RESUME")
-            .unwrap_or_else(|_| "codex --search resume {uuid}".to_string())
-    } else {
-        args.resume_command
-    };
+    let keys = KeyMap::new(&config.keys)?;
+    let stream = discovery::stream_sessions(session_paths, discovery.clone());
+    ensure_resume_target_available(args.resume_in)?;
 
     tui::run(
         TuiConfig {
             limit: args.limit,
             resume_command: resume_template,
+            resume_in: args.resume_in,
             dry_run: args.dry_run,
             initial_query: query,
             empty_status,
             total_expected: stream.total,
-            filter_cwd: if args.cwd { Some(std::env::current_dir()?) } else { None },
+            filter_cwd: if args.cwd {
+                Some(std::env::current_dir()?)
+            } else {
+                None
+            },
+            search,
+            glyphs,
+            browse: args.browse,
+            role_glyphs: args.role_glyphs,
+            progress_bar: args.progress_bar,
+            index: args.index,
+            watch: args.watch,
+            keys,
+            discovery,
+            export_path: args.export_path,
+            theme: if args.high_contrast
+                || std::env::var("CODEX_SEARCH_HIGH_CONTRAST")
+                    .is_ok_and(|v| !v.is_empty() && v != "0")
+            {
+                Theme::high_contrast()
+            } else {
+                Theme::standard()
+            },
         },
         stream,
     )
 }
 
-fn run_cli_mode(
-    sessions: &[Session],
-    query: &str,
-    limit: usize,
-    json: bool,
-    sessions_root: &Path,
-    root_exists: bool,
-    cwd_filter: Option<&Path>,
-) -> Result<()> {
-    if sessions.is_empty() {
-        // Try expanded scan before giving up when searching
-        if !json && root_exists && !query.trim().is_empty() {
-            let mut discovery = DiscoveryOptions::with_defaults()?;
-            discovery.root = sessions_root.to_path_buf();
-            discovery.scan_limit = 1000;
-            let mut expanded = discovery::collect_sessions(&discovery)?;
-            if let Some(cwd) = cwd_filter {
-                expanded = filter_sessions_by_cwd(expanded, cwd);
-            }
-            if !expanded.is_empty() {
-                return run_cli_mode(&expanded, query, limit, json, sessions_root, root_exists, cwd_filter);
-            }
-        }
-        if json {
-            println!("[]");
-        } else {
-            let hint = if root_exists {
-                format!("no sessions discovered under {}", sessions_root.display())
-            } else {
-                format!(
-                    "sessions directory {} does not exist",
-                    sessions_root.display()
-                )
-            };
-            eprintln!("{hint}");
-        }
-        return Ok(());
+/// The query from the positional terms, else `env_query` (`CODEX_SEARCH_QUERY`, so
+/// wrapper scripts can avoid quoting); positional terms always win.
+fn resolve_query(args: &Args, env_query: Option<String>) -> String {
+    let query = args.query.join(" ");
+    if !query.trim().is_empty() {
+        return query.trim().to_owned();
     }
+    env_query.unwrap_or_default().trim().to_owned()
+}
 
-    let mut results = search_sessions(sessions, query, limit)?;
-    if !json && results.is_empty() && !query.trim().is_empty() && root_exists {
-        // Auto-expand scan window and retry once
-        let mut discovery = DiscoveryOptions::with_defaults()?;
-        discovery.root = sessions_root.to_path_buf();
-        discovery.scan_limit = 1000; // wider window for narrow queries
-        let mut expanded = discovery::collect_sessions(&discovery)?;
-        if let Some(cwd) = cwd_filter {
-            expanded = filter_sessions_by_cwd(expanded, cwd);
-        }
-        results = search_sessions(&expanded, query, limit)?;
-    }
-    if json {
-        serde_json::to_writer_pretty(std::io::stdout(), &results)
-            .context("failed to serialize results")?;
-        println!();
-        return Ok(());
+/// Writes the completion script to stdout, registered for the name the binary was
+/// invoked as (`cdxs` or the `codex-search` alias).
+fn print_completions(shell: clap_complete::Shell) {
+    let bin_name = std::env::args_os()
+        .next()
+        .and_then(|arg0| {
+            Path::new(&arg0)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "cdxs".into());
+    clap_complete::generate(
+        shell,
+        &mut Args::command(),
+        bin_name,
+        &mut std::io::stdout(),
+    );
+}
+
+/// Parses the command line, expanding `--search NAME` from the config when given and
+/// filling unset flags from the config's `[defaults]`.
+fn parse_args() -> Result<(Args, Config)> {
+    let mut matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    let config = Config::load(args.config.as_deref())?;
+    if let Some(name) = &args.search {
+        let saved = config.saved_search(name)?;
+        let argv = expand_saved_search(
+            std::env::args_os().collect(),
+            &saved,
+            !args.query.is_empty(),
+        )
+        .with_context(|| format!("in saved search {name:?}"))?;
+        matches = Args::command().get_matches_from(argv);
+        args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
     }
+    apply_config_defaults(&mut args, &matches, &config.defaults)?;
+    Ok((args, config))
+}
 
-    let now = OffsetDateTime::now_utc();
-    for result in results {
-        let session = &result.session;
-        let matched_message = &result.matched_message;
-        let snippet = &result.snippet;
+/// Fills the flags the command line (or saved search) left unset from the config's
+/// `[defaults]`, except where the flag's env var is set: command line > env var >
+/// config file > built-in default.
+fn apply_config_defaults(args: &mut Args, matches: &ArgMatches, defaults: &Defaults) -> Result<()> {
+    let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+    let no_env = |name: &str| std::env::var_os(name).is_none();
+    if unset("sessions_dir") && !defaults.sessions_dir.is_empty() {
+        args.sessions_dir = defaults
+            .sessions_dir
+            .iter()
+            .map(|dir| PathBuf::from(discovery::expand_tilde(dir)))
+            .collect();
+    }
+    if unset("resume_command")
+        && no_env("CODEX_SEARCH_RESUME")
+        && let Some(command) = &defaults.resume_command
+    {
+        args.resume_command = command.clone();
+    }
+    if unset("limit")
+        && let Some(limit) = defaults.limit
+    {
+        args.limit = limit;
+    }
+    if unset("scan_limit") && no_env("CODEX_SEARCH_SCAN_LIMIT") && defaults.scan_limit.is_some() {
+        args.scan_limit = defaults.scan_limit;
+    }
+    if unset("recency_half_life")
+        && no_env("CODEX_SEARCH_RECENCY_HALF_LIFE")
+        && let Some(raw) = &defaults.recency_half_life
+    {
+        args.recency_half_life =
+            Some(parse_duration(raw).context("recency-half-life in the config's [defaults]")?);
+    }
+    if unset("message_recency_weight")
+        && let Some(weight) = defaults.message_recency_weight
+    {
+        args.message_recency_weight = weight;
+    }
+    if unset("jobs") && no_env("CODEX_SEARCH_JOBS") && defaults.jobs.is_some() {
+        args.jobs = defaults.jobs;
+    }
+    Ok(())
+}
 
-        // Use the match anchor time (matched message -> latest message -> file mtime)
-        let anchor = result.match_timestamp();
-        let updated = format_timestamp(anchor);
+/// `--resume-command`, else `CODEX_SEARCH_RESUME`, else the Codex CLI.
+fn resume_template(args: &Args) -> String {
+    if args.resume_command.is_empty() {
+        std::env::var("CODEX_SEARCH_RESUME")
+            .unwrap_or_else(|_| "codex --search resume {uuid}".to_string())
+    } else {
+        args.resume_command.clone()
+    }
+}
+
+/// `--print-config`: the settings in effect, as a `[defaults]` table that can be
+/// pasted into the config file.
+fn print_config(
+    args: &Args,
+    discovery: &DiscoveryOptions,
+    search: &SearchOptions,
+    output: &Output,
+) -> Result<()> {
+    let path = Config::path(args.config.as_deref())?;
+    let status = if path.exists() { "" } else { " (not found)" };
+    let effective = Defaults {
+        sessions_dir: discovery
+            .roots
+            .iter()
+            .map(|root| root.to_string_lossy().into_owned())
+            .collect(),
+        resume_command: Some(resume_template(args)),
+        limit: Some(args.limit),
+        scan_limit: Some(discovery.scan_limit),
+        recency_half_life: Some(
+            humantime::format_duration(search.recency_half_life.unsigned_abs()).to_string(),
+        ),
+        message_recency_weight: Some(search.message_recency_weight),
+        jobs: Some(jobs(args.jobs).unwrap_or(0)),
+    };
+    #[derive(Serialize)]
+    struct Effective<'a> {
+        defaults: &'a Defaults,
+    }
+    let toml = toml::to_string(&Effective {
+        defaults: &effective,
+    })
+    .context("formatting the effective settings")?;
+    let mut out = output.writer()?;
+    writeln!(out, "# config file: {}{status}", path.display())?;
+    write!(out, "{toml}")?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Splices a saved search into `argv`: its flags go first so the command line overrides
+/// them, and its query terms are appended only when the command line has none.
+fn expand_saved_search(
+    argv: Vec<OsString>,
+    saved: &[String],
+    has_query: bool,
+) -> Result<Vec<OsString>> {
+    let (flags, query) = split_saved_search(saved);
+    // Surface bad flags or values as errors about the saved search itself.
+    Args::try_parse_from(
+        std::iter::once("cdxs")
+            .chain(flags.iter().map(String::as_str))
+            .chain(query.iter().map(String::as_str)),
+    )?;
+    let mut argv = argv.into_iter();
+    let mut expanded: Vec<OsString> = argv.next().into_iter().collect();
+    expanded.extend(flags.into_iter().map(OsString::from));
+    expanded.extend(argv);
+    if !has_query {
+        expanded.extend(query.into_iter().map(OsString::from));
+    }
+    Ok(expanded)
+}
+
+/// Separates a saved search into flags (with their values) and query terms, so terms may
+/// appear anywhere in the entry (e.g. `rust --cwd`) despite the trailing query argument.
+fn split_saved_search(saved: &[String]) -> (Vec<String>, Vec<String>) {
+    let command = Args::command();
+    let takes_value = |token: &str| {
+        command
+            .get_arguments()
+            .find(|arg| match token.strip_prefix("--") {
+                Some(long) => arg.get_long() == Some(long),
+                None => token.len() == 2 && arg.get_short() == token.chars().nth(1),
+            })
+            .is_some_and(|arg| arg.get_action().takes_values())
+    };
+    let (mut flags, mut query) = (Vec::new(), Vec::new());
+    let mut tokens = saved.iter();
+    while let Some(token) = tokens.next() {
+        if token == "--" {
+            query.extend(tokens.by_ref().cloned());
+        } else if token.len() > 1 && token.starts_with('-') {
+            flags.push(token.clone());
+            if !token.contains('=')
+                && takes_value(token)
+                && let Some(value) = tokens.next()
+            {
+                flags.push(value.clone());
+            }
+        } else {
+            query.push(token.clone());
+        }
+    }
+    (flags, query)
+}
+
+fn parse_duration(raw: &str) -> Result<time::Duration> {
+    let std_duration = humantime::parse_duration(raw.trim())
+        .with_context(|| format!("invalid duration {raw:?}"))?;
+    time::Duration::try_from(std_duration).context("duration out of range")
+}
+
+/// Reads `--restrict-uuids`: a file path when one exists, otherwise a comma-separated list.
+/// Files may hold `--interactive`-style lines; only the leading token of each line is used.
+fn parse_uuid_list(spec: &str) -> Result<HashSet<String>> {
+    let path = Path::new(spec);
+    let uuids: HashSet<String> = if path.is_file() {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read uuid list {}", path.display()))?
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .map(|uuid| uuid.to_lowercase())
+            .collect()
+    } else {
+        spec.split(',')
+            .map(|uuid| uuid.trim().to_lowercase())
+            .filter(|uuid| !uuid.is_empty())
+            .collect()
+    };
+    if uuids.is_empty() {
+        bail!("--restrict-uuids {spec:?} contains no uuids");
+    }
+    Ok(uuids)
+}
+
+/// A narrow query may only match older sessions, so text output retries once with a
+/// wider window, unless the window is already that wide (e.g. under --cwd).
+fn can_widen(discovery: &DiscoveryOptions, query: &str, output: &Output) -> bool {
+    !(output.json || output.ndjson || output.count)
+        && discovery.any_root_exists()
+        && !query.trim().is_empty()
+        && discovery.scan_limit < WIDENED_SCAN_LIMIT
+}
+
+fn run_cli_mode(
+    sessions: &[Session],
+    query: &str,
+    limit: usize,
+    discovery: &DiscoveryOptions,
+    search: &SearchOptions,
+    cwd_filter: Option<&Path>,
+    output: &Output,
+) -> Result<()> {
+    let root_exists = discovery.any_root_exists();
+    let can_widen = can_widen(discovery, query, output);
+    let widened = DiscoveryOptions {
+        scan_limit: WIDENED_SCAN_LIMIT,
+        ..discovery.clone()
+    };
+    let load_widened = || -> Result<Vec<Session>> {
+        let mut expanded = discovery::collect_sessions(&widened)?;
+        if let Some(cwd) = cwd_filter {
+            expanded = filter_sessions_by_cwd(expanded, cwd);
+        }
+        Ok(expanded)
+    };
+
+    if sessions.is_empty() {
+        if can_widen {
+            let expanded = load_widened()?;
+            if !expanded.is_empty() {
+                return run_cli_mode(
+                    &expanded, query, limit, &widened, search, cwd_filter, output,
+                );
+            }
+        }
+        if output.json {
+            writeln!(output.writer()?, "[]")?;
+        } else if output.count {
+            output.write_count(0)?;
+        } else if !output.ndjson {
+            let hint = match missing_roots(discovery) {
+                Some(missing) if !root_exists => format!("sessions {missing}"),
+                missing => {
+                    let found =
+                        format!("no sessions discovered under {}", discovery.roots_display());
+                    match missing {
+                        Some(missing) => format!("{found}; {missing}"),
+                        None => found,
+                    }
+                }
+            };
+            eprintln!("{hint}");
+        }
+        return Ok(());
+    }
+
+    let mut results = search_sessions(sessions, query, limit, search)?;
+    if can_widen && results.is_empty() {
+        results = search_sessions(&load_widened()?, query, limit, search)?;
+    }
+    if discovery.low_memory {
+        let mut scorer = Scorer::with_options(query, search.clone());
+        results = discovery::rehydrate_results(results, &mut scorer, discovery);
+    }
+    print_results(results, search.time_basis, output)
+}
+
+/// `directory X does not exist` / `directories X, Y do not exist` for the roots
+/// that are missing, or `None` when all exist.
+fn missing_roots(discovery: &DiscoveryOptions) -> Option<String> {
+    match discovery.missing_roots().as_slice() {
+        [] => None,
+        [root] => Some(format!("directory {} does not exist", root.display())),
+        missing => Some(format!(
+            "directories {} do not exist",
+            discovery::display_paths(missing.iter().copied())
+        )),
+    }
+}
+
+/// Where non-interactive modes write: stdout, or `--output-file`.
+struct Output {
+    json: bool,
+    /// `--ndjson`: one compact JSON result per line, flushed as it is written.
+    ndjson: bool,
+    /// `--count`: only the number of results is printed.
+    count: bool,
+    file: Option<PathBuf>,
+    /// List lines may carry terminal escapes (`--color`).
+    color: bool,
+    /// Set by `--json-meta`: search results are wrapped with it.
+    meta: Option<SearchMeta>,
+    /// `--span`: list lines end with the session's message time span.
+    span: bool,
+}
+
+/// What produced a result set, so archived `--json-meta` output can be reproduced.
+#[derive(Debug, Serialize)]
+struct SearchMeta {
+    version: &'static str,
+    generated_at: String,
+    query: String,
+    limit: usize,
+    sort: String,
+    matcher: String,
+    role: String,
+    first_message: bool,
+    time_basis: String,
+    all_terms: bool,
+    regex: bool,
+    /// Working directory the results were scoped to (`--cwd`).
+    cwd: Option<PathBuf>,
+    in_session: Option<String>,
+    raw: bool,
+    merge_cwd: bool,
+    /// Sessions were read from stdin, so `sessions_roots` were not scanned.
+    stdin: bool,
+    include_reasoning: bool,
+    include_tools: bool,
+    unicode_normalize: bool,
+    /// The first of `sessions_roots`, as written before several roots were allowed.
+    sessions_root: PathBuf,
+    sessions_roots: Vec<PathBuf>,
+    archive_root: Option<PathBuf>,
+    scan_limit: usize,
+}
+
+impl SearchMeta {
+    fn new(
+        args: &Args,
+        query: &str,
+        discovery: &DiscoveryOptions,
+        search: &SearchOptions,
+    ) -> Result<Self> {
+        let cwd = if args.cwd {
+            Some(std::env::current_dir().context("reading current directory")?)
+        } else {
+            None
+        };
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION"),
+            generated_at: OffsetDateTime::now_utc()
+                .format(&time::format_description::well_known::Rfc3339)
+                .context("formatting the current time")?,
+            query: normalize_query(query),
+            limit: args.limit,
+            sort: value_name(search.sort),
+            matcher: value_name(search.matcher),
+            role: value_name(search.role),
+            first_message: search.first_message,
+            time_basis: value_name(search.time_basis),
+            all_terms: search.all_terms,
+            regex: search.regex,
+            cwd,
+            in_session: args.in_session.clone(),
+            raw: args.raw,
+            merge_cwd: args.merge_cwd,
+            stdin: args.stdin,
+            include_reasoning: discovery.include_reasoning,
+            include_tools: discovery.include_tools,
+            unicode_normalize: discovery.unicode_normalize,
+            sessions_root: discovery.roots[0].clone(),
+            sessions_roots: discovery.roots.clone(),
+            archive_root: discovery.archive_root.clone(),
+            scan_limit: discovery.scan_limit,
+        })
+    }
+}
+
+/// The command-line spelling of a `ValueEnum` value (e.g. `recent`).
+fn value_name<T: ValueEnum>(value: T) -> String {
+    value
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+impl Output {
+    fn writer(&self) -> Result<Box<dyn Write>> {
+        Ok(match &self.file {
+            Some(path) => Box::new(std::io::BufWriter::new(
+                std::fs::File::create(path)
+                    .with_context(|| format!("creating {}", path.display()))?,
+            )),
+            None => Box::new(std::io::stdout().lock()),
+        })
+    }
+
+    /// Writes `value` as pretty JSON followed by a newline.
+    fn write_json<T: serde::Serialize>(&self, value: &T) -> Result<()> {
+        let mut out = self.writer()?;
+        serde_json::to_writer_pretty(&mut out, value).context("failed to serialize results")?;
+        writeln!(out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Writes search results as JSON, wrapped with `meta` under `--json-meta`.
+    fn write_results<T: serde::Serialize>(&self, results: &T) -> Result<()> {
+        match &self.meta {
+            Some(meta) => {
+                #[derive(Serialize)]
+                struct WithMeta<'a, T> {
+                    meta: &'a SearchMeta,
+                    results: &'a T,
+                }
+                self.write_json(&WithMeta { meta, results })
+            }
+            None => self.write_json(results),
+        }
+    }
+
+    fn write_count(&self, count: usize) -> Result<()> {
+        let mut out = self.writer()?;
+        writeln!(out, "{count}")?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// `text` dimmed when color is on; plain otherwise.
+    fn dim(&self, text: &str) -> String {
+        if self.color {
+            format!("\x1b[2m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Writes each of `items` as compact JSON on its own line, flushing per line so a
+    /// consumer sees every complete line as soon as it is written.
+    fn write_ndjson<T: serde::Serialize>(&self, items: &[T]) -> Result<()> {
+        let mut out = self.writer()?;
+        for item in items {
+            write_ndjson_line(&mut out, item)?;
+        }
+        Ok(())
+    }
+
+    /// Snippet line for list output; highlights only when color is on.
+    fn snippet_line(&self, snippet: &crate::session::Snippet) -> String {
+        snippet_to_cli_line(snippet, self.color)
+    }
+}
+
+fn write_ndjson_line<T: serde::Serialize>(out: &mut dyn Write, item: &T) -> Result<()> {
+    serde_json::to_writer(&mut *out, item).context("failed to serialize results")?;
+    writeln!(out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Sizes rayon's global pool, which discovery and scanning run on.
+fn configure_jobs(flag: Option<usize>) -> Result<()> {
+    if let Some(jobs) = jobs(flag) {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+            .context("failed to size the thread pool")?;
+    }
+    Ok(())
+}
+
+/// `--jobs` (or its config default), else `CODEX_SEARCH_JOBS`; `None` or 0 means all
+/// cores.
+fn jobs(flag: Option<usize>) -> Option<usize> {
+    flag.or_else(|| {
+        std::env::var("CODEX_SEARCH_JOBS")
+            .ok()
+            .and_then(|val| val.parse::<usize>().ok())
+    })
+}
+
+/// Prints results as pretty JSON, NDJSON, a count, or as the tab-separated list with
+/// snippet lines.
+fn print_results(results: Vec<SearchResult>, basis: TimeBasis, output: &Output) -> Result<()> {
+    if output.json {
+        return output.write_results(&results);
+    }
+    if output.ndjson {
+        return output.write_ndjson(&results);
+    }
+    if output.count {
+        return output.write_count(results.len());
+    }
+
+    let mut out = output.writer()?;
+    let now = OffsetDateTime::now_utc();
+    for result in results {
+        let session = &result.session;
+        let matched_message = &result.matched_message;
+        let snippet = &result.snippet;
+
+        // Match anchor by default (matched message -> latest message -> file mtime)
+        let anchor = result.timestamp(basis);
+        let updated = format_timestamp(anchor);
         let relative = format_relative(anchor, now);
         let msg_time = format_time_of_day(anchor);
         let label = &session.label;
@@ -270,27 +1373,572 @@ fn run_cli_mode(
             .map(|m| match m.role {
                 crate::session::MessageRole::User => "you",
                 crate::session::MessageRole::Assistant => "codex",
+                crate::session::MessageRole::Tool => "tool",
             })
             .unwrap_or("session");
 
-        println!(
-            "{uuid}\t{updated}\t{relative}\t{msg_time}\t{label} ({role})",
+        let model = match &session.model {
+            Some(model) => format!(" {}", output.dim(model)),
+            None => String::new(),
+        };
+        let archived = if session.archived { " [archived]" } else { "" };
+        let span = if output.span {
+            format!("\t{}", message_span(session))
+        } else {
+            String::new()
+        };
+        writeln!(
+            out,
+            "{uuid}\t{updated}\t{relative}\t{msg_time}\t{label}{model} ({role}){archived}{span}",
             uuid = session.uuid,
             updated = updated,
             relative = relative,
             msg_time = msg_time,
             label = label,
             role = role,
+        )?;
+        writeln!(out, "    {}", output.snippet_line(snippet))?;
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+/// `2025-01-01 09:00 → 11:30 (2h 30m)`; the end shows only the time when it falls on
+/// the same day. `-` when no message carries a timestamp.
+fn message_span(session: &crate::session::Session) -> String {
+    let (Some(first), Some(last)) = (session.earliest_message_time, session.latest_message_time)
+    else {
+        return "-".to_string();
+    };
+    let end = if local_date(first) == local_date(last) {
+        format_time_of_day(last)
+    } else {
+        format_timestamp(last)
+    };
+    format!(
+        "{} → {} ({})",
+        format_timestamp(first),
+        end,
+        format_duration(last - first)
+    )
+}
+
+fn run_validate(discovery: &DiscoveryOptions, output: &Output) -> Result<()> {
+    if !discovery.any_root_exists()
+        && let Some(missing) = missing_roots(discovery)
+    {
+        bail!("sessions {missing}");
+    }
+    let reports = discovery::validate_sessions(discovery)?;
+    let failed = reports
+        .iter()
+        .filter(|r| matches!(r.status, FileStatus::Error { .. }))
+        .count();
+
+    if output.json {
+        output.write_json(&reports)?;
+    } else if output.ndjson {
+        output.write_ndjson(&reports)?;
+    } else {
+        let mut out = output.writer()?;
+        for report in &reports {
+            let status = match &report.status {
+                FileStatus::Ok { messages } => format!("ok\t{messages} messages"),
+                FileStatus::Empty => "empty\t-".to_string(),
+                FileStatus::Error { reason } => format!("error\t{reason}"),
+            };
+            writeln!(out, "{status}\t{}", report.path.display())?;
+        }
+        out.flush()?;
+        let empty = reports
+            .iter()
+            .filter(|r| matches!(r.status, FileStatus::Empty))
+            .count();
+        eprintln!(
+            "{} files checked: {} ok, {empty} empty, {failed} failed",
+            reports.len(),
+            reports.len() - empty - failed
         );
-        let snippet_line = snippet_to_cli_line(snippet);
-        println!("    {}", snippet_line);
     }
 
+    if failed > 0 {
+        let noun = if failed == 1 { "file" } else { "files" };
+        bail!("{failed} session {noun} failed to parse");
+    }
     Ok(())
 }
 
-pub fn spawn_resume_command(command_template: &str, uuid: &str) -> Result<()> {
-    let command = command_template.replace("{uuid}", uuid);
+/// Number of cwds listed by `--stats`.
+const STATS_TOP_CWDS: usize = 10;
+
+/// `--stats` summary of the loaded sessions.
+#[derive(Debug, Serialize)]
+struct CorpusStats {
+    sessions: usize,
+    messages: usize,
+    /// Earliest and latest session day times (`created_at`, else `updated_at`).
+    first: Option<OffsetDateTime>,
+    last: Option<OffsetDateTime>,
+    /// Oldest day first.
+    per_day: Vec<DayStats>,
+    /// Most common cwds, most sessions first.
+    top_cwds: Vec<CwdStats>,
+    sessions_without_cwd: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct DayStats {
+    day: String,
+    sessions: usize,
+    messages: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct CwdStats {
+    cwd: PathBuf,
+    sessions: usize,
+}
+
+impl CorpusStats {
+    fn from_sessions(sessions: &[Session]) -> Self {
+        let mut days: std::collections::BTreeMap<time::Date, DayStats> = Default::default();
+        let mut cwds: std::collections::HashMap<&Path, usize> = Default::default();
+        let mut stats = CorpusStats {
+            sessions: sessions.len(),
+            messages: 0,
+            first: None,
+            last: None,
+            per_day: Vec::new(),
+            top_cwds: Vec::new(),
+            sessions_without_cwd: 0,
+        };
+        for session in sessions {
+            let messages = session.messages.len();
+            stats.messages += messages;
+            let at = session.created_at.unwrap_or(session.updated_at);
+            stats.first = Some(stats.first.map_or(at, |first| first.min(at)));
+            stats.last = Some(stats.last.map_or(at, |last| last.max(at)));
+            let date = local_date(at);
+            let day = days.entry(date).or_insert_with(|| DayStats {
+                day: date.to_string(),
+                sessions: 0,
+                messages: 0,
+            });
+            day.sessions += 1;
+            day.messages += messages;
+            match session.cwd.as_deref() {
+                Some(cwd) => *cwds.entry(cwd).or_default() += 1,
+                None => stats.sessions_without_cwd += 1,
+            }
+        }
+        stats.per_day = days.into_values().collect();
+        let mut cwds: Vec<(&Path, usize)> = cwds.into_iter().collect();
+        cwds.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        stats.top_cwds = cwds
+            .into_iter()
+            .take(STATS_TOP_CWDS)
+            .map(|(cwd, sessions)| CwdStats {
+                cwd: cwd.to_path_buf(),
+                sessions,
+            })
+            .collect();
+        stats
+    }
+
+    fn write_text(&self, out: &mut dyn Write, glyphs: &Glyphs) -> std::io::Result<()> {
+        writeln!(out, "sessions\t{}", self.sessions)?;
+        writeln!(out, "messages\t{}", self.messages)?;
+        if let (Some(first), Some(last)) = (self.first, self.last) {
+            writeln!(
+                out,
+                "range\t{} {} {} ({})",
+                format_timestamp(first),
+                glyphs.arrow,
+                format_timestamp(last),
+                format_duration(last - first)
+            )?;
+        }
+        if !self.per_day.is_empty() {
+            writeln!(out, "\nper day")?;
+            for day in &self.per_day {
+                writeln!(
+                    out,
+                    "{}\t{} sessions\t{} messages",
+                    day.day, day.sessions, day.messages
+                )?;
+            }
+        }
+        if !self.top_cwds.is_empty() || self.sessions_without_cwd > 0 {
+            writeln!(out, "\ntop cwds")?;
+            for cwd in &self.top_cwds {
+                writeln!(out, "{}\t{}", cwd.sessions, cwd.cwd.display())?;
+            }
+            if self.sessions_without_cwd > 0 {
+                writeln!(out, "{}\t(no cwd)", self.sessions_without_cwd)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn run_stats(
+    discovery: &DiscoveryOptions,
+    cwd_only: bool,
+    glyphs: &Glyphs,
+    output: &Output,
+) -> Result<()> {
+    if !discovery.any_root_exists()
+        && let Some(missing) = missing_roots(discovery)
+    {
+        bail!("sessions {missing}");
+    }
+    let unlimited = DiscoveryOptions {
+        scan_limit: usize::MAX,
+        ..discovery.clone()
+    };
+    let mut sessions = discovery::collect_sessions(&unlimited)?;
+    if cwd_only {
+        let cwd = std::env::current_dir().context("reading current directory")?;
+        sessions = filter_sessions_by_cwd(sessions, &cwd);
+    }
+    let stats = CorpusStats::from_sessions(&sessions);
+    if output.json {
+        return output.write_json(&stats);
+    }
+    let mut out = output.writer()?;
+    stats.write_text(&mut out, glyphs)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn run_in_session(
+    discovery: &DiscoveryOptions,
+    search: &SearchOptions,
+    uuid: &str,
+    query: &str,
+    limit: usize,
+    output: &Output,
+) -> Result<()> {
+    let path = discovery::find_session_path(discovery, uuid)?.ok_or_else(|| {
+        anyhow!(
+            "no session with uuid {uuid} under {}",
+            discovery.roots_display()
+        )
+    })?;
+    let matches = match discovery::load_session_from_path(path, discovery)? {
+        Some(session) => search_messages(&session, query, limit, search),
+        None => Vec::new(),
+    };
+
+    if output.json {
+        return output.write_results(&matches);
+    }
+    if output.ndjson {
+        return output.write_ndjson(&matches);
+    }
+    if output.count {
+        return output.write_count(matches.len());
+    }
+
+    if matches.is_empty() {
+        eprintln!("no matching messages in session {uuid}");
+        return Ok(());
+    }
+
+    let mut out = output.writer()?;
+    for m in matches {
+        let when = m
+            .timestamp
+            .map(format_timestamp)
+            .unwrap_or_else(|| "-".to_string());
+        let role = match m.role {
+            crate::session::MessageRole::User => "you",
+            crate::session::MessageRole::Assistant => "codex",
+            crate::session::MessageRole::Tool => "tool",
+        };
+        writeln!(out, "#{index}\t{when}\t{role}", index = m.index)?;
+        writeln!(out, "    {}", output.snippet_line(&m.snippet))?;
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+static PROMPT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{prompt:([^}=]+)(?:=([^}]*))?\}").expect("valid prompt regex"));
+
+/// A `{prompt:label}` or `{prompt:label=default}` placeholder in a resume template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResumePrompt {
+    placeholder: String,
+    pub label: String,
+    pub default: String,
+}
+
+/// Prompt placeholders in `template`, in order of first appearance; repeats of the
+/// same placeholder are asked once.
+pub fn resume_prompts(template: &str) -> Vec<ResumePrompt> {
+    let mut prompts: Vec<ResumePrompt> = Vec::new();
+    for caps in PROMPT_RE.captures_iter(template) {
+        let placeholder = caps[0].to_string();
+        if prompts.iter().any(|p| p.placeholder == placeholder) {
+            continue;
+        }
+        prompts.push(ResumePrompt {
+            placeholder,
+            label: caps[1].trim().to_string(),
+            default: caps.get(2).map_or("", |m| m.as_str()).to_string(),
+        });
+    }
+    prompts
+}
+
+/// Substitutes the answers for `prompts`; empty or missing answers use the default.
+pub fn fill_resume_prompts(template: &str, prompts: &[ResumePrompt], values: &[String]) -> String {
+    let mut filled = template.to_string();
+    for (index, prompt) in prompts.iter().enumerate() {
+        let value = values
+            .get(index)
+            .filter(|value| !value.is_empty())
+            .unwrap_or(&prompt.default);
+        filled = filled.replace(&prompt.placeholder, value);
+    }
+    filled
+}
+
+/// Asks for each prompt on stderr and reads the answers from stdin (non-TUI selection).
+fn read_prompt_values(prompts: &[ResumePrompt]) -> Result<Vec<String>> {
+    let mut values = Vec::with_capacity(prompts.len());
+    for prompt in prompts {
+        if prompt.default.is_empty() {
+            eprint!("{}: ", prompt.label);
+        } else {
+            eprint!("{} [{}]: ", prompt.label, prompt.default);
+        }
+        std::io::stderr().flush()?;
+        let mut line = String::new();
+        std::io::stdin()
+            .read_line(&mut line)
+            .context("failed to read prompt answer")?;
+        values.push(line.trim().to_string());
+    }
+    Ok(values)
+}
+
+/// The session picked for resuming and the message that matched in it.
+#[derive(Debug, Clone)]
+pub struct Selection {
+    pub uuid: String,
+    /// Session file, for `{path}`.
+    pub path: PathBuf,
+    /// Session label, for `{label}`.
+    pub label: String,
+    pub matched_message: Option<Message>,
+}
+
+impl Selection {
+    pub fn from_result(result: &SearchResult) -> Self {
+        Self {
+            uuid: result.session.uuid.clone(),
+            path: result.session.path.clone(),
+            label: result.session.label.clone(),
+            matched_message: result.matched_message.clone(),
+        }
+    }
+}
+
+/// Substitutes `{uuid}`, `{path}`, `{label}`, `{match_role}` and `{match}` and wraps
+/// the command for the requested resume target. Path, label and match are quoted as
+/// one word each; `{match}` goes last so placeholders inside the message text are
+/// left alone.
+pub fn render_resume_command(
+    command_template: &str,
+    selection: &Selection,
+    target: ResumeTarget,
+) -> Result<String> {
+    let message = selection.matched_message.as_ref();
+    let role = message.map_or("", |m| match m.role {
+        crate::session::MessageRole::User => "user",
+        crate::session::MessageRole::Assistant => "assistant",
+        crate::session::MessageRole::Tool => "tool",
+    });
+    let matched = message.map_or(String::new(), |m| {
+        shell_words::quote(&m.full_text).into_owned()
+    });
+    let command = command_template
+        .replace("{uuid}", &selection.uuid)
+        .replace(
+            "{path}",
+            &shell_words::quote(&selection.path.to_string_lossy()),
+        )
+        .replace("{label}", &shell_words::quote(&selection.label))
+        .replace("{match_role}", role)
+        .replace("{match}", &matched);
+    if target == ResumeTarget::Current {
+        return Ok(command);
+    }
+    ensure_resume_target_available(target)?;
+    let cwd = std::env::current_dir().context("reading current directory")?;
+    Ok(wrap_in_tmux(&command, target, &cwd))
+}
+
+/// `command` as a tmux invocation opening it in a new window or pane at `cwd`.
+fn wrap_in_tmux(command: &str, target: ResumeTarget, cwd: &Path) -> String {
+    let subcommand = match target {
+        ResumeTarget::Current => return command.to_string(),
+        ResumeTarget::TmuxWindow => "new-window",
+        ResumeTarget::TmuxPane => "split-window",
+    };
+    shell_words::join(["tmux", subcommand, "-c", &cwd.to_string_lossy(), command])
+}
+
+fn ensure_resume_target_available(target: ResumeTarget) -> Result<()> {
+    check_resume_target(target, std::env::var_os("TMUX").is_some())
+}
+
+fn check_resume_target(target: ResumeTarget, inside_tmux: bool) -> Result<()> {
+    if target != ResumeTarget::Current && !inside_tmux {
+        let name = target
+            .to_possible_value()
+            .map(|v| v.get_name().to_owned())
+            .unwrap_or_default();
+        bail!("--resume-in {name} requires running inside tmux ($TMUX is not set)");
+    }
+    Ok(())
+}
+
+/// Runs the resume command for `selection`, or just prints it when `dry_run` is set.
+pub fn resume_session(
+    command_template: &str,
+    selection: &Selection,
+    target: ResumeTarget,
+    dry_run: bool,
+) -> Result<()> {
+    if dry_run {
+        let command = render_resume_command(command_template, selection, target)?;
+        println!("{command}");
+        match check_resume_command(&command) {
+            Ok(program) => eprintln!("resume command ok: runs {}", program.display()),
+            Err(err) => eprintln!("warning: {err:#}"),
+        }
+        Ok(())
+    } else {
+        spawn_resume_command(command_template, selection, target)
+    }
+}
+
+/// Parses `command` like `spawn_resume_command` would and resolves its program on `PATH`.
+fn check_resume_command(command: &str) -> Result<PathBuf> {
+    let parts = shell_words::split(command).context("resume command does not parse")?;
+    let program = parts
+        .first()
+        .ok_or_else(|| anyhow!("resume command is empty"))?;
+    find_executable(program)
+        .ok_or_else(|| anyhow!("resume program `{program}` was not found on PATH"))
+}
+
+fn find_executable(program: &str) -> Option<PathBuf> {
+    let is_executable = |path: &Path| {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            path.metadata()
+                .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        }
+        #[cfg(not(unix))]
+        {
+            path.is_file()
+        }
+    };
+    if program.contains(std::path::MAIN_SEPARATOR) {
+        let path = PathBuf::from(program);
+        return is_executable(&path).then_some(path);
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| is_executable(path))
+}
+
+/// Pipes one tab-separated line per result (uuid first) into `selector` and returns
+/// the result whose line it prints. `None` means the selection was cancelled.
+fn select_with_command<'a>(
+    selector: &str,
+    results: &'a [SearchResult],
+    basis: TimeBasis,
+) -> Result<Option<&'a SearchResult>> {
+    let parts = shell_words::split(selector).context("failed to parse selector command")?;
+    let (program, args) = parts
+        .split_first()
+        .ok_or_else(|| anyhow!("selector command is empty"))?;
+
+    let mut child = match Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            bail!("selector `{program}` not found; install it or pass --selector <CMD>")
+        }
+        Err(err) => return Err(err).with_context(|| format!("failed to start `{program}`")),
+    };
+
+    {
+        let mut stdin = child.stdin.take().context("selector stdin unavailable")?;
+        for result in results {
+            let line = format!(
+                "{}\t{}\t{}\t{}\n",
+                result.session.uuid,
+                format_timestamp(result.timestamp(basis)),
+                result.session.label,
+                snippet_to_plain_line(&result.snippet)
+            );
+            // The selector may exit before reading everything; that is not an error.
+            if stdin.write_all(line.as_bytes()).is_err() {
+                break;
+            }
+        }
+    }
+
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("failed to read selection from `{program}`"))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let chosen = String::from_utf8_lossy(&output.stdout);
+    let Some(uuid) = chosen
+        .lines()
+        .next()
+        .and_then(|line| line.split('\t').next())
+        .map(str::trim)
+        .filter(|uuid| !uuid.is_empty())
+    else {
+        return Ok(None);
+    };
+    match results.iter().find(|r| r.session.uuid == uuid) {
+        Some(result) => Ok(Some(result)),
+        None => bail!("selector returned unknown session {uuid}"),
+    }
+}
+
+fn snippet_to_plain_line(snippet: &crate::session::Snippet) -> String {
+    snippet
+        .segments
+        .iter()
+        .map(|segment| segment.text.as_str())
+        .collect::<String>()
+        .replace(['\t', '\n'], " ")
+}
+
+pub fn spawn_resume_command(
+    command_template: &str,
+    selection: &Selection,
+    target: ResumeTarget,
+) -> Result<()> {
+    let command = render_resume_command(command_template, selection, target)?;
     let parts = shell_words::split(&command).context("failed to parse resume command")?;
     let program = parts
         .first()
@@ -305,7 +1953,7 @@ pub fn spawn_resume_command(command_template: &str, uuid: &str) -> Result<()> {
     Ok(())
 }
 
-fn filter_sessions_by_cwd(mut sessions: Vec<Session>, cwd: &Path) -> Vec<Session> {
+fn filter_sessions_by_cwd(sessions: Vec<Session>, cwd: &Path) -> Vec<Session> {
     let cwd_norm = normalize_path(cwd);
     sessions
         .into_iter()
@@ -316,27 +1964,49 @@ fn filter_sessions_by_cwd(mut sessions: Vec<Session>, cwd: &Path) -> Vec<Session
         .collect()
 }
 
-fn normalize_path(p: &Path) -> std::path::PathBuf {
-    std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf())
-}
-
-fn paths_related(a: &Path, b: &Path) -> bool {
-    a.starts_with(b) || b.starts_with(a)
-}
-
+/// Times discovery and parsing once, then scores the loaded sessions `iters` times.
 fn run_bench(
-    sessions: &[Session],
     query: &str,
     iters: usize,
     limit: usize,
-    sessions_root: &Path,
-    root_exists: bool,
+    cwd_filter: Option<&Path>,
+    discovery: &DiscoveryOptions,
+    search: &SearchOptions,
+    output: &Output,
 ) -> Result<()> {
     use std::time::Instant;
+    let root_exists = discovery.any_root_exists();
+    let (mut sessions, discovery_ms, parse_ms) = if root_exists {
+        let t0 = Instant::now();
+        let paths = discovery::collect_session_paths(discovery)?;
+        let discovery_ms = t0.elapsed().as_secs_f64() * 1000.0;
+        let t0 = Instant::now();
+        let sessions = discovery::load_sessions(paths, discovery)?;
+        (sessions, discovery_ms, t0.elapsed().as_secs_f64() * 1000.0)
+    } else {
+        (Vec::new(), 0.0, 0.0)
+    };
+    if let Some(cwd) = cwd_filter {
+        sessions = filter_sessions_by_cwd(sessions, cwd);
+    }
+    let sessions = sessions.as_slice();
+
+    // Count every session file so runs on differently sized corpora can be compared.
+    let discovered_paths = if root_exists {
+        count_session_files(discovery)?
+    } else {
+        0
+    };
     let mut results = serde_json::json!({
         "root_exists": root_exists,
-        "sessions_root": sessions_root.display().to_string(),
+        "sessions_root": discovery.roots[0],
+        "sessions_roots": discovery.roots,
+        "discovered_paths": discovered_paths,
+        "scan_limit": discovery.scan_limit,
+        "scan_limit_hit": discovered_paths > discovery.scan_limit,
         "sessions_count": sessions.len(),
+        "discovery_ms": discovery_ms,
+        "parse_ms": parse_ms,
         "query": query,
         "limit": limit,
         "iterations": iters,
@@ -346,22 +2016,878 @@ fn run_bench(
     let mut runs = Vec::new();
     for _ in 0..iters {
         let t0 = Instant::now();
-        let scored = search_sessions(sessions, query, limit)?;
+        let scored = search_sessions(sessions, query, limit, search)?;
         let t1 = t0.elapsed();
         runs.push(serde_json::json!({
             "search_ms": t1.as_millis(),
-            "top_uuid": scored.get(0).map(|r| r.session.uuid.clone()),
+            "top_uuid": scored.first().map(|r| r.session.uuid.clone()),
         }));
     }
     results["runs"] = serde_json::Value::Array(runs);
     // Aggregate
-    let times: Vec<u128> = results["runs"].as_array().unwrap().iter().filter_map(|v| v["search_ms"].as_u64().map(|x| x as u128)).collect();
-    let avg = if times.is_empty() { 0.0 } else { (times.iter().sum::<u128>() as f64) / (times.len() as f64) };
+    let times: Vec<u128> = results["runs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|v| v["search_ms"].as_u64().map(|x| x as u128))
+        .collect();
+    let avg = if times.is_empty() {
+        0.0
+    } else {
+        (times.iter().sum::<u128>() as f64) / (times.len() as f64)
+    };
     results["avg_search_ms"] = serde_json::json!(avg);
+    results["typing"] = bench_typing(sessions, query, search);
 
-    serde_json::to_writer_pretty(std::io::stdout(), &results).context("failed to write bench json")?;
-    println!();
-    Ok(())
+    output.write_json(&results)
+}
+
+/// Scores every prefix of `query` in turn, as the TUI does while it is typed, once
+/// with a fresh scorer per keystroke and once carrying the `MessageCache` along.
+fn bench_typing(sessions: &[Session], query: &str, search: &SearchOptions) -> serde_json::Value {
+    use std::time::Instant;
+    let prefixes: Vec<&str> = query
+        .char_indices()
+        .map(|(i, c)| &query[..i + c.len_utf8()])
+        .collect();
+    let score_all = |scorer: &mut Scorer| -> Vec<(usize, i64)> {
+        sessions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, session)| scorer.score_session(session).map(|s| (i, s.score)))
+            .collect()
+    };
+
+    let t0 = Instant::now();
+    let uncached: Vec<Vec<(usize, i64)>> = prefixes
+        .iter()
+        .map(|prefix| score_all(&mut Scorer::with_options(prefix, search.clone())))
+        .collect();
+    let uncached_ms = t0.elapsed().as_secs_f64() * 1000.0;
+
+    let t0 = Instant::now();
+    let mut cache = MessageCache::default();
+    let mut cached = Vec::new();
+    for prefix in &prefixes {
+        let mut scorer = Scorer::with_cache(prefix, search.clone(), cache);
+        cached.push(score_all(&mut scorer));
+        cache = scorer.into_cache();
+    }
+    let cached_ms = t0.elapsed().as_secs_f64() * 1000.0;
+
+    serde_json::json!({
+        "prefixes": prefixes.len(),
+        "uncached_ms": uncached_ms,
+        "cached_ms": cached_ms,
+        "identical": uncached == cached,
+    })
+}
+
+/// Session files under the root, ignoring the scan limit.
+fn count_session_files(discovery: &DiscoveryOptions) -> Result<usize> {
+    let unlimited = DiscoveryOptions {
+        scan_limit: usize::MAX,
+        ..discovery.clone()
+    };
+    Ok(discovery::collect_session_paths(&unlimited)?.len())
 }
 
 use time::OffsetDateTime;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn os_strings(items: &[&str]) -> Vec<OsString> {
+        items.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn count_mode_prints_only_the_number_of_results() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("count.txt");
+        let output = Output {
+            json: false,
+            ndjson: false,
+            count: true,
+            file: Some(path.clone()),
+            color: false,
+            meta: None,
+            span: false,
+        };
+        print_results(Vec::new(), TimeBasis::default(), &output).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "0\n");
+    }
+
+    #[test]
+    fn config_defaults_fill_only_flags_left_unset() {
+        let defaults = Defaults {
+            sessions_dir: vec!["/srv/codex".into()],
+            limit: Some(7),
+            message_recency_weight: Some(40),
+            jobs: Some(2),
+            ..Defaults::default()
+        };
+        let parse = |argv: &[&str]| {
+            let matches = Args::command().get_matches_from(argv);
+            let mut args = Args::from_arg_matches(&matches).unwrap();
+            apply_config_defaults(&mut args, &matches, &defaults).unwrap();
+            args
+        };
+        let args = parse(&["cdxs", "--limit", "3", "tokio"]);
+        assert_eq!(args.limit, 3);
+        assert_eq!(args.message_recency_weight, 40);
+        assert_eq!(args.jobs, Some(2));
+        assert_eq!(args.sessions_dir, [PathBuf::from("/srv/codex")]);
+        let args = parse(&[
+            "cdxs",
+            "--sessions-dir",
+            "/tmp/s",
+            "--message-recency-weight",
+            "0",
+            "--jobs",
+            "4",
+        ]);
+        assert_eq!(args.limit, 7);
+        assert_eq!(args.message_recency_weight, 0);
+        assert_eq!(args.jobs, Some(4));
+        assert_eq!(args.sessions_dir, [PathBuf::from("/tmp/s")]);
+    }
+
+    #[test]
+    fn stats_count_sessions_per_day_and_cwd() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, cwd, messages) in [
+            ("a-2025-01-01T10-00-00-aaaa", Some("/work/app"), 2),
+            ("b-2025-01-01T10-00-01-bbbb", Some("/work/app"), 1),
+            ("c-2025-01-03T10-00-00-cccc", None, 3),
+        ] {
+            let mut lines: Vec<String> = cwd
+                .map(|cwd| format!(r#"{{"type":"session_meta","payload":{{"cwd":"{cwd}"}}}}"#))
+                .into_iter()
+                .collect();
+            lines.extend(
+                (0..messages).map(|i| format!(r#"{{"role":"user","content":"message {i}"}}"#)),
+            );
+            std::fs::write(dir.join(format!("{name}.jsonl")), lines.join("\n")).unwrap();
+        }
+        let options = DiscoveryOptions {
+            roots: vec![dir.clone()],
+            ..DiscoveryOptions::with_defaults().unwrap()
+        };
+        let sessions = discovery::collect_sessions(&options).unwrap();
+        let stats = CorpusStats::from_sessions(&sessions);
+
+        assert_eq!((stats.sessions, stats.messages), (3, 6));
+        let days: Vec<(usize, usize)> = stats
+            .per_day
+            .iter()
+            .map(|d| (d.sessions, d.messages))
+            .collect();
+        assert_eq!(days, [(2, 3), (1, 3)]);
+        assert_eq!(stats.top_cwds.len(), 1);
+        assert_eq!(stats.top_cwds[0].cwd, Path::new("/work/app"));
+        assert_eq!(stats.top_cwds[0].sessions, 2);
+        assert_eq!(stats.sessions_without_cwd, 1);
+        assert!(stats.first < stats.last);
+
+        let mut text = Vec::new();
+        stats.write_text(&mut text, &Glyphs::ascii()).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        let range = text.lines().find(|l| l.starts_with("range\t")).unwrap();
+        assert!(range.contains(" -> ") && range.is_ascii(), "{range}");
+    }
+
+    #[test]
+    fn json_field_cwd_sessions_pass_the_cwd_filter() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("meta-2025-01-01T00-00-00-abcd.jsonl");
+        std::fs::write(
+            &path,
+            [
+                r#"{"type":"session_meta","payload":{"id":"abcd","cwd":"/work/app"}}"#,
+                r#"{"role":"user","content":"hello"}"#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        let options = DiscoveryOptions {
+            roots: vec![dir.clone()],
+            ..DiscoveryOptions::with_defaults().unwrap()
+        };
+        let session = discovery::load_session_from_path(path, &options)
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.cwd.as_deref(), Some(Path::new("/work/app")));
+
+        let kept = filter_sessions_by_cwd(vec![session.clone()], Path::new("/work/app/src"));
+        assert_eq!(kept.len(), 1);
+        assert!(filter_sessions_by_cwd(vec![session], Path::new("/work/other")).is_empty());
+    }
+
+    #[test]
+    fn snippet_highlights_degrade_to_plain_text_without_color() {
+        use crate::session::{Snippet, SnippetSegment};
+        let segment = |text: &str, highlighted| SnippetSegment {
+            text: text.into(),
+            highlighted,
+        };
+        let snippet = Snippet {
+            segments: vec![segment("fix the ", false), segment("tokio", true)],
+        };
+        assert_eq!(snippet_to_cli_line(&snippet, false), "fix the tokio");
+        assert_eq!(
+            snippet_to_cli_line(&snippet, true),
+            "fix the \x1b[1mtokio\x1b[0m"
+        );
+        assert!(!ColorChoice::Never.enabled(true));
+        assert!(ColorChoice::Always.enabled(false));
+        assert!(!ColorChoice::Auto.enabled(false));
+    }
+
+    #[test]
+    fn completions_cover_the_flags() {
+        let mut out: Vec<u8> = Vec::new();
+        clap_complete::generate(
+            clap_complete::Shell::Bash,
+            &mut Args::command(),
+            "cdxs",
+            &mut out,
+        );
+        let script = String::from_utf8(out).unwrap();
+        assert!(script.contains("_cdxs()"));
+        assert!(script.contains("--ndjson"));
+    }
+
+    #[test]
+    fn clear_state_takes_its_kind_as_a_value_and_no_query() {
+        let kind = |argv: &[&str]| Args::try_parse_from(argv).unwrap().clear_state;
+        assert_eq!(
+            kind(&["cdxs", "--clear-state=cache"]),
+            Some(StateKind::Cache)
+        );
+        assert_eq!(
+            kind(&["cdxs", "--clear-state", "history"]),
+            Some(StateKind::History)
+        );
+        assert_eq!(kind(&["cdxs", "--clear-state"]), Some(StateKind::All));
+        assert!(Args::try_parse_from(["cdxs", "--clear-state", "logs"]).is_err());
+
+        for argv in [
+            ["cdxs", "--clear-state", "cache", "deploy"],
+            ["cdxs", "--state-paths", "deploy", "api"],
+        ] {
+            let err = run_with(Args::try_parse_from(argv).unwrap(), &Config::default());
+            assert!(err.unwrap_err().to_string().contains("take no query terms"));
+        }
+    }
+
+    /// Runs `cdxs` with `argv` over `sessions` (file name, user message) in a fresh
+    /// sessions dir and no index, returning what it wrote to `--output-file`.
+    fn run_over(sessions: &[(&str, &str)], argv: &[&str]) -> Result<String> {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("sessions");
+        std::fs::create_dir_all(&root).unwrap();
+        for (name, text) in sessions {
+            let line =
+                format!(r#"{{"role":"user","content":[{{"type":"input_text","text":"{text}"}}]}}"#);
+            std::fs::write(root.join(name), line).unwrap();
+        }
+        let out = tmp.path().join("out.txt");
+        let args = Args::try_parse_from(
+            [
+                "cdxs",
+                "--no-cache",
+                "--sessions-dir",
+                root.to_str().unwrap(),
+            ]
+            .into_iter()
+            .chain(["--output-file", out.to_str().unwrap()])
+            .chain(argv.iter().copied()),
+        )?;
+        run_with(args, &Config::default())?;
+        Ok(std::fs::read_to_string(out).unwrap_or_default())
+    }
+
+    #[test]
+    fn list_files_prints_the_scan_window_in_order() {
+        let sessions = [
+            ("a-2025-01-01T00-00-00-aaaa.jsonl", "deploy the api"),
+            ("b-2025-01-02T00-00-00-bbbb.jsonl", "deploy the worker"),
+            ("c-2025-01-03T00-00-00-cccc.jsonl", "unrelated"),
+        ];
+        let names = |paths: &serde_json::Value| -> Vec<String> {
+            paths
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|p| {
+                    Path::new(p.as_str().unwrap())
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect()
+        };
+        let json = |argv: &[&str]| -> serde_json::Value {
+            serde_json::from_str(&run_over(&sessions, argv).unwrap()).unwrap()
+        };
+
+        let listed = json(&["--list-files", "--json", "--scan-limit", "2"]);
+        assert_eq!(names(&listed), [sessions[2].0, sessions[1].0]);
+        let listed = json(&["--list-files", "--json", "--exclude", "**/b-*"]);
+        assert_eq!(names(&listed), [sessions[2].0, sessions[0].0]);
+        let wrapped = json(&["--list-files", "--json", "--json-meta"]);
+        assert!(wrapped["meta"]["sessions_root"].is_string());
+        assert_eq!(names(&wrapped["results"]).len(), 3);
+
+        let text = run_over(&sessions, &["--list-files"]).unwrap();
+        assert_eq!(text.lines().count(), 3);
+        assert!(text.lines().next().unwrap().ends_with(sessions[2].0));
+    }
+
+    #[test]
+    fn ndjson_writes_one_result_per_line_in_every_mode() {
+        let sessions = [
+            ("a-2025-01-01T00-00-00-aaaa.jsonl", "deploy the api"),
+            ("b-2025-01-02T00-00-00-bbbb.jsonl", "deploy the worker"),
+            ("c-2025-01-03T00-00-00-cccc.jsonl", "unrelated"),
+        ];
+        let lines = |argv: &[&str]| -> Vec<serde_json::Value> {
+            run_over(&sessions, argv)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        };
+        let mut uuids: Vec<String> = lines(&["--ndjson", "deploy"])
+            .iter()
+            .map(|r| r["session"]["uuid"].as_str().unwrap().to_string())
+            .collect();
+        uuids.sort();
+        assert_eq!(uuids, ["aaaa", "bbbb"]);
+        let results = lines(&["--ndjson", "deploy"]);
+        assert!(
+            results
+                .iter()
+                .all(|r| r["score"].is_i64() && r.get("text_score").is_none())
+        );
+
+        let files = lines(&["--ndjson", "--list-files"]);
+        assert_eq!(files.len(), 3);
+        assert!(files[0].as_str().unwrap().ends_with("cccc.jsonl"));
+        let reports = lines(&["--ndjson", "--validate"]);
+        assert_eq!(reports.len(), 3);
+        assert!(reports.iter().all(|r| r["path"].is_string()));
+
+        for mode in ["--bench", "--stats"] {
+            assert!(run_over(&sessions, &["--ndjson", mode, "deploy"]).is_err());
+        }
+    }
+
+    #[test]
+    fn json_meta_and_bench_keep_the_first_sessions_root() {
+        let sessions = [("a-2025-01-01T00-00-00-aaaa.jsonl", "deploy the api")];
+        let json = |argv: &[&str]| -> serde_json::Value {
+            serde_json::from_str(&run_over(&sessions, argv).unwrap()).unwrap()
+        };
+        for value in [
+            json(&["--json", "--json-meta", "deploy"])["meta"].clone(),
+            json(&["--bench", "--bench-iters", "1", "deploy"]),
+        ] {
+            let root = value["sessions_root"].as_str().unwrap();
+            assert!(root.ends_with("sessions"));
+            assert_eq!(value["sessions_roots"], serde_json::json!([root]));
+        }
+    }
+
+    #[test]
+    fn first_message_rejects_roles_it_can_never_match() {
+        let sessions = [("a-2025-01-01T00-00-00-aaaa.jsonl", "deploy the api")];
+        let run = |role: &str| {
+            run_over(
+                &sessions,
+                &["--json", "--first-message", "--role", role, "deploy"],
+            )
+        };
+        assert!(run("user").unwrap().contains("aaaa"));
+        for role in ["assistant", "tool"] {
+            let err = run(role).unwrap_err().to_string();
+            assert!(err.contains("--first-message"), "{err}");
+        }
+    }
+
+    #[test]
+    fn ndjson_lines_are_compact_and_newline_terminated() {
+        let mut out: Vec<u8> = Vec::new();
+        write_ndjson_line(&mut out, &serde_json::json!({"uuid": "a", "score": 3})).unwrap();
+        write_ndjson_line(&mut out, &serde_json::json!({"uuid": "b", "score": 1})).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            [r#"{"score":3,"uuid":"a"}"#, r#"{"score":1,"uuid":"b"}"#]
+        );
+        assert!(text.ends_with('\n'));
+    }
+
+    #[test]
+    fn match_placeholders_quote_the_message_as_one_word() {
+        let text = "it's {uuid}\nsecond line";
+        let selection = Selection {
+            uuid: "abcd".into(),
+            path: PathBuf::from("/home/me/.codex/sessions/my session.jsonl"),
+            label: "it's done".into(),
+            matched_message: Some(Message {
+                role: crate::session::MessageRole::User,
+                text: text.into(),
+                timestamp: None,
+                full_text: text.into(),
+                full_text_lower: text.to_lowercase(),
+                full_text_ws_lower: text.to_lowercase(),
+                source_line: 0,
+            }),
+        };
+        let template = "wrap {uuid} --role {match_role} --context {match}";
+        let command = render_resume_command(template, &selection, ResumeTarget::Current).unwrap();
+        assert_eq!(
+            shell_words::split(&command).unwrap(),
+            strings(&["wrap", "abcd", "--role", "user", "--context", text])
+        );
+
+        let bare = Selection {
+            matched_message: None,
+            ..selection
+        };
+        let command = render_resume_command(template, &bare, ResumeTarget::Current).unwrap();
+        assert_eq!(command, "wrap abcd --role  --context ");
+
+        let command =
+            render_resume_command("$EDITOR {path} # {label}", &bare, ResumeTarget::Current)
+                .unwrap();
+        assert_eq!(
+            command,
+            r#"$EDITOR '/home/me/.codex/sessions/my session.jsonl' # 'it'\''s done'"#
+        );
+        let command = render_resume_command("vim {path}", &bare, ResumeTarget::Current).unwrap();
+        assert_eq!(
+            shell_words::split(&command).unwrap(),
+            strings(&["vim", "/home/me/.codex/sessions/my session.jsonl"])
+        );
+    }
+
+    #[test]
+    fn positional_terms_beat_the_env_var() {
+        let resolve = |argv: &[&str], env: Option<&str>| {
+            resolve_query(&Args::try_parse_from(argv).unwrap(), env.map(str::to_owned))
+        };
+        let env = Some(" from env ");
+        assert_eq!(resolve(&["cdxs", "axum", " tokio "], env), "axum  tokio");
+        assert_eq!(resolve(&["cdxs"], env), "from env");
+        assert_eq!(resolve(&["cdxs", " "], env), "from env");
+        assert_eq!(resolve(&["cdxs"], None), "");
+    }
+
+    #[test]
+    fn saved_search_flags_come_first_and_query_only_when_missing() {
+        let saved = strings(&["rust", "--limit", "5", "--cwd", "tokio"]);
+
+        let argv = os_strings(&["cdxs", "--search", "r", "--limit", "9"]);
+        let args = Args::parse_from(expand_saved_search(argv, &saved, false).unwrap());
+        assert_eq!(args.limit, 9);
+        assert!(args.cwd);
+        assert_eq!(args.query, strings(&["rust", "tokio"]));
+
+        let argv = os_strings(&["cdxs", "--search", "r", "axum"]);
+        let args = Args::parse_from(expand_saved_search(argv, &saved, true).unwrap());
+        assert_eq!(args.limit, 5);
+        assert_eq!(args.query, strings(&["axum"]));
+
+        assert!(expand_saved_search(os_strings(&["cdxs"]), &strings(&["--bogus"]), false).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn saved_search_passes_non_utf8_arguments_through() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let dir = OsString::from_vec(b"/tmp/caf\xe9".to_vec());
+        let mut argv = os_strings(&["cdxs", "--search", "r", "--sessions-dir"]);
+        argv.push(dir.clone());
+        let expanded = expand_saved_search(argv, &strings(&["rust"]), false).unwrap();
+        assert_eq!(expanded[4], dir);
+        let args = Args::parse_from(expanded);
+        assert_eq!(args.sessions_dir, vec![PathBuf::from(dir)]);
+    }
+
+    #[test]
+    fn tmux_targets_wrap_the_command_and_require_tmux() {
+        let cwd = Path::new("/home/me/my project");
+        let command = "codex resume 'a b'";
+        assert_eq!(wrap_in_tmux(command, ResumeTarget::Current, cwd), command);
+        for (target, subcommand) in [
+            (ResumeTarget::TmuxWindow, "new-window"),
+            (ResumeTarget::TmuxPane, "split-window"),
+        ] {
+            let wrapped = shell_words::split(&wrap_in_tmux(command, target, cwd)).unwrap();
+            assert_eq!(
+                wrapped,
+                ["tmux", subcommand, "-c", "/home/me/my project", command]
+            );
+        }
+
+        assert!(check_resume_target(ResumeTarget::Current, false).is_ok());
+        assert!(check_resume_target(ResumeTarget::TmuxPane, true).is_ok());
+        let err = check_resume_target(ResumeTarget::TmuxWindow, false).unwrap_err();
+        assert!(err.to_string().contains("--resume-in tmux-window"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn selector_picks_the_uuid_of_the_printed_line() {
+        let dir =
+            std::env::temp_dir().join(format!("codex-search-selector-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, text) in [
+            ("a-2025-01-01T00-00-00-aaaa.jsonl", "deploy the api"),
+            ("b-2025-01-02T00-00-00-bbbb.jsonl", "deploy the worker"),
+        ] {
+            let line = format!(r#"{{"role":"user","content":"{text}"}}"#);
+            std::fs::write(dir.join(name), line).unwrap();
+        }
+        let options = DiscoveryOptions {
+            roots: vec![dir.clone()],
+            ..DiscoveryOptions::with_defaults().unwrap()
+        };
+        let sessions = discovery::collect_sessions(&options).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let results = search_sessions(&sessions, "deploy", 10, &SearchOptions::default()).unwrap();
+        assert_eq!(results.len(), 2);
+        let select = |selector: &str| {
+            select_with_command(selector, &results, TimeBasis::default())
+                .map(|chosen| chosen.map(|r| r.session.uuid.clone()))
+        };
+
+        let second = results[1].session.uuid.clone();
+        assert_eq!(select("sed -n 2p").unwrap(), Some(second));
+        assert_eq!(select("false").unwrap(), None);
+        let err = select("echo no-such-uuid").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "selector returned unknown session no-such-uuid"
+        );
+        let err = select("cdxs-no-such-selector").unwrap_err();
+        assert!(err.to_string().contains("not found"), "{err}");
+    }
+
+    #[test]
+    fn bench_counts_session_files_past_the_scan_limit() {
+        let dir =
+            std::env::temp_dir().join(format!("codex-search-bench-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, text) in [
+            ("a-2025-01-01T00-00-00-aaaa.jsonl", "deploy the api"),
+            ("b-2025-01-02T00-00-00-bbbb.jsonl", "deploy the worker"),
+            ("c-2025-01-03T00-00-00-cccc.jsonl", "unrelated"),
+        ] {
+            let line = format!(r#"{{"role":"user","content":"{text}"}}"#);
+            std::fs::write(dir.join(name), line).unwrap();
+        }
+        let limited = DiscoveryOptions {
+            roots: vec![dir.clone()],
+            scan_limit: 2,
+            ..DiscoveryOptions::with_defaults().unwrap()
+        };
+        let loaded = discovery::collect_sessions(&limited).unwrap().len();
+        let counted = count_session_files(&limited).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(loaded, 2);
+        assert_eq!(counted, 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dry_run_check_resolves_the_resume_program() {
+        let program = check_resume_command("sh -c 'codex resume abcd'").unwrap();
+        assert!(program.is_absolute() && program.ends_with("sh"));
+
+        let script = std::env::temp_dir().join(format!(
+            "codex-search-not-executable-{}",
+            std::process::id()
+        ));
+        std::fs::write(&script, "").unwrap();
+        let message = |command: &str| format!("{:#}", check_resume_command(command).unwrap_err());
+        let not_executable = message(&script.to_string_lossy());
+        std::fs::remove_file(&script).unwrap();
+        assert!(not_executable.contains("not found on PATH"));
+        assert!(message("cdxs-no-such-program {uuid}").contains("not found on PATH"));
+        assert!(message("codex 'unterminated").contains("does not parse"));
+        assert!(message("").contains("empty"));
+    }
+
+    #[test]
+    fn output_file_receives_json_and_list_output() {
+        let dir =
+            std::env::temp_dir().join(format!("codex-search-output-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, text) in [
+            ("a-2025-01-01T00-00-00-aaaa.jsonl", "deploy the api"),
+            ("b-2025-01-02T00-00-00-bbbb.jsonl", "deploy the worker"),
+        ] {
+            let line = format!(r#"{{"role":"user","content":"{text}"}}"#);
+            std::fs::write(dir.join(name), line).unwrap();
+        }
+        let options = DiscoveryOptions {
+            roots: vec![dir.clone()],
+            ..DiscoveryOptions::with_defaults().unwrap()
+        };
+        let sessions = discovery::collect_sessions(&options).unwrap();
+        let results = search_sessions(&sessions, "deploy", 10, &SearchOptions::default()).unwrap();
+        let out = dir.join("out.txt");
+        let write = |json: bool, file: &Path| {
+            let output = Output {
+                json,
+                ndjson: false,
+                count: false,
+                file: Some(file.to_path_buf()),
+                color: false,
+                meta: None,
+                span: false,
+            };
+            print_results(results.clone(), TimeBasis::default(), &output)
+        };
+
+        write(true, &out).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 2);
+        write(false, &out).unwrap();
+        let list = std::fs::read_to_string(&out).unwrap();
+        assert_eq!(list.lines().filter(|l| l.contains("deploy the")).count(), 2);
+        assert!(!list.contains('\x1b'));
+
+        let err = write(true, &dir.join("missing/out.json")).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(format!("{err:#}").contains("creating"), "{err:#}");
+    }
+
+    #[test]
+    fn empty_text_searches_widen_the_scan_window_once() {
+        let dir =
+            std::env::temp_dir().join(format!("codex-search-widen-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // Only the older session matches, past a one-file scan window.
+        for (i, (name, text)) in [
+            ("a-2025-01-01T00-00-00-aaaa.jsonl", "deploy the api"),
+            ("b-2025-01-02T00-00-00-bbbb.jsonl", "unrelated"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let path = dir.join(name);
+            std::fs::write(&path, format!(r#"{{"role":"user","content":"{text}"}}"#)).unwrap();
+            let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(60 * i as u64);
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|file| file.set_modified(modified))
+                .unwrap();
+        }
+        let out = dir.join("out.txt");
+        let output = |json| Output {
+            json,
+            ndjson: false,
+            count: false,
+            file: Some(out.clone()),
+            color: false,
+            meta: None,
+            span: false,
+        };
+        let (text, json) = (output(false), output(true));
+        let discovery = |scan_limit| DiscoveryOptions {
+            roots: vec![dir.clone()],
+            scan_limit,
+            ..DiscoveryOptions::with_defaults().unwrap()
+        };
+        assert!(can_widen(&discovery(50), "deploy", &text));
+        assert!(!can_widen(&discovery(WIDENED_SCAN_LIMIT), "deploy", &text));
+        assert!(!can_widen(&discovery(50), "deploy", &json));
+        assert!(!can_widen(&discovery(50), " ", &text));
+
+        let narrow = discovery(1);
+        let sessions = discovery::collect_sessions(&narrow).unwrap();
+        assert_eq!(sessions.len(), 1);
+        let search = SearchOptions::default();
+        let run = |output: &Output| {
+            run_cli_mode(&sessions, "deploy", 10, &narrow, &search, None, output).unwrap();
+            std::fs::read_to_string(&out).unwrap()
+        };
+        let list = run(&text);
+        assert!(list.contains("deploy the api"), "{list}");
+        let found = run(&json);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(found.trim(), "[]");
+    }
+
+    #[test]
+    fn json_meta_records_the_query_and_filters() {
+        let args = Args::try_parse_from([
+            "cdxs",
+            "--json",
+            "--json-meta",
+            "--limit",
+            "1",
+            "--sort",
+            "score",
+            "deploy",
+        ])
+        .unwrap();
+        let discovery = DiscoveryOptions {
+            scan_limit: 7,
+            ..DiscoveryOptions::with_defaults().unwrap()
+        };
+        let search = SearchOptions {
+            sort: args.sort,
+            ..SearchOptions::default()
+        };
+        let meta = SearchMeta::new(&args, "deploy  api", &discovery, &search).unwrap();
+        let out = std::env::temp_dir().join(format!(
+            "codex-search-json-meta-test-{}.json",
+            std::process::id()
+        ));
+        let output = |meta| Output {
+            json: true,
+            ndjson: false,
+            count: false,
+            file: Some(out.clone()),
+            color: false,
+            meta,
+            span: false,
+        };
+        let read = || -> serde_json::Value {
+            serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap()
+        };
+
+        output(Some(meta)).write_results(&["r1"]).unwrap();
+        let json = read();
+        let meta = &json["meta"];
+        assert_eq!(meta["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(meta["query"], "deploy api");
+        assert_eq!(meta["limit"], 1);
+        assert_eq!(meta["sort"], "score");
+        assert_eq!(meta["scan_limit"], 7);
+        assert_eq!(meta["cwd"], serde_json::Value::Null);
+        assert!(meta["generated_at"].as_str().unwrap().contains('T'));
+        assert_eq!(json["results"], serde_json::json!(["r1"]));
+
+        output(None).write_results(&["r1"]).unwrap();
+        let plain = read();
+        std::fs::remove_file(&out).unwrap();
+        assert_eq!(plain, serde_json::json!(["r1"]));
+    }
+
+    #[test]
+    fn path_and_label_placeholders_survive_shell_syntax_in_names() {
+        let dir = std::env::temp_dir().join(format!(
+            "codex-search-placeholder-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("it's $(whoami) `id`-2025-01-01T00-00-00-abcd.jsonl");
+        std::fs::write(&path, r#"{"role":"user","content":"hello"}"#).unwrap();
+        let options = DiscoveryOptions {
+            roots: vec![dir.clone()],
+            ..DiscoveryOptions::with_defaults().unwrap()
+        };
+        let session = discovery::load_session_from_path(path.clone(), &options)
+            .unwrap()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let result = Scorer::new("")
+            .score_session_arc(std::sync::Arc::new(session))
+            .unwrap();
+        let selection = Selection::from_result(&result);
+
+        let command = render_resume_command(
+            "$EDITOR {path} --title {label}",
+            &selection,
+            ResumeTarget::Current,
+        )
+        .unwrap();
+        let words = shell_words::split(&command).unwrap();
+        assert_eq!(words.len(), 4, "{command}");
+        assert_eq!(words[1], path.to_str().unwrap());
+        assert_eq!(words[3], selection.label);
+        assert!(selection.label.contains("$(whoami)"), "{}", selection.label);
+    }
+
+    #[test]
+    fn bench_times_discovery_and_parsing_once_before_the_search_runs() {
+        let dir = std::env::temp_dir().join(format!(
+            "codex-search-bench-timing-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sessions")).unwrap();
+        for (i, (name, text)) in [
+            ("a-2025-01-01T00-00-00-aaaa.jsonl", "deploy the api"),
+            ("b-2025-01-02T00-00-00-bbbb.jsonl", "deploy the worker"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let path = dir.join("sessions").join(name);
+            std::fs::write(&path, format!(r#"{{"role":"user","content":"{text}"}}"#)).unwrap();
+            let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(60 * i as u64);
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|file| file.set_modified(modified))
+                .unwrap();
+        }
+        let out = dir.join("out.json");
+        let output = Output {
+            json: true,
+            ndjson: false,
+            count: false,
+            file: Some(out.clone()),
+            color: false,
+            meta: None,
+            span: false,
+        };
+        let discovery = DiscoveryOptions {
+            roots: vec![dir.join("sessions")],
+            ..DiscoveryOptions::with_defaults().unwrap()
+        };
+        let search = SearchOptions::default();
+        run_bench("deploy", 3, 10, None, &discovery, &search, &output).unwrap();
+        let bench: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        for phase in ["discovery_ms", "parse_ms"] {
+            assert!(bench[phase].as_f64().unwrap() > 0.0, "{phase}: {bench}");
+        }
+        assert_eq!(bench["sessions_count"], 2);
+        let runs = bench["runs"].as_array().unwrap();
+        assert_eq!(runs.len(), 3);
+        assert!(
+            runs.iter()
+                .all(|run| run["search_ms"].is_u64() && run["top_uuid"] == "bbbb")
+        );
+    }
+}
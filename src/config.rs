@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use directories::BaseDirs;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::keys::KeySpec;
+
+/// Settings read from `config.toml`; every section is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Saved searches: name -> command-line arguments (query terms and flags).
+    pub searches: BTreeMap<String, String>,
+    /// TUI key bindings: action name -> key or list of keys; see `keys::KeyMap`.
+    pub keys: BTreeMap<String, KeySpec>,
+    /// Values for flags not given on the command line.
+    pub defaults: Defaults,
+}
+
+/// The `[defaults]` table: one entry per flag, named like it. A flag on the command
+/// line, then the flag's env var, takes precedence over its entry here.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Defaults {
+    /// One directory or a list; a leading `~` is the home directory.
+    #[serde(
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub sessions_dir: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resume_command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_limit: Option<usize>,
+    /// A duration such as `"3d"` or `"36h"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recency_half_life: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_recency_weight: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+}
+
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+impl Config {
+    /// `explicit` (`--config`), else `CODEX_SEARCH_CONFIG`, else
+    /// `~/.config/codex-search/config.toml` (platform config dir).
+    pub fn path(explicit: Option<&Path>) -> Result<PathBuf> {
+        if let Some(path) = explicit {
+            return Ok(path.to_path_buf());
+        }
+        if let Some(path) = std::env::var_os("CODEX_SEARCH_CONFIG") {
+            return Ok(PathBuf::from(path));
+        }
+        let base = BaseDirs::new().context("failed to determine home directory")?;
+        Ok(base.config_dir().join("codex-search").join("config.toml"))
+    }
+
+    /// Loads the config file; a missing file yields the defaults unless it was named
+    /// explicitly with `--config`.
+    pub fn load(explicit: Option<&Path>) -> Result<Self> {
+        let path = Self::path(explicit)?;
+        match std::fs::read_to_string(&path) {
+            // Keep the parser's explanation: only the outermost context reaches the user.
+            Ok(text) => Self::parse(&text).map_err(|err| anyhow!("{}: {err:#}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound && explicit.is_none() => {
+                Ok(Self::default())
+            }
+            Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        toml::from_str(text).context("invalid config")
+    }
+
+    /// The arguments stored under saved search `name`, split like a shell would.
+    pub fn saved_search(&self, name: &str) -> Result<Vec<String>> {
+        let Some(entry) = self.searches.get(name) else {
+            let known: Vec<&str> = self.searches.keys().map(String::as_str).collect();
+            if known.is_empty() {
+                bail!("unknown saved search {name:?}: no [searches] defined in the config");
+            }
+            bail!(
+                "unknown saved search {name:?} (available: {})",
+                known.join(", ")
+            );
+        };
+        shell_words::split(entry).with_context(|| format!("saved search {name:?} does not parse"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_searches_split_into_arguments() {
+        let config = Config::parse(
+            r#"
+            [searches]
+            recent-rust = "rust --cwd --limit 5"
+            quoted = "'gold coin' --all-terms"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.saved_search("recent-rust").unwrap(),
+            vec!["rust", "--cwd", "--limit", "5"]
+        );
+        assert_eq!(
+            config.saved_search("quoted").unwrap(),
+            vec!["gold coin", "--all-terms"]
+        );
+        let err = config.saved_search("nope").unwrap_err().to_string();
+        assert!(err.contains("quoted, recent-rust"), "{err}");
+    }
+
+    #[test]
+    fn keys_accept_a_key_or_a_list() {
+        let config = Config::parse(
+            r#"
+            [keys]
+            quit = ["esc", "ctrl-q"]
+            raw-record = "f2"
+            "#,
+        )
+        .unwrap();
+        assert!(crate::keys::KeyMap::new(&config.keys).is_ok());
+        assert!(Config::parse("[keys]\nquit = 3").is_err());
+    }
+
+    #[test]
+    fn defaults_mirror_flag_names() {
+        let config = Config::parse(
+            r#"
+            [defaults]
+            sessions-dir = "~/codex/sessions"
+            limit = 5
+            recency-half-life = "3d"
+            jobs = 2
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.defaults.sessions_dir, ["~/codex/sessions"]);
+        assert_eq!(config.defaults.limit, Some(5));
+        assert_eq!(config.defaults.jobs, Some(2));
+        assert_eq!(config.defaults.scan_limit, None);
+        let many = Config::parse("[defaults]\nsessions-dir = [\"/a\", \"/b\"]").unwrap();
+        assert_eq!(many.defaults.sessions_dir, ["/a", "/b"]);
+        assert!(Config::parse("[defaults]\nlimt = 5").is_err());
+    }
+}
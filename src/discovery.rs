@@ -1,28 +1,67 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::thread;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use anyhow::{Context, Result};
-use crossbeam_channel::{Receiver, unbounded};
+use anyhow::{Context, Result, anyhow, bail};
+use bincode::Options as _;
+use crossbeam_channel::{Receiver, RecvTimeoutError, unbounded};
 use directories::BaseDirs;
+use flate2::read::MultiGzDecoder;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use once_cell::sync::Lazy;
 use rayon::prelude::*;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use time::format_description::well_known::Rfc3339;
 use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
 use walkdir::WalkDir;
 
-use crate::session::{Message, MessageRole, Session};
+use crate::search::{
+    SNIPPET_CONTEXT_CHARS, Scorer, SearchOptions, normalize_query, snippet_from_text,
+};
+use crate::session::{Message, MessageRole, SearchResult, Session};
+use crate::util::nfc;
 
 const SEARCH_BLOB_LIMIT: usize = 64 * 1024;
+/// Separate search blob budget for `--include-tools` text, so tool output never
+/// crowds conversation text out of the blob.
+const TOOL_BLOB_LIMIT: usize = 16 * 1024;
 const MAX_MESSAGE_CHARS: usize = 8 * 1024;
+const MAX_TITLE_CHARS: usize = 200;
+/// Bumped whenever `CachedSession` or what is extracted into it changes, so older
+/// index files are ignored.
+const INDEX_VERSION: u32 = 5;
+/// Index files larger than this are ignored rather than read into memory.
+const MAX_INDEX_BYTES: u64 = 2 << 30;
+/// Quiet period after a file's last change before `watch_sessions` reloads it, so a
+/// session being written is loaded once per burst rather than once per line.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+/// Complete leading lines `looks_like_conversation` must see before ruling a file out.
+const SNIFF_LINES: usize = 8;
+/// Record `type`s written by Codex (current rollouts and older flat exports).
+const SESSION_RECORD_TYPES: &[&str] = &[
+    "session_meta",
+    "response_item",
+    "event_msg",
+    "turn_context",
+    "compacted",
+    "message",
+    "reasoning",
+    "function_call",
+    "function_call_output",
+];
 
 pub struct SessionStream {
     receiver: Receiver<Session>,
-    handle: thread::JoinHandle<()>,
+    /// Files that failed to load, collected in `--strict` mode.
+    handle: thread::JoinHandle<Vec<(PathBuf, anyhow::Error)>>,
     pub total: usize,
 }
 
@@ -31,60 +70,704 @@ impl SessionStream {
         self.receiver.clone()
     }
 
-    pub fn join(self) {
-        let _ = self.handle.join();
+    /// Waits for the loader; in `--strict` mode, fails if any file did not load.
+    pub fn join(self) -> Result<()> {
+        match self.handle.join() {
+            Ok(failures) if !failures.is_empty() => Err(parse_failures(failures)),
+            _ => Ok(()),
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct DiscoveryOptions {
-    pub root: PathBuf,
+    /// Sessions directories; their files are merged newest first before `scan_limit`
+    /// applies.
+    pub roots: Vec<PathBuf>,
     pub scan_limit: usize,
     pub preview_char_limit: usize,
+    /// Keep sessions with no user/assistant messages so they stay findable by label.
+    pub keep_empty: bool,
+    /// Keep only previews per message and the whitespace-collapsed search blob;
+    /// full message text is reloaded from disk for displayed results.
+    pub low_memory: bool,
+    /// Also index assistant reasoning/thinking text: searchable, and the snippet when
+    /// only reasoning matches, but never shown as a message.
+    pub include_reasoning: bool,
+    /// Also extract tool calls (shell commands, arguments) and their output as
+    /// `MessageRole::Tool` messages.
+    pub include_tools: bool,
+    /// Stop collecting messages after this many per session; later matches are not found.
+    pub max_messages: Option<usize>,
+    /// Fold consecutive same-role messages into one (newline-joined, first timestamp).
+    pub merge_turns: bool,
+    /// Also scan this archive directory; its sessions rank after primary ones on ties.
+    pub archive_root: Option<PathBuf>,
+    /// Replaces the built-in session filename pattern; see `compile_filename_regex`.
+    pub filename_regex: Option<Regex>,
+    /// Only consider files whose filename uuid (lowercased) is in this set.
+    pub restrict_uuids: Option<HashSet<String>>,
+    /// Skip session files, and sessions whose cwd, matched by these globs.
+    pub exclude: Option<ExcludeGlobs>,
+    /// Keep the filename label for display even when the metadata carries a title.
+    pub filename_labels: bool,
+    /// Treat invalid JSON lines as errors and fail instead of skipping unloadable files.
+    pub strict: bool,
+    /// Bring message, reasoning and title text to Unicode NFC while loading.
+    pub unicode_normalize: bool,
+    /// On-disk index of parsed sessions (see `load_index`); `None` parses every file.
+    pub index_path: Option<PathBuf>,
+    /// Ignore the existing index and re-parse every file, then rewrite it.
+    pub rebuild_index: bool,
 }
 
 impl DiscoveryOptions {
     pub fn with_defaults() -> Result<Self> {
         Ok(Self {
-            root: default_sessions_dir()?,
+            roots: vec![default_sessions_dir()?],
             // Lower default scan limit to keep TUI snappy on large datasets
             scan_limit: 50,
             preview_char_limit: 240,
+            keep_empty: false,
+            low_memory: false,
+            include_reasoning: false,
+            include_tools: false,
+            max_messages: None,
+            merge_turns: false,
+            archive_root: None,
+            filename_regex: None,
+            restrict_uuids: None,
+            exclude: None,
+            filename_labels: false,
+            strict: false,
+            unicode_normalize: false,
+            index_path: None,
+            rebuild_index: false,
         })
     }
+
+    pub fn any_root_exists(&self) -> bool {
+        self.roots.iter().any(|root| root.exists())
+    }
+
+    pub fn missing_roots(&self) -> Vec<&Path> {
+        self.roots
+            .iter()
+            .filter(|root| !root.exists())
+            .map(PathBuf::as_path)
+            .collect()
+    }
+
+    /// The roots as `a, b` for messages.
+    pub fn roots_display(&self) -> String {
+        display_paths(self.roots.iter().map(PathBuf::as_path))
+    }
 }
 
+/// `a, b, c` for listing paths in messages.
+pub fn display_paths<'a>(paths: impl IntoIterator<Item = &'a Path>) -> String {
+    paths
+        .into_iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `archived_sessions` beside the sessions directory, where Codex moves archived sessions.
+pub fn default_archive_dir(sessions_root: &Path) -> PathBuf {
+    sessions_root
+        .parent()
+        .unwrap_or(sessions_root)
+        .join("archived_sessions")
+}
+
+/// `~/.codex/sessions`, or the first existing alternative layout when it is missing.
 pub fn default_sessions_dir() -> Result<PathBuf> {
+    Ok(first_existing_dir(sessions_dir_candidates()?))
+}
+
+/// The first of `candidates` that is a directory, else the first (standard) one.
+fn first_existing_dir(mut candidates: Vec<PathBuf>) -> PathBuf {
+    match candidates.iter().position(|dir| dir.is_dir()) {
+        Some(found) => candidates.swap_remove(found),
+        None => candidates.swap_remove(0),
+    }
+}
+
+/// Known session locations in probe order; the first entry is the standard layout.
+pub fn sessions_dir_candidates() -> Result<Vec<PathBuf>> {
     let base = BaseDirs::new().context("failed to determine home directory")?;
-    Ok(base.home_dir().join(".codex/sessions"))
+    Ok(sessions_dir_candidates_in(
+        base.home_dir(),
+        std::env::var_os("CODEX_HOME"),
+    ))
+}
+
+fn sessions_dir_candidates_in(home: &Path, codex_home: Option<OsString>) -> Vec<PathBuf> {
+    let mut candidates = vec![
+        home.join(".codex/sessions"),
+        home.join(".config/codex/sessions"),
+    ];
+    if let Some(codex_home) = codex_home.filter(|v| !v.is_empty()) {
+        candidates.push(PathBuf::from(codex_home).join("sessions"));
+    }
+    candidates.push(home.join("Library/Application Support/codex/sessions"));
+    candidates
 }
 
 pub fn collect_sessions(options: &DiscoveryOptions) -> Result<Vec<Session>> {
-    let paths = collect_session_paths(options)?;
-    let sessions: Vec<Session> = paths
+    load_sessions(collect_session_paths(options)?, options)
+}
+
+/// Parses `paths` in parallel, reusing and refreshing the on-disk index.
+pub fn load_sessions(paths: Vec<PathBuf>, options: &DiscoveryOptions) -> Result<Vec<Session>> {
+    let index = load_index(options);
+    let loaded: Vec<(PathBuf, Option<Loaded>, Result<Option<Session>>)> = paths
         .into_par_iter()
-        .filter_map(|path| {
-            load_session_from_path(path, options.preview_char_limit)
-                .ok()
-                .flatten()
+        .map(|path| {
+            let (loaded, result) = index.load(path.clone(), options);
+            (path, loaded, result)
         })
         .collect();
+    let mut index = index;
+    let mut sessions = Vec::with_capacity(loaded.len());
+    let mut failures = Vec::new();
+    for (path, loaded, result) in loaded {
+        if let Some(loaded) = loaded {
+            index.insert(path.clone(), loaded);
+        }
+        match result {
+            Ok(session) => sessions.extend(session),
+            Err(err) if options.strict => failures.push((path, err)),
+            Err(_) => {}
+        }
+    }
+    save_index(&mut index, options);
+    if !failures.is_empty() {
+        return Err(parse_failures(failures));
+    }
     Ok(sessions)
 }
 
+/// A file's modification time and size, which must match for an index entry to be reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct FileStamp {
+    modified_nanos: u128,
+    len: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self {
+            modified_nanos: modified.as_nanos(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// A freshly parsed file, to be recorded in the index.
+struct Loaded {
+    stamp: FileStamp,
+    session: Option<Session>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    stamp: FileStamp,
+    /// `None` for files that held no session, so they are not re-parsed either.
+    #[serde(with = "cached_session")]
+    session: Option<Session>,
+}
+
+/// Parsed sessions from earlier runs keyed by file path (`--no-cache` skips it,
+/// `--rebuild-index` starts it over). Only valid for the discovery options it was
+/// built with.
+#[derive(Default, Serialize, Deserialize)]
+pub struct SessionIndexFile {
+    version: u32,
+    options: String,
+    entries: HashMap<PathBuf, IndexEntry>,
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl SessionIndexFile {
+    /// The session at `path`: the indexed one when its stamp still matches, else a
+    /// fresh parse, returned alongside for `insert`.
+    fn load(
+        &self,
+        path: PathBuf,
+        options: &DiscoveryOptions,
+    ) -> (Option<Loaded>, Result<Option<Session>>) {
+        let stamp = options
+            .index_path
+            .as_ref()
+            .and_then(|_| FileStamp::of(&path));
+        if let Some(stamp) = stamp
+            && let Some(entry) = self.entries.get(&path)
+            && entry.stamp == stamp
+        {
+            return (None, Ok(entry.session.clone()));
+        }
+        let result = load_session_from_path(path, options);
+        let loaded = match (&result, stamp) {
+            (Ok(session), Some(stamp)) => Some(Loaded {
+                stamp,
+                session: session.clone(),
+            }),
+            _ => None,
+        };
+        (loaded, result)
+    }
+
+    fn insert(&mut self, path: PathBuf, loaded: Loaded) {
+        self.entries.insert(
+            path,
+            IndexEntry {
+                stamp: loaded.stamp,
+                session: loaded.session,
+            },
+        );
+        self.changed = true;
+    }
+}
+
+/// Everything in `DiscoveryOptions` that changes what a file parses into.
+fn index_options_key(options: &DiscoveryOptions) -> String {
+    format!(
+        "{} preview={} keep_empty={} low_memory={} reasoning={} tools={} max_messages={:?} merge_turns={} archive={:?} regex={:?} exclude={:?} filename_labels={} nfc={}",
+        env!("CARGO_PKG_VERSION"),
+        options.preview_char_limit,
+        options.keep_empty,
+        options.low_memory,
+        options.include_reasoning,
+        options.include_tools,
+        options.max_messages,
+        options.merge_turns,
+        options.archive_root,
+        options.filename_regex.as_ref().map(Regex::as_str),
+        options.exclude.as_ref().map(|globs| &globs.patterns),
+        options.filename_labels,
+        options.unicode_normalize,
+    )
+}
+
+/// Reads the index at `options.index_path`. Missing, unreadable, outdated or
+/// differently configured indexes (and `rebuild_index`) yield an empty one.
+pub fn load_index(options: &DiscoveryOptions) -> SessionIndexFile {
+    let key = index_options_key(options);
+    let empty = SessionIndexFile {
+        version: INDEX_VERSION,
+        options: key.clone(),
+        ..SessionIndexFile::default()
+    };
+    let Some(path) = &options.index_path else {
+        return empty;
+    };
+    if options.rebuild_index {
+        return SessionIndexFile {
+            changed: true,
+            ..empty
+        };
+    }
+    let Ok(file) = File::open(path) else {
+        return empty;
+    };
+    let len = file.metadata().map_or(u64::MAX, |meta| meta.len());
+    if len > MAX_INDEX_BYTES {
+        return SessionIndexFile {
+            changed: true,
+            ..empty
+        };
+    }
+    // Limited to the file's size, so a corrupt length prefix fails instead of allocating.
+    let decoded = index_codec()
+        .with_limit(len)
+        .deserialize_from::<_, SessionIndexFile>(std::io::BufReader::new(file));
+    match decoded {
+        Ok(index) if index.version == INDEX_VERSION && index.options == key => index,
+        _ => SessionIndexFile {
+            changed: true,
+            ..empty
+        },
+    }
+}
+
+/// Drops entries whose file is gone and writes the index back when anything changed.
+/// The index is only a cache, so failures are reported and otherwise ignored.
+pub fn save_index(index: &mut SessionIndexFile, options: &DiscoveryOptions) {
+    let Some(path) = &options.index_path else {
+        return;
+    };
+    let before = index.entries.len();
+    index.entries.retain(|path, _| path.exists());
+    if !index.changed && index.entries.len() == before {
+        return;
+    }
+    let write = || -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Write beside the index and rename, so concurrent runs never read a torn file.
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        let mut writer = std::io::BufWriter::new(File::create(&tmp)?);
+        index_codec().serialize_into(&mut writer, &*index)?;
+        writer.into_inner()?.sync_all()?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    };
+    match write() {
+        Ok(()) => index.changed = false,
+        Err(err) => eprintln!("failed to save session index {}: {err:#}", path.display()),
+    }
+}
+
+/// bincode settings of the index file: those of `bincode::serialize`, to which
+/// `load_index` adds a size limit.
+fn index_codec() -> impl bincode::Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+}
+
+/// `Message` with every field serialized, for the index. Borrows when writing the
+/// index and owns when reading it.
+#[derive(Serialize, Deserialize)]
+struct CachedMessage<'a> {
+    role: MessageRole,
+    text: Cow<'a, str>,
+    timestamp: Option<OffsetDateTime>,
+    full_text: Cow<'a, str>,
+    full_text_lower: Cow<'a, str>,
+    full_text_ws_lower: Cow<'a, str>,
+    source_line: usize,
+}
+
+/// `Session` with every field serialized (its own `Serialize` is the JSON output,
+/// which leaves the search fields out), for the index. Borrows like `CachedMessage`.
+#[derive(Serialize, Deserialize)]
+struct CachedSession<'a> {
+    uuid: Cow<'a, str>,
+    label: Cow<'a, str>,
+    title_meta: Option<Cow<'a, str>>,
+    label_lower: Cow<'a, str>,
+    path: Cow<'a, Path>,
+    created_at: Option<OffsetDateTime>,
+    updated_at: OffsetDateTime,
+    earliest_message_time: Option<OffsetDateTime>,
+    latest_message_time: Option<OffsetDateTime>,
+    cwd: Option<Cow<'a, Path>>,
+    model: Option<Cow<'a, str>>,
+    messages: Vec<CachedMessage<'a>>,
+    search_blob: Cow<'a, str>,
+    search_blob_lower: Cow<'a, str>,
+    search_blob_ws_lower: Cow<'a, str>,
+    uuid_lower: Cow<'a, str>,
+    archived: bool,
+    reasoning: Cow<'a, str>,
+    reasoning_ws_lower: Cow<'a, str>,
+    compacted: bool,
+}
+
+impl<'a> From<&'a Session> for CachedSession<'a> {
+    fn from(session: &'a Session) -> Self {
+        Self {
+            uuid: Cow::Borrowed(&session.uuid),
+            label: Cow::Borrowed(&session.label),
+            title_meta: session.title_meta.as_deref().map(Cow::Borrowed),
+            label_lower: Cow::Borrowed(&session.label_lower),
+            path: Cow::Borrowed(&session.path),
+            created_at: session.created_at,
+            updated_at: session.updated_at,
+            earliest_message_time: session.earliest_message_time,
+            latest_message_time: session.latest_message_time,
+            cwd: session.cwd.as_deref().map(Cow::Borrowed),
+            model: session.model.as_deref().map(Cow::Borrowed),
+            messages: session
+                .messages
+                .iter()
+                .map(|m| CachedMessage {
+                    role: m.role.clone(),
+                    text: Cow::Borrowed(&m.text),
+                    timestamp: m.timestamp,
+                    full_text: Cow::Borrowed(&m.full_text),
+                    full_text_lower: Cow::Borrowed(&m.full_text_lower),
+                    full_text_ws_lower: Cow::Borrowed(&m.full_text_ws_lower),
+                    source_line: m.source_line,
+                })
+                .collect(),
+            search_blob: Cow::Borrowed(&session.search_blob),
+            search_blob_lower: Cow::Borrowed(&session.search_blob_lower),
+            search_blob_ws_lower: Cow::Borrowed(&session.search_blob_ws_lower),
+            uuid_lower: Cow::Borrowed(&session.uuid_lower),
+            archived: session.archived,
+            reasoning: Cow::Borrowed(&session.reasoning),
+            reasoning_ws_lower: Cow::Borrowed(&session.reasoning_ws_lower),
+            compacted: session.compacted,
+        }
+    }
+}
+
+impl From<CachedSession<'_>> for Session {
+    fn from(cached: CachedSession<'_>) -> Self {
+        Self {
+            uuid: cached.uuid.into_owned(),
+            label: cached.label.into_owned(),
+            title_meta: cached.title_meta.map(Cow::into_owned),
+            label_lower: cached.label_lower.into_owned(),
+            path: cached.path.into_owned(),
+            created_at: cached.created_at,
+            updated_at: cached.updated_at,
+            earliest_message_time: cached.earliest_message_time,
+            latest_message_time: cached.latest_message_time,
+            cwd: cached.cwd.map(Cow::into_owned),
+            model: cached.model.map(Cow::into_owned),
+            messages: cached
+                .messages
+                .into_iter()
+                .map(|m| Message {
+                    role: m.role,
+                    text: m.text.into_owned(),
+                    timestamp: m.timestamp,
+                    full_text: m.full_text.into_owned(),
+                    full_text_lower: m.full_text_lower.into_owned(),
+                    full_text_ws_lower: m.full_text_ws_lower.into_owned(),
+                    source_line: m.source_line,
+                })
+                .collect(),
+            search_blob: cached.search_blob.into_owned(),
+            search_blob_lower: cached.search_blob_lower.into_owned(),
+            search_blob_ws_lower: cached.search_blob_ws_lower.into_owned(),
+            uuid_lower: cached.uuid_lower.into_owned(),
+            archived: cached.archived,
+            reasoning: cached.reasoning.into_owned(),
+            reasoning_ws_lower: cached.reasoning_ws_lower.into_owned(),
+            compacted: cached.compacted,
+        }
+    }
+}
+
+/// Serde `with` module storing an indexed `Session` through `CachedSession`.
+mod cached_session {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{CachedSession, Session};
+
+    pub fn serialize<S: Serializer>(
+        session: &Option<Session>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        session
+            .as_ref()
+            .map(CachedSession::from)
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Session>, D::Error> {
+        Ok(Option::<CachedSession>::deserialize(deserializer)?.map(Session::from))
+    }
+}
+
+/// One error listing every file that failed to load and why.
+fn parse_failures(failures: Vec<(PathBuf, anyhow::Error)>) -> anyhow::Error {
+    let details: Vec<String> = failures
+        .iter()
+        .map(|(path, err)| format!("  {}: {err:#}", path.display()))
+        .collect();
+    let noun = if failures.len() == 1 { "file" } else { "files" };
+    anyhow!(
+        "{} session {noun} failed to parse:\n{}",
+        failures.len(),
+        details.join("\n")
+    )
+}
+
+/// Keeps watching the roots while alive; sessions created or changed after it
+/// started arrive on `receiver`, already loaded.
+pub struct SessionWatcher {
+    receiver: Receiver<Session>,
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl SessionWatcher {
+    pub fn receiver(&self) -> Receiver<Session> {
+        self.receiver.clone()
+    }
+}
+
+/// Watches every existing root recursively and loads session files once they have
+/// been quiet for `WATCH_DEBOUNCE`. A changed file is sent again in full; files that
+/// fail to load (e.g. caught mid-write) are retried on their next change.
+pub fn watch_sessions(options: DiscoveryOptions) -> Result<SessionWatcher> {
+    use notify::{EventKind, RecursiveMode, Watcher};
+
+    let (path_tx, path_rx) = unbounded::<PathBuf>();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            for path in event.paths.into_iter().filter(|p| is_session_file(p)) {
+                let _ = path_tx.send(path);
+            }
+        }
+    })
+    .context("starting the session watcher")?;
+    for root in options.roots.iter().filter(|root| root.exists()) {
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .with_context(|| format!("watching {}", root.display()))?;
+    }
+
+    let (tx, rx) = unbounded();
+    thread::spawn(move || {
+        let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+        loop {
+            // Ends once the watcher, which owns the sending side, is dropped.
+            match path_rx.recv_timeout(WATCH_DEBOUNCE) {
+                Ok(path) => {
+                    pending.insert(path, Instant::now());
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            let settled: Vec<PathBuf> = pending
+                .iter()
+                .filter(|(_, changed)| changed.elapsed() >= WATCH_DEBOUNCE)
+                .map(|(path, _)| path.clone())
+                .collect();
+            for path in settled {
+                pending.remove(&path);
+                if !is_allowed_uuid(&path, &options) || is_excluded(&path, &options) {
+                    continue;
+                }
+                if let Ok(Some(session)) = load_session_from_path(path, &options)
+                    && tx.send(session).is_err()
+                {
+                    return;
+                }
+            }
+        }
+    });
+
+    Ok(SessionWatcher {
+        receiver: rx,
+        _watcher: watcher,
+    })
+}
+
+/// Newest session files across all roots first, up to `scan_limit`, followed by up
+/// to `scan_limit` archived ones when an archive root is set.
 pub fn collect_session_paths(options: &DiscoveryOptions) -> Result<Vec<PathBuf>> {
-    let mut entries: Vec<(PathBuf, OffsetDateTime)> = WalkDir::new(&options.root)
+    let mut entries: Vec<(PathBuf, OffsetDateTime)> = Vec::new();
+    for root in &options.roots {
+        entries.extend(scan_session_entries(root));
+    }
+    entries.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+    // The same directory may be given twice, or one root may sit inside another.
+    let mut seen = HashSet::new();
+    let mut paths: Vec<PathBuf> = entries
+        .into_iter()
+        .map(|(path, _)| path)
+        .filter(|path| {
+            seen.insert(path.clone())
+                && is_allowed_uuid(path, options)
+                && !is_excluded(path, options)
+        })
+        .take(options.scan_limit)
+        .collect();
+    if let Some(archive) = &options.archive_root {
+        paths.extend(
+            scan_session_files(archive)
+                .into_iter()
+                .filter(|path| is_allowed_uuid(path, options) && !is_excluded(path, options))
+                .take(options.scan_limit),
+        );
+    }
+    Ok(paths)
+}
+
+/// `--exclude` patterns, compiled once.
+#[derive(Debug, Clone)]
+pub struct ExcludeGlobs {
+    patterns: Vec<String>,
+    set: GlobSet,
+}
+
+impl ExcludeGlobs {
+    /// Compiles shell-style globs (`*`, `**`, `?`, `[..]`, `{a,b}`; a leading `~` is the
+    /// home directory); errors name the pattern that does not parse.
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = Glob::new(&expand_tilde(pattern))
+                .with_context(|| format!("invalid --exclude pattern {pattern:?}"))?;
+            builder.add(glob);
+        }
+        Ok(Self {
+            patterns: patterns.to_vec(),
+            set: builder.build().context("compiling --exclude patterns")?,
+        })
+    }
+
+    /// True when `path` or one of its ancestors matches, so `**/scratch` covers
+    /// everything inside a `scratch` directory.
+    pub fn matches(&self, path: &Path) -> bool {
+        path.ancestors()
+            .take_while(|p| !p.as_os_str().is_empty())
+            .any(|p| self.set.is_match(p))
+    }
+}
+
+fn is_excluded(path: &Path, options: &DiscoveryOptions) -> bool {
+    options
+        .exclude
+        .as_ref()
+        .is_some_and(|globs| globs.matches(path))
+}
+
+/// Whether the session's cwd matches an `--exclude` pattern.
+fn is_excluded_cwd(cwd: Option<&Path>, options: &DiscoveryOptions) -> bool {
+    cwd.is_some_and(|cwd| is_excluded(cwd, options))
+}
+
+/// Filename-only check against `restrict_uuids`, so skipped files are never opened.
+fn is_allowed_uuid(path: &Path, options: &DiscoveryOptions) -> bool {
+    let Some(allowed) = &options.restrict_uuids else {
+        return true;
+    };
+    parse_from_filename(path, options.filename_regex.as_ref())
+        .is_ok_and(|(_, _, uuid)| allowed.contains(&uuid.to_lowercase()))
+}
+
+/// All `.jsonl`, `.jsonl.gz` and `.json` files under `root`, newest modification first.
+fn scan_session_files(root: &Path) -> Vec<PathBuf> {
+    scan_session_entries(root)
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// `.jsonl`, `.jsonl.gz` or `.json`, by name only.
+fn is_session_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("jsonl") || ext.eq_ignore_ascii_case("json"))
+        || is_gzipped(path)
+}
+
+/// Session files under `root` with their modification times, newest first.
+fn scan_session_entries(root: &Path) -> Vec<(PathBuf, OffsetDateTime)> {
+    let mut entries: Vec<(PathBuf, OffsetDateTime)> = WalkDir::new(root)
         .max_depth(8)
         .into_iter()
         .filter_map(|entry| match entry {
             Ok(entry) => {
-                if entry.file_type().is_file()
-                    && entry
-                        .path()
-                        .extension()
-                        .and_then(|ext| ext.to_str())
-                        .is_some_and(|ext| ext.eq_ignore_ascii_case("jsonl"))
-                {
+                if entry.file_type().is_file() && is_session_file(entry.path()) {
                     let modified = entry
                         .metadata()
                         .ok()
@@ -99,34 +782,176 @@ pub fn collect_session_paths(options: &DiscoveryOptions) -> Result<Vec<PathBuf>>
         })
         .collect();
 
-    entries.sort_by(|a, b| b.1.cmp(&a.1));
+    entries.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+    entries
+}
 
-    Ok(entries
-        .into_iter()
-        .map(|(path, _)| path)
-        .take(options.scan_limit)
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FileStatus {
+    Ok { messages: usize },
+    Empty,
+    Error { reason: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReport {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub status: FileStatus,
+}
+
+/// Parses every session file under the roots (ignoring the scan limit) and reports
+/// the outcome per file instead of silently dropping empty or broken ones.
+pub fn validate_sessions(options: &DiscoveryOptions) -> Result<Vec<FileReport>> {
+    let unlimited = DiscoveryOptions {
+        scan_limit: usize::MAX,
+        keep_empty: true,
+        ..options.clone()
+    };
+    let paths = collect_session_paths(&unlimited)?;
+    Ok(paths
+        .into_par_iter()
+        .enumerate()
+        .map(|(index, path)| {
+            // The newest file may be mid-write; an unterminated last line there is normal.
+            let status = match load_session_checked(path.clone(), &unlimited, index == 0) {
+                Ok((_, Some((line_no, err)))) => FileStatus::Error {
+                    reason: format!("line {line_no}: {err}"),
+                },
+                Ok((Some(session), None)) if !session.messages.is_empty() => FileStatus::Ok {
+                    messages: session.messages.len(),
+                },
+                Ok((_, None)) => FileStatus::Empty,
+                Err(err) => FileStatus::Error {
+                    reason: format!("{err:#}"),
+                },
+            };
+            FileReport { path, status }
+        })
         .collect())
 }
 
-pub fn stream_sessions(paths: Vec<PathBuf>, preview_char_limit: usize) -> SessionStream {
+/// `.jsonl.gz`: a JSONL session compressed by gzip.
+fn is_gzipped(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.to_ascii_lowercase().ends_with(".jsonl.gz"))
+}
+
+/// Opens a session file, decompressing `.jsonl.gz` on the fly (every member, so
+/// concatenated gzip streams read as one file).
+fn open_session(path: &Path) -> Result<Box<dyn Read + Send>> {
+    let file = File::open(path).with_context(|| format!("opening session {:?}", path))?;
+    Ok(if is_gzipped(path) {
+        Box::new(MultiGzDecoder::new(file))
+    } else {
+        Box::new(file)
+    })
+}
+
+/// The whole (decompressed) contents of a session file.
+fn read_session(path: &Path) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    open_session(path)?
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("reading session {:?}", path))?;
+    Ok(bytes)
+}
+
+/// `.json` files, or any file whose content starts with `[`, hold one JSON array of
+/// records rather than JSONL.
+fn is_json_array(path: &Path, head: &[u8]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+        || head.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'[')
+}
+
+/// False only for files that are clearly not sessions: binary data, or a JSONL head of
+/// `SNIFF_LINES` complete lines none of which has a conversation record's shape.
+/// Anything inconclusive (long records, short files, invalid lines) counts as a session.
+fn looks_like_conversation(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return false;
+    }
+    // Only whole lines: the buffer may end mid-record.
+    let Some(end) = head.iter().rposition(|b| *b == b'\n') else {
+        return true;
+    };
+    let mut complete = 0;
+    for line in head[..end].split(|b| *b == b'\n').take(SNIFF_LINES) {
+        complete += 1;
+        let Ok(record) = serde_json::from_slice::<Value>(line) else {
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            return true;
+        };
+        if is_conversation_record(&record) {
+            return true;
+        }
+    }
+    complete < SNIFF_LINES
+}
+
+/// An object with a conversation record's shape: a known `type`, or one of the keys
+/// only session records carry.
+fn is_conversation_record(record: &Value) -> bool {
+    let Value::Object(record) = record else {
+        return false;
+    };
+    let known_type = record
+        .get("type")
+        .and_then(Value::as_str)
+        .is_some_and(|kind| SESSION_RECORD_TYPES.contains(&kind));
+    known_type
+        || ["role", "payload", "record_type", "instructions"]
+            .iter()
+            .any(|key| record.contains_key(*key))
+}
+
+/// Locates the session file for `uuid` anywhere under the root, ignoring the scan limit.
+pub fn find_session_path(options: &DiscoveryOptions, uuid: &str) -> Result<Option<PathBuf>> {
+    let wanted = uuid.trim().to_lowercase();
+    let unlimited = DiscoveryOptions {
+        scan_limit: usize::MAX,
+        ..options.clone()
+    };
+    Ok(collect_session_paths(&unlimited)?.into_iter().find(|path| {
+        parse_from_filename(path, options.filename_regex.as_ref())
+            .is_ok_and(|(_, _, candidate)| candidate.to_lowercase() == wanted)
+    }))
+}
+
+pub fn stream_sessions(paths: Vec<PathBuf>, options: DiscoveryOptions) -> SessionStream {
     let total = paths.len();
     let (tx, rx) = unbounded();
 
     let handle = thread::spawn(move || {
+        let mut index = load_index(&options);
+        let mut failures = Vec::new();
         for path in paths {
             let display = path.clone();
-            match load_session_from_path(path, preview_char_limit) {
+            let (loaded, result) = index.load(path, &options);
+            if let Some(loaded) = loaded {
+                index.insert(display.clone(), loaded);
+            }
+            match result {
                 Ok(Some(session)) => {
                     if tx.send(session).is_err() {
                         break;
                     }
                 }
                 Ok(None) => {}
+                Err(err) if options.strict => failures.push((display, err)),
                 Err(err) => {
                     eprintln!("failed to load session {:?}: {err}", display);
                 }
             }
         }
+        save_index(&mut index, &options);
+        failures
     });
 
     SessionStream {
@@ -136,84 +961,502 @@ pub fn stream_sessions(paths: Vec<PathBuf>, preview_char_limit: usize) -> Sessio
     }
 }
 
-pub fn load_session_from_path(path: PathBuf, preview_char_limit: usize) -> Result<Option<Session>> {
+pub fn load_session_from_path(
+    path: PathBuf,
+    options: &DiscoveryOptions,
+) -> Result<Option<Session>> {
+    // An unterminated last line is a write in progress, not corruption.
+    let (session, invalid) = load_session_checked(path, options, true)?;
+    if options.strict
+        && let Some((line_no, err)) = invalid
+    {
+        bail!("line {line_no}: {err}");
+    }
+    Ok(session)
+}
+
+/// A record that is not valid JSON: its 1-based line and the parse error.
+type InvalidLine = (usize, serde_json::Error);
+
+/// Loads a session file along with its first record that is not valid JSON, found in
+/// the same pass. With `allow_partial_tail`, an unterminated final line (a write in
+/// progress) is not reported.
+fn load_session_checked(
+    path: PathBuf,
+    options: &DiscoveryOptions,
+    allow_partial_tail: bool,
+) -> Result<(Option<Session>, Option<InvalidLine>)> {
     let metadata = std::fs::metadata(&path).context("reading session metadata")?;
     let updated_at = system_time_to_offset(metadata.modified()?);
 
-    let file = File::open(&path).with_context(|| format!("opening session {:?}", path))?;
-    let reader = BufReader::new(file);
+    let mut reader = BufReader::new(open_session(&path)?);
+    let head = reader.fill_buf().context("reading session")?;
+    let is_array = is_json_array(&path, head);
+    if !is_array && !options.keep_empty && !looks_like_conversation(head) {
+        return Ok((None, None));
+    }
+    let mut invalid = None;
+    // Each record comes with its index so messages can point back at their raw line.
+    let records: Box<dyn Iterator<Item = (usize, Value)> + '_> = if is_array {
+        // A whole-file export: one array of records instead of one record per line.
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).context("reading session")?;
+        // Anything else with a `.json` name (or a leading `[`) is not a session, even
+        // with `keep_empty`; parse errors still surface under `--strict`/`--validate`.
+        match serde_json::from_slice::<Vec<Value>>(&bytes) {
+            Ok(records) if records.iter().any(is_conversation_record) => {
+                Box::new(records.into_iter().enumerate())
+            }
+            Ok(_) => return Ok((None, None)),
+            Err(err) => {
+                let line = err.line();
+                return Ok((None, Some((line, err))));
+            }
+        }
+    } else {
+        // A read error (e.g. the file being truncated under us) ends the session there.
+        // Codex may still be writing the file: a torn last line can be invalid UTF-8 or
+        // JSON. Skip such lines instead of dropping the whole session.
+        let lines = std::iter::from_fn(move || {
+            let mut bytes = Vec::new();
+            match reader.read_until(b'\n', &mut bytes) {
+                Ok(0) | Err(_) => None,
+                Ok(_) => Some(bytes),
+            }
+        });
+        Box::new(lines.enumerate().filter_map(|(line, bytes)| {
+            match serde_json::from_slice(&bytes) {
+                Ok(value) => Some((line, value)),
+                Err(_) if bytes.iter().all(u8::is_ascii_whitespace) => None,
+                Err(err) => {
+                    // Only the last line can lack its newline.
+                    let partial_tail = bytes.last() != Some(&b'\n');
+                    if invalid.is_none() && !(allow_partial_tail && partial_tail) {
+                        invalid = Some((line + 1, err));
+                    }
+                    None
+                }
+            }
+        }))
+    };
+
+    let extracted = extract_records(records, options);
+    if (extracted.messages.is_empty() && !options.keep_empty)
+        || is_excluded_cwd(extracted.cwd.as_deref(), options)
+    {
+        return Ok((None, invalid));
+    }
+    let (label, created_at, uuid) = parse_from_filename(&path, options.filename_regex.as_ref())?;
+    let identity = SessionIdentity {
+        path,
+        label,
+        created_at,
+        uuid,
+        updated_at,
+    };
+    Ok((
+        Some(assemble_session(extracted, identity, options)),
+        invalid,
+    ))
+}
+
+/// Reads JSONL sessions from `reader` (e.g. stdin). A `session_meta` record starts a
+/// new session, so concatenated session files split apart again; without one the
+/// whole stream is a single session. Uuids come from the `session_meta` id, else
+/// `stdin-N`.
+pub fn sessions_from_reader(
+    reader: impl BufRead,
+    options: &DiscoveryOptions,
+) -> Result<Vec<Session>> {
+    let mut groups: Vec<Vec<(usize, Value)>> = vec![Vec::new()];
+    for (line, bytes) in reader.split(b'\n').enumerate() {
+        let bytes = bytes.context("reading sessions from stdin")?;
+        if bytes.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        let value = match serde_json::from_slice::<Value>(&bytes) {
+            Ok(value) => value,
+            Err(err) if options.strict => bail!("stdin line {}: {err}", line + 1),
+            Err(_) => continue,
+        };
+        let starts_session = value.get("type").and_then(Value::as_str) == Some("session_meta");
+        if starts_session && groups.last().is_some_and(|group| !group.is_empty()) {
+            groups.push(Vec::new());
+        }
+        if let Some(group) = groups.last_mut() {
+            group.push((line, value));
+        }
+    }
 
+    let mut sessions = Vec::new();
+    for (index, records) in groups.into_iter().enumerate() {
+        let meta = records
+            .first()
+            .filter(|(_, value)| value.get("type").and_then(Value::as_str) == Some("session_meta"))
+            .and_then(|(_, value)| value.get("payload"))
+            .cloned();
+        let extracted = extract_records(records.into_iter(), options);
+        if (extracted.messages.is_empty() && !options.keep_empty)
+            || is_excluded_cwd(extracted.cwd.as_deref(), options)
+        {
+            continue;
+        }
+        let created_at = meta
+            .as_ref()
+            .and_then(|meta| meta.get("timestamp"))
+            .and_then(parse_timestamp_value);
+        let uuid = meta
+            .as_ref()
+            .and_then(|meta| meta.get("id"))
+            .and_then(Value::as_str)
+            .map_or_else(|| format!("stdin-{}", index + 1), str::to_owned);
+        let updated_at = extracted
+            .messages
+            .iter()
+            .filter_map(|m| m.timestamp)
+            .max()
+            .or(created_at)
+            .unwrap_or_else(OffsetDateTime::now_utc);
+        let identity = SessionIdentity {
+            path: PathBuf::from("-"),
+            label: "stdin".to_string(),
+            created_at,
+            uuid,
+            updated_at,
+        };
+        sessions.push(assemble_session(extracted, identity, options));
+    }
+    Ok(sessions)
+}
+
+/// What the records of one session yield, before it gets an identity.
+struct Extracted {
+    messages: Vec<Message>,
+    search_blob: String,
+    reasoning: String,
+    cwd: Option<PathBuf>,
+    title_meta: Option<String>,
+    model: Option<String>,
+}
+
+/// Where a session came from and what to call it.
+struct SessionIdentity {
+    path: PathBuf,
+    label: String,
+    created_at: Option<OffsetDateTime>,
+    uuid: String,
+    updated_at: OffsetDateTime,
+}
+
+/// Extracts messages, cwd, title, model and reasoning from a session's records (with their
+/// line or array index).
+fn extract_records(
+    records: impl Iterator<Item = (usize, Value)>,
+    options: &DiscoveryOptions,
+) -> Extracted {
     let mut messages: Vec<Message> = Vec::new();
     let mut search_blob = String::new();
+    // Bytes of the blob taken by conversation and by tool text, each against its budget.
+    let (mut conversation_len, mut tool_len) = (0, 0);
+    let mut reasoning = String::new();
     let mut detected_cwd: Option<PathBuf> = None;
+    // A `cwd`/`workdir` field on a meta record; used only when no environment
+    // context names one.
+    let mut field_cwd: Option<PathBuf> = None;
+    let mut title_meta: Option<String> = None;
+    let mut model: Option<String> = None;
 
-    for line in reader.lines() {
-        let line = line?;
-        if line.trim().is_empty() {
-            continue;
+    let normalize = |text: String| {
+        if options.unicode_normalize {
+            nfc(&text)
+        } else {
+            text
         }
+    };
 
-        if let Ok(value) = serde_json::from_str::<Value>(&line) {
-            if let Some((msg, full_text, is_meta)) = extract_message(&value, preview_char_limit) {
-                if !is_meta {
-                    if search_blob.len() + full_text.len() + 1 < SEARCH_BLOB_LIMIT {
-                        if !search_blob.is_empty() {
-                            search_blob.push('\n');
-                        }
-                        search_blob.push_str(&full_text);
+    for (line, value) in records {
+        if title_meta.is_none() {
+            title_meta = extract_title(&value).map(normalize);
+        }
+        if let Some(name) = extract_model(&value) {
+            model = Some(name);
+        }
+        if field_cwd.is_none() {
+            field_cwd = extract_cwd_field(&value);
+        }
+        let below_cap = options.max_messages.is_none_or(|max| messages.len() < max);
+        // Past the cap only metadata (the last-wins model above, and cwd until found)
+        // still comes from the remaining lines.
+        if !below_cap && (detected_cwd.is_some() || field_cwd.is_some()) {
+            continue;
+        }
+        if options.include_reasoning
+            && below_cap
+            && let Some(text) = extract_reasoning(&value).map(normalize)
+            && reasoning.len() + text.len() + 1 < SEARCH_BLOB_LIMIT
+        {
+            if !reasoning.is_empty() {
+                reasoning.push('\n');
+            }
+            reasoning.push_str(&text);
+        }
+        if let Some((mut msg, mut full_text, is_meta)) =
+            extract_message(&value, options.preview_char_limit, options.include_tools)
+        {
+            if options.unicode_normalize {
+                msg.normalize_unicode();
+                full_text = nfc(&full_text);
+            }
+            msg.source_line = line;
+            let from_tool = msg.role == MessageRole::Tool;
+            if !is_meta && below_cap {
+                let (used, limit) = if from_tool {
+                    (&mut tool_len, TOOL_BLOB_LIMIT)
+                } else {
+                    (&mut conversation_len, SEARCH_BLOB_LIMIT)
+                };
+                if *used + full_text.len() + 1 < limit {
+                    if !search_blob.is_empty() {
+                        search_blob.push('\n');
                     }
-                    messages.push(msg);
+                    search_blob.push_str(&full_text);
+                    *used += full_text.len() + 1;
                 }
-                // Always try to detect cwd regardless of meta flag; capture only once
-                if detected_cwd.is_none() {
-                    if let Some(cwd) = extract_cwd_from_text(&full_text) {
-                        detected_cwd = Some(cwd);
+                match messages.last_mut() {
+                    Some(last) if options.merge_turns && last.role == msg.role => {
+                        merge_message(last, &msg, options.preview_char_limit);
+                    }
+                    last => {
+                        // The previous message can no longer grow, so it is safe to compact.
+                        if options.low_memory
+                            && let Some(last) = last
+                        {
+                            last.compact();
+                        }
+                        messages.push(msg);
                     }
                 }
             }
+            // Always try to detect cwd regardless of meta flag; capture only once.
+            // Tool output may quote other sessions' environment context.
+            if detected_cwd.is_none()
+                && !from_tool
+                && let Some(cwd) = extract_cwd_from_text(&full_text)
+            {
+                detected_cwd = Some(cwd);
+            }
         }
     }
 
-    if messages.is_empty() {
-        return Ok(None);
+    if options.low_memory
+        && let Some(last) = messages.last_mut()
+    {
+        last.compact();
+    }
+    Extracted {
+        messages,
+        search_blob,
+        reasoning,
+        cwd: detected_cwd.or(field_cwd),
+        title_meta,
+        model,
     }
+}
 
-    let (label, created_at, uuid) = parse_from_filename(&path)?;
+fn assemble_session(
+    extracted: Extracted,
+    identity: SessionIdentity,
+    options: &DiscoveryOptions,
+) -> Session {
+    let Extracted {
+        messages,
+        mut search_blob,
+        reasoning,
+        cwd,
+        title_meta,
+        model,
+    } = extracted;
+    let SessionIdentity {
+        path,
+        mut label,
+        created_at,
+        uuid,
+        updated_at,
+    } = identity;
+    if !options.filename_labels
+        && let Some(title) = &title_meta
+    {
+        label = title.clone();
+    }
     let label_lower = label.to_lowercase();
     let uuid_lower = uuid.to_lowercase();
+    let earliest_message_time = messages.iter().filter_map(|m| m.timestamp).min();
     let latest_message_time = messages.iter().filter_map(|m| m.timestamp).max();
 
     if !search_blob.is_empty() {
         search_blob.push('\n');
     }
     search_blob.push_str(&label);
+    if let Some(title) = title_meta.as_ref().filter(|title| **title != label) {
+        search_blob.push('\n');
+        search_blob.push_str(title);
+    }
     search_blob.push('\n');
     search_blob.push_str(&uuid);
-    let search_blob_lower = search_blob.to_lowercase();
+    if let Some(cwd) = &cwd {
+        search_blob.push('\n');
+        search_blob.push_str(&cwd.to_string_lossy());
+    }
+    let mut search_blob_lower = search_blob.to_lowercase();
     let search_blob_ws_lower = collapse_ws_lower(&search_blob_lower);
+    if options.low_memory {
+        search_blob_lower = String::new();
+    }
+    let reasoning_ws_lower = collapse_ws_lower(&reasoning.to_lowercase());
 
-    Ok(Some(Session {
+    let archived = options
+        .archive_root
+        .as_ref()
+        .is_some_and(|archive| path.starts_with(archive));
+
+    Session {
         uuid,
         label,
+        title_meta,
         label_lower,
         path,
         created_at,
         updated_at,
+        earliest_message_time,
         latest_message_time,
-        cwd: detected_cwd,
+        cwd,
+        model,
         messages,
         search_blob,
         search_blob_lower,
         search_blob_ws_lower,
         uuid_lower,
-    }))
+        reasoning,
+        reasoning_ws_lower,
+        archived,
+        compacted: options.low_memory,
+    }
 }
 
-fn extract_message(value: &Value, preview_char_limit: usize) -> Option<(Message, String, bool)> {
+/// The raw record at `index` (as stored in `Message::source_line`), pretty-printed.
+/// Lines that are no longer valid JSON are returned as they are.
+pub fn raw_record(path: &Path, index: usize) -> Result<String> {
+    let bytes = read_session(path)?;
+    if is_json_array(path, &bytes) {
+        let records: Vec<Value> =
+            serde_json::from_slice(&bytes).with_context(|| format!("parsing {:?}", path))?;
+        let record = records
+            .get(index)
+            .with_context(|| format!("record {index} no longer exists in {:?}", path))?;
+        return Ok(serde_json::to_string_pretty(record)?);
+    }
+    let line = bytes
+        .split(|b| *b == b'\n')
+        .nth(index)
+        .with_context(|| format!("line {} no longer exists in {:?}", index + 1, path))?;
+    Ok(match serde_json::from_slice::<Value>(line) {
+        Ok(value) => serde_json::to_string_pretty(&value)?,
+        Err(_) => String::from_utf8_lossy(line).into_owned(),
+    })
+}
+
+/// Matches the query against raw file lines (case-insensitive substring), bypassing
+/// message extraction. Scores by the number of matching lines; the snippet comes from
+/// the first one. Label and uuid still come from the filename.
+pub fn search_raw(
+    options: &DiscoveryOptions,
+    query: &str,
+    limit: usize,
+    search: &SearchOptions,
+) -> Result<Vec<SearchResult>> {
+    let query_lower = normalize_query(query).to_lowercase();
+    let keep = DiscoveryOptions {
+        keep_empty: true,
+        ..options.clone()
+    };
+    let mut results: Vec<SearchResult> = collect_session_paths(options)?
+        .into_par_iter()
+        .filter_map(|path| {
+            let file = open_session(&path).ok()?;
+            let mut first_line = None;
+            let mut hits = 0i64;
+            for line in BufReader::new(file).lines().map_while(|line| line.ok()) {
+                if line.to_lowercase().contains(&query_lower) {
+                    hits += 1;
+                    first_line.get_or_insert(line);
+                }
+            }
+            let line = first_line?;
+            let session = load_session_from_path(path, &keep).ok().flatten()?;
+            Some(SearchResult {
+                session: Arc::new(session),
+                matched_message: None,
+                score: hits,
+                text_score: hits,
+                snippet: snippet_from_text(
+                    &line,
+                    &query_lower,
+                    SNIPPET_CONTEXT_CHARS,
+                    &search.ellipsis,
+                ),
+            })
+        })
+        .collect();
+    Scorer::with_options(query, search.clone()).sort(&mut results);
+    results.truncate(limit);
+    Ok(results)
+}
+
+/// Reloads compacted sessions in `results` with full message text and rescores them
+/// so displayed snippets come from the complete messages. Costs one file read per
+/// compacted result.
+pub fn rehydrate_results(
+    results: Vec<SearchResult>,
+    scorer: &mut Scorer,
+    options: &DiscoveryOptions,
+) -> Vec<SearchResult> {
+    let mut rehydrated: Vec<SearchResult> = results
+        .into_iter()
+        .map(|result| {
+            if !result.session.compacted {
+                return result;
+            }
+            load_full_session(&result.session.path, options)
+                .and_then(|session| scorer.score_session_arc(Arc::new(session)))
+                .unwrap_or(result)
+        })
+        .collect();
+    scorer.sort(&mut rehydrated);
+    rehydrated
+}
+
+/// The session at `path` with full message text, whatever `--low-memory` says;
+/// `None` when it can no longer be read.
+pub fn load_full_session(path: &Path, options: &DiscoveryOptions) -> Option<Session> {
+    let full = DiscoveryOptions {
+        low_memory: false,
+        ..options.clone()
+    };
+    load_session_from_path(path.to_path_buf(), &full)
+        .ok()
+        .flatten()
+}
+
+fn extract_message(
+    value: &Value,
+    preview_char_limit: usize,
+    include_tools: bool,
+) -> Option<(Message, String, bool)> {
     // Supported shapes:
     // 1) { type: "response_item", payload: { type: "message", role: "user"|"assistant", content: [...] } }
     // 2) { type: "event_msg", payload: { type: "user_message", message: "..." } }
     // 3) Flat: { role: "user"|"assistant", content: [...] }
+    // 4) With `include_tools`: { type: "response_item", payload: { type: "function_call" | ... } }
     // Special-case: event stream user_message (no content array)
     if let Some(payload) = value.get("payload") {
         let payload_obj = payload.as_object()?;
@@ -240,6 +1483,7 @@ fn extract_message(value: &Value, preview_char_limit: usize) -> Option<(Message,
                         full_text: clipped.clone(),
                         full_text_lower: clipped.to_lowercase(),
                         full_text_ws_lower: collapse_ws_lower(&clipped.to_lowercase()),
+                        source_line: 0,
                     },
                     clipped,
                     is_meta,
@@ -248,6 +1492,24 @@ fn extract_message(value: &Value, preview_char_limit: usize) -> Option<(Message,
             Some("message") => {
                 // Fall through to the generic path below
             }
+            Some(kind) if include_tools && TOOL_RECORD_TYPES.contains(&kind) => {
+                let full_text = extract_tool_text(payload)?;
+                let clipped = clip_chars(&full_text, MAX_MESSAGE_CHARS);
+                let preview = make_preview(&clipped, preview_char_limit);
+                return Some((
+                    Message {
+                        role: MessageRole::Tool,
+                        text: preview,
+                        timestamp: extract_timestamp(value),
+                        full_text: clipped.clone(),
+                        full_text_lower: clipped.to_lowercase(),
+                        full_text_ws_lower: collapse_ws_lower(&clipped.to_lowercase()),
+                        source_line: 0,
+                    },
+                    clipped,
+                    false,
+                ));
+            }
             _ => return None,
         }
     }
@@ -295,12 +1557,141 @@ fn extract_message(value: &Value, preview_char_limit: usize) -> Option<(Message,
             full_text: clipped.clone(),
             full_text_lower: clipped.to_lowercase(),
             full_text_ws_lower: collapse_ws_lower(&clipped.to_lowercase()),
+            source_line: 0,
         },
         clipped,
         is_meta,
     ))
 }
 
+/// Payload types of tool calls and their results, extracted with `include_tools`.
+const TOOL_RECORD_TYPES: &[&str] = &[
+    "function_call",
+    "local_shell_call",
+    "custom_tool_call",
+    "function_call_output",
+    "custom_tool_call_output",
+];
+
+/// Searchable text of a tool record: the command line of shell calls, `name arguments`
+/// for other calls, and the output text of results.
+fn extract_tool_text(payload: &Value) -> Option<String> {
+    let text = match payload.get("type").and_then(Value::as_str)? {
+        "local_shell_call" => command_text(payload.get("action")?.get("command")?)?,
+        "function_call" | "custom_tool_call" => {
+            let name = payload.get("name").and_then(Value::as_str).unwrap_or("");
+            let raw = payload
+                .get("arguments")
+                .or_else(|| payload.get("input"))
+                .and_then(Value::as_str)
+                .unwrap_or("");
+            let command = serde_json::from_str::<Value>(raw)
+                .ok()
+                .and_then(|args| args.get("command").and_then(command_text));
+            match command {
+                Some(command) => command,
+                None => format!("{name} {raw}"),
+            }
+        }
+        _ => match payload.get("output")? {
+            // Shell results wrap the text as `{"output": "...", "metadata": {...}}`.
+            Value::String(raw) => serde_json::from_str::<Value>(raw)
+                .ok()
+                .and_then(|v| v.get("output").and_then(Value::as_str).map(str::to_owned))
+                .unwrap_or_else(|| raw.clone()),
+            output => extract_text(output.get("content").unwrap_or(output))?,
+        },
+    };
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_owned())
+}
+
+/// A shell command given as a string or an argv array; `bash -lc SCRIPT` yields the script.
+fn command_text(command: &Value) -> Option<String> {
+    match command {
+        Value::String(command) => Some(command.clone()),
+        Value::Array(argv) => {
+            let argv: Vec<&str> = argv.iter().filter_map(Value::as_str).collect();
+            match argv.as_slice() {
+                [_, "-lc" | "-c", script] => Some(script.to_string()),
+                [] => None,
+                argv => Some(shell_words::join(argv)),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// A human-readable title from a metadata record (`session_meta` and similar):
+/// `title`, else a string `summary`, on the record or its payload. Message records
+/// never supply one.
+fn extract_title(value: &Value) -> Option<String> {
+    let payload = value.get("payload").unwrap_or(value);
+    if payload.get("role").is_some() || value.get("role").is_some() {
+        return None;
+    }
+    ["title", "summary"].iter().find_map(|key| {
+        let text = payload.get(*key).or_else(|| value.get(*key))?.as_str()?;
+        let line = text.lines().next()?.trim();
+        (!line.is_empty()).then(|| clip_chars(line, MAX_TITLE_CHARS))
+    })
+}
+
+/// The `model` of a `turn_context` or `session_meta` record (or any other non-message
+/// record carrying one).
+fn extract_model(value: &Value) -> Option<String> {
+    let payload = value.get("payload").unwrap_or(value);
+    if payload.get("role").is_some() || value.get("role").is_some() {
+        return None;
+    }
+    let model = payload
+        .get("model")
+        .or_else(|| value.get("model"))?
+        .as_str()?
+        .trim();
+    (!model.is_empty()).then(|| clip_chars(model, MAX_TITLE_CHARS))
+}
+
+/// Assistant reasoning text, either from a standalone `reasoning` record
+/// (`summary`/`content` arrays) or from `reasoning`/`thinking` items inside message content.
+fn extract_reasoning(value: &Value) -> Option<String> {
+    let payload = value.get("payload").unwrap_or(value);
+    let parts: Vec<&Value> = if payload.get("type").and_then(Value::as_str) == Some("reasoning") {
+        ["summary", "content"]
+            .iter()
+            .filter_map(|key| payload.get(*key).and_then(Value::as_array))
+            .flatten()
+            .collect()
+    } else {
+        payload
+            .get("content")
+            .and_then(Value::as_array)
+            .map(|items| {
+                items
+                    .iter()
+                    .filter(|item| {
+                        matches!(
+                            item.get("type").and_then(Value::as_str),
+                            Some("reasoning" | "thinking")
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let text = parts
+        .iter()
+        .filter_map(|item| {
+            item.get("text")
+                .or_else(|| item.get("thinking"))
+                .and_then(Value::as_str)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if text.is_empty() { None } else { Some(text) }
+}
+
 fn extract_text(content: &Value) -> Option<String> {
     match content {
         Value::String(s) => Some(s.clone()),
@@ -310,22 +1701,16 @@ fn extract_text(content: &Value) -> Option<String> {
                 if let Value::Object(map) = item {
                     // Only include visible user/assistant text; skip thinking/tool internals.
                     let allowed = match map.get("type").and_then(Value::as_str) {
-                        Some(t) => matches!(
-                            t,
-                            "input_text"
-                                | "output_text"
-                                | "assistant_text"
-                                | "text"
-                        ),
+                        Some(t) => {
+                            matches!(t, "input_text" | "output_text" | "assistant_text" | "text")
+                        }
                         None => true,
                     };
-                    if allowed {
-                        if let Some(Value::String(text)) = map.get("text") {
+                    if allowed && let Some(Value::String(text)) = map.get("text") {
                         if !acc.is_empty() {
                             acc.push('\n');
                         }
                         acc.push_str(text);
-                        }
                     }
                 }
             }
@@ -340,10 +1725,10 @@ fn extract_text(content: &Value) -> Option<String> {
 }
 
 fn extract_timestamp(value: &Value) -> Option<OffsetDateTime> {
-    if let Some(payload) = value.get("payload") {
-        if let Some(ts) = extract_timestamp(payload) {
-            return Some(ts);
-        }
+    if let Some(payload) = value.get("payload")
+        && let Some(ts) = extract_timestamp(payload)
+    {
+        return Some(ts);
     }
 
     value
@@ -383,6 +1768,18 @@ fn is_meta_text(text: &str) -> bool {
         .any(|marker| trimmed.starts_with(marker))
 }
 
+/// Appends `next` to `target` as one turn; `target` keeps its timestamp.
+fn merge_message(target: &mut Message, next: &Message, preview_char_limit: usize) {
+    let merged = format!("{}\n{}", target.full_text, next.full_text);
+    target.full_text = clip_chars(&merged, MAX_MESSAGE_CHARS);
+    target.full_text_lower = target.full_text.to_lowercase();
+    target.full_text_ws_lower = collapse_ws_lower(&target.full_text_lower);
+    target.text = make_preview(&target.full_text, preview_char_limit);
+    if target.timestamp.is_none() {
+        target.timestamp = next.timestamp;
+    }
+}
+
 fn make_preview(full_text: &str, limit: usize) -> String {
     let trimmed = full_text.trim();
     if limit == 0 {
@@ -405,8 +1802,10 @@ fn make_preview(full_text: &str, limit: usize) -> String {
 
 fn extract_cwd_from_text(text: &str) -> Option<PathBuf> {
     static CWD_RE: Lazy<Regex> = Lazy::new(|| {
-        Regex::new(r"(?si)<environment_context>.*?<cwd>(?P<cwd>[^<]+)</cwd>.*?</environment_context>")
-            .expect("invalid cwd regex")
+        Regex::new(
+            r"(?si)<environment_context>.*?<cwd>(?P<cwd>[^<]+)</cwd>.*?</environment_context>",
+        )
+        .expect("invalid cwd regex")
     });
     let trimmed = text.trim();
     if let Some(caps) = CWD_RE.captures(trimmed) {
@@ -417,23 +1816,62 @@ fn extract_cwd_from_text(text: &str) -> Option<PathBuf> {
     None
 }
 
-fn expand_tilde(p: &str) -> String {
-    if p.starts_with("~/") {
-        if let Some(home) = directories::BaseDirs::new().map(|b| b.home_dir().to_path_buf()) {
-            let mut s = home.to_string_lossy().to_string();
-            if !s.ends_with('/') { s.push('/'); }
-            s.push_str(&p[2..]);
-            return s;
-        }
+/// A top-level or `payload` `cwd`/`workdir` string, as newer formats store it on
+/// `session_meta` and `turn_context` records. Message records are skipped.
+fn extract_cwd_field(value: &Value) -> Option<PathBuf> {
+    let payload = value.get("payload").unwrap_or(value);
+    if payload.get("role").is_some() || value.get("role").is_some() {
+        return None;
     }
-    p.to_string()
+    ["cwd", "workdir"]
+        .iter()
+        .filter_map(|key| payload.get(*key).or_else(|| value.get(*key)))
+        .filter_map(Value::as_str)
+        .map(str::trim)
+        .find(|cwd| !cwd.is_empty())
+        .map(|cwd| PathBuf::from(expand_tilde(cwd)))
+}
+
+pub(crate) fn expand_tilde(p: &str) -> String {
+    match directories::BaseDirs::new() {
+        Some(base) => expand_home(p, &base.home_dir().to_string_lossy()),
+        None => p.to_string(),
+    }
+}
+
+/// Replaces a leading `~/`, `~\` or `%USERPROFILE%` with `home`, keeping the
+/// separator style of the rest of the path.
+fn expand_home(p: &str, home: &str) -> String {
+    let rest = if let Some(rest) = p.strip_prefix("~/").or_else(|| p.strip_prefix("~\\")) {
+        rest
+    } else if p
+        .get(..13)
+        .is_some_and(|head| head.eq_ignore_ascii_case("%USERPROFILE%"))
+    {
+        p[13..].trim_start_matches(['/', '\\'])
+    } else {
+        return p.to_string();
+    };
+    let separator = if p.contains('\\') { '\\' } else { '/' };
+    let mut s = home.trim_end_matches(['/', '\\']).to_string();
+    s.push(separator);
+    s.push_str(rest);
+    s
 }
 
 fn clip_chars(text: &str, limit: usize) -> String {
-    if text.chars().count() <= limit { return text.to_owned(); }
+    if text.chars().count() <= limit {
+        return text.to_owned();
+    }
     let mut it = text.chars();
     let mut out = String::with_capacity(limit + 1);
-    for _ in 0..limit { if let Some(ch) = it.next() { out.push(ch); } else { break; } }
+    for _ in 0..limit {
+        if let Some(ch) = it.next() {
+            out.push(ch);
+        } else {
+            break;
+        }
+    }
     out.push('…');
     out
 }
@@ -455,23 +1893,55 @@ fn collapse_ws_lower(s: &str) -> String {
     out.trim().to_string()
 }
 
-fn parse_from_filename(path: &Path) -> Result<(String, Option<OffsetDateTime>, String)> {
+/// Compiles a custom session filename pattern, matched against the file stem. It must
+/// have a `uuid` named group; `label` and `datetime` (`YYYY-MM-DDTHH-MM-SS`) are optional.
+pub fn compile_filename_regex(pattern: &str) -> Result<Regex> {
+    let regex = Regex::new(pattern).context("invalid filename regex")?;
+    if !regex.capture_names().any(|name| name == Some("uuid")) {
+        anyhow::bail!(
+            "filename regex needs a `(?P<uuid>...)` group (optional: `label`, `datetime`)"
+        );
+    }
+    Ok(regex)
+}
+
+fn parse_from_filename(
+    path: &Path,
+    custom: Option<&Regex>,
+) -> Result<(String, Option<OffsetDateTime>, String)> {
+    // `label-YYYY-MM-DDTHH-MM-SS-uuid`, with `_` or `.` also accepted as separators
+    // and the label optional.
     static SESSION_RE: Lazy<Regex> = Lazy::new(|| {
         Regex::new(
-            r"^(?P<label>.+?)-(?P<datetime>\d{4}-\d{2}-\d{2}T\d{2}-\d{2}-\d{2})-(?P<uuid>[0-9a-fA-F-]+)$",
+            r"^(?:(?P<label>.+?)[-_.])?(?P<datetime>\d{4}-\d{2}-\d{2}[T_ ]\d{2}[-:.]\d{2}[-:.]\d{2})[-_.](?P<uuid>[0-9a-fA-F-]+)$",
+        )
+        .expect("invalid session regex")
+    });
+    // No datetime: an optional label (e.g. `rollout`) before a canonical 36-char uuid.
+    static UUID_RE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r"^(?:(?P<label>.+?)[-_.])?(?P<uuid>[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12})$",
         )
         .expect("invalid session regex")
     });
 
-    let stem = path
+    let mut stem = path
         .file_stem()
         .and_then(|s| s.to_str())
         .context("invalid utf-8 in session filename")?;
+    if is_gzipped(path) {
+        // `name.jsonl.gz`: drop the inner extension too.
+        stem = &stem[..stem.len() - ".jsonl".len()];
+    }
 
-    if let Some(caps) = SESSION_RE.captures(stem) {
+    let caps = match custom {
+        Some(regex) => regex.captures(stem),
+        None => SESSION_RE.captures(stem).or_else(|| UUID_RE.captures(stem)),
+    };
+    if let Some(caps) = caps {
         let label = caps
             .name("label")
-            .map(|m| m.as_str().replace('-', " "))
+            .map(|m| m.as_str().replace(['-', '_'], " "))
             .unwrap_or_else(|| "session".into());
         let datetime = caps.name("datetime").map(|m| m.as_str());
         let uuid = caps
@@ -491,9 +1961,9 @@ fn parse_filename_datetime(raw: &str) -> Result<OffsetDateTime> {
     use time::macros::format_description;
 
     let (date, time_part) = raw
-        .split_once('T')
+        .split_once(['T', '_', ' '])
         .context("missing 'T' separator in filename timestamp")?;
-    let time_part = time_part.replace('-', ":");
+    let time_part = time_part.replace(['-', '.'], ":");
     let candidate = format!("{date}T{time_part}");
     let format = format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]");
     let naive = PrimitiveDateTime::parse(&candidate, &format)?;
@@ -518,3 +1988,986 @@ fn parse_datetime_string(raw: &str) -> Result<OffsetDateTime> {
 fn system_time_to_offset(time: SystemTime) -> OffsetDateTime {
     OffsetDateTime::from(time)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_session(dir: &Path, name: &str, lines: &[&str]) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        path
+    }
+
+    fn options() -> DiscoveryOptions {
+        DiscoveryOptions {
+            roots: vec![std::env::temp_dir()],
+            ..DiscoveryOptions::with_defaults().unwrap()
+        }
+    }
+
+    #[test]
+    fn meta_only_session_is_kept_when_requested() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_session(
+            dir.path(),
+            "meta-only-2025-01-01T00-00-00-abcd.jsonl",
+            &[
+                r#"{"role":"user","content":"<environment_context><cwd>/tmp</cwd></environment_context>"}"#,
+            ],
+        );
+
+        assert!(
+            load_session_from_path(path.clone(), &options())
+                .unwrap()
+                .is_none()
+        );
+
+        let keep = DiscoveryOptions {
+            keep_empty: true,
+            ..options()
+        };
+        let session = load_session_from_path(path, &keep).unwrap().unwrap();
+        assert!(session.messages.is_empty());
+        assert_eq!(session.label, "meta only");
+        assert!(session.search_blob_ws_lower.contains("meta only"));
+    }
+
+    #[test]
+    fn validate_reports_every_file_past_the_scan_limit() {
+        let dir =
+            std::env::temp_dir().join(format!("codex-search-validate-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, lines: &[&str]| {
+            let path = dir.join(name);
+            std::fs::write(&path, lines.join("\n")).unwrap();
+            path
+        };
+        let user = r#"{"role":"user","content":"fix the build"}"#;
+        let ok = write("ok-2025-01-01T00-00-00-0001.jsonl", &[user, user]);
+        let empty = write(
+            "empty-2025-01-01T00-00-00-0002.jsonl",
+            &[
+                r#"{"role":"user","content":"<environment_context><cwd>/tmp</cwd></environment_context>"}"#,
+            ],
+        );
+        let broken = write(
+            "broken-2025-01-01T00-00-00-0003.jsonl",
+            &[user, "{not json", user],
+        );
+
+        let limited = DiscoveryOptions {
+            roots: vec![dir.clone()],
+            scan_limit: 1,
+            ..options()
+        };
+        let mut reports = validate_sessions(&limited).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        reports.sort_by(|a, b| a.path.cmp(&b.path));
+        let statuses: Vec<_> = reports
+            .iter()
+            .map(|r| (r.path.clone(), r.status.clone()))
+            .collect();
+        assert_eq!(statuses.len(), 3);
+        assert_eq!(statuses[0].0, broken);
+        assert!(
+            matches!(&statuses[0].1, FileStatus::Error { reason } if reason.starts_with("line 2:")),
+            "{:?}",
+            statuses[0].1
+        );
+        assert_eq!(statuses[1].0, empty);
+        assert!(matches!(statuses[1].1, FileStatus::Empty));
+        assert_eq!(statuses[2].0, ok);
+        assert!(matches!(statuses[2].1, FileStatus::Ok { messages: 2 }));
+    }
+
+    #[test]
+    fn sessions_dir_falls_back_to_the_first_existing_layout() {
+        let home =
+            std::env::temp_dir().join(format!("codex-search-layout-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&home);
+        let home = home.as_path();
+        let codex_home = home.join("codex-home");
+        let candidates = || sessions_dir_candidates_in(home, Some(codex_home.clone().into()));
+        assert_eq!(
+            candidates(),
+            [
+                home.join(".codex/sessions"),
+                home.join(".config/codex/sessions"),
+                codex_home.join("sessions"),
+                home.join("Library/Application Support/codex/sessions"),
+            ]
+        );
+        assert_eq!(sessions_dir_candidates_in(home, Some("".into())).len(), 3);
+
+        assert_eq!(
+            first_existing_dir(candidates()),
+            home.join(".codex/sessions")
+        );
+        std::fs::create_dir_all(home.join("Library/Application Support/codex/sessions")).unwrap();
+        std::fs::create_dir_all(codex_home.join("sessions")).unwrap();
+        assert_eq!(
+            first_existing_dir(candidates()),
+            codex_home.join("sessions")
+        );
+        std::fs::create_dir_all(home.join(".codex/sessions")).unwrap();
+        assert_eq!(
+            first_existing_dir(candidates()),
+            home.join(".codex/sessions")
+        );
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn low_memory_results_are_rehydrated_with_full_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let long = format!("{} needle at the end", "filler ".repeat(100));
+        let line = format!(r#"{{"role":"user","content":"{long}"}}"#);
+        let path = write_session(
+            dir.path(),
+            "low-memory-2025-01-01T00-00-00-beef.jsonl",
+            &[&line],
+        );
+
+        let low = DiscoveryOptions {
+            low_memory: true,
+            ..options()
+        };
+        let session = load_session_from_path(path, &low).unwrap().unwrap();
+        assert!(session.compacted);
+        assert!(session.messages[0].full_text.len() < long.len());
+        assert!(session.search_blob_ws_lower.contains("needle"));
+
+        let mut scorer = Scorer::new("needle");
+        let result = scorer.score_session_arc(Arc::new(session)).unwrap();
+        let results = rehydrate_results(vec![result], &mut scorer, &low);
+        assert!(!results[0].session.compacted);
+        assert_eq!(results[0].session.messages[0].full_text, long);
+    }
+
+    #[test]
+    fn reasoning_is_indexed_only_when_requested() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_session(
+            dir.path(),
+            "reasoning-2025-01-01T00-00-00-cafe.jsonl",
+            &[
+                r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"fix the build"}]}}"#,
+                r#"{"type":"response_item","payload":{"type":"reasoning","summary":[{"type":"summary_text","text":"Considering the linker flags"}]}}"#,
+            ],
+        );
+
+        let session = load_session_from_path(path.clone(), &options())
+            .unwrap()
+            .unwrap();
+        assert!(session.reasoning.is_empty());
+
+        let with_reasoning = DiscoveryOptions {
+            include_reasoning: true,
+            ..options()
+        };
+        let session = load_session_from_path(path, &with_reasoning)
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.reasoning, "Considering the linker flags");
+        assert_eq!(session.messages.len(), 1);
+        assert!(!session.search_blob_ws_lower.contains("linker"));
+        assert!(
+            Scorer::new("linker flags")
+                .score_session(&session)
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn tool_calls_are_extracted_only_when_requested() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_session(
+            dir.path(),
+            "tools-2025-01-01T00-00-00-7001.jsonl",
+            &[
+                r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"run the release tests"}]}}"#,
+                r#"{"type":"response_item","payload":{"type":"function_call","name":"shell","arguments":"{\"command\":[\"bash\",\"-lc\",\"cargo test --release\"]}","call_id":"c1"}}"#,
+                r#"{"type":"response_item","payload":{"type":"function_call_output","call_id":"c1","output":"{\"output\":\"test result: ok. 12 passed\",\"metadata\":{\"exit_code\":0}}"}}"#,
+                r#"{"type":"response_item","payload":{"type":"local_shell_call","action":{"type":"exec","command":["git","commit","-m","fix build"]}}}"#,
+                r#"{"type":"response_item","payload":{"type":"custom_tool_call","name":"apply_patch","input":"*** Begin Patch"}}"#,
+            ],
+        );
+
+        let session = load_session_from_path(path.clone(), &options())
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.messages.len(), 1);
+        assert!(!session.search_blob_ws_lower.contains("cargo"));
+
+        let with_tools = DiscoveryOptions {
+            include_tools: true,
+            ..options()
+        };
+        let session = load_session_from_path(path, &with_tools).unwrap().unwrap();
+        let tools: Vec<(&MessageRole, &str)> = session
+            .messages
+            .iter()
+            .map(|m| (&m.role, m.full_text.as_str()))
+            .skip(1)
+            .collect();
+        assert_eq!(
+            tools,
+            [
+                (&MessageRole::Tool, "cargo test --release"),
+                (&MessageRole::Tool, "test result: ok. 12 passed"),
+                (&MessageRole::Tool, "git commit -m 'fix build'"),
+                (&MessageRole::Tool, "apply_patch *** Begin Patch"),
+            ]
+        );
+        let tool_only = SearchOptions {
+            role: crate::search::RoleFilter::Tool,
+            ..SearchOptions::default()
+        };
+        let results =
+            crate::search::search_sessions(&[session], "cargo test", 10, &tool_only).unwrap();
+        let matched = results[0].matched_message.as_ref().unwrap();
+        assert_eq!(matched.full_text, "cargo test --release");
+    }
+
+    #[test]
+    fn tool_output_does_not_crowd_conversation_out_of_the_blob() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = format!(
+            r#"{{"type":"response_item","payload":{{"type":"function_call_output","call_id":"c1","output":"{}"}}}}"#,
+            "log line ".repeat(800)
+        );
+        let mut lines: Vec<&str> = vec![output.as_str(); 16];
+        lines.push(
+            r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"now bisect the flaky test"}]}}"#,
+        );
+        let path = write_session(dir.path(), "tools-2025-01-01T00-00-00-7002.jsonl", &lines);
+        let with_tools = DiscoveryOptions {
+            include_tools: true,
+            ..options()
+        };
+        let session = load_session_from_path(path, &with_tools).unwrap().unwrap();
+        assert!(
+            session
+                .search_blob_ws_lower
+                .contains("bisect the flaky test")
+        );
+        assert!(session.search_blob_ws_lower.len() < TOOL_BLOB_LIMIT + 1024);
+    }
+
+    #[test]
+    fn cwd_is_part_of_the_search_blob() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_session(
+            dir.path(),
+            "where-2025-01-01T00-00-00-c0de.jsonl",
+            &[
+                r#"{"type":"session_meta","payload":{"cwd":"/work/payments-service"}}"#,
+                r#"{"role":"user","content":"why is this slow"}"#,
+            ],
+        );
+        let session = load_session_from_path(path, &options()).unwrap().unwrap();
+        assert!(session.search_blob_ws_lower.contains("payments-service"));
+        let results =
+            crate::search::search_sessions(&[session], "payments", 10, &SearchOptions::default())
+                .unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn messages_beyond_the_cap_are_not_indexed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_session(
+            dir.path(),
+            "capped-2025-01-01T00-00-00-f00d.jsonl",
+            &[
+                r#"{"role":"user","content":"first question"}"#,
+                r#"{"role":"assistant","content":"first answer"}"#,
+                r#"{"type":"response_item","payload":{"type":"reasoning","summary":[{"type":"summary_text","text":"late musing"}]}}"#,
+                r#"{"role":"user","content":"late needle"}"#,
+                r#"{"type":"turn_context","payload":{"cwd":"/tmp/capped","model":"gpt-5-codex"}}"#,
+            ],
+        );
+        let capped = DiscoveryOptions {
+            max_messages: Some(2),
+            include_reasoning: true,
+            ..options()
+        };
+        let session = load_session_from_path(path, &capped).unwrap().unwrap();
+        assert_eq!(session.messages.len(), 2);
+        assert!(!session.search_blob_ws_lower.contains("needle"));
+        assert!(session.search_blob_ws_lower.contains("capped"));
+        assert!(session.reasoning.is_empty());
+        // Metadata keeps coming from records past the cap.
+        assert_eq!(session.model.as_deref(), Some("gpt-5-codex"));
+        assert_eq!(session.cwd.as_deref(), Some(Path::new("/tmp/capped")));
+    }
+
+    #[test]
+    fn archive_sessions_follow_primary_and_are_marked() {
+        let tmp = tempfile::tempdir().unwrap();
+        let base = tmp.path().to_path_buf();
+        let (primary, archive) = (base.join("sessions"), base.join("archived_sessions"));
+        for (dir, name) in [(&primary, "live"), (&archive, "old")] {
+            std::fs::create_dir_all(dir).unwrap();
+            std::fs::write(
+                dir.join(format!("{name}-2025-01-01T00-00-00-abcd.jsonl")),
+                r#"{"role":"user","content":"hello"}"#,
+            )
+            .unwrap();
+        }
+        let options = DiscoveryOptions {
+            roots: vec![primary],
+            archive_root: Some(default_archive_dir(&base.join("sessions"))),
+            ..options()
+        };
+
+        let sessions: Vec<Session> = collect_session_paths(&options)
+            .unwrap()
+            .into_iter()
+            .map(|path| load_session_from_path(path, &options).unwrap().unwrap())
+            .collect();
+        let labels: Vec<(&str, bool)> = sessions
+            .iter()
+            .map(|s| (s.label.as_str(), s.archived))
+            .collect();
+        assert_eq!(labels, vec![("live", false), ("old", true)]);
+    }
+
+    #[test]
+    fn roots_are_merged_newest_first_under_one_scan_limit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let base = tmp.path().to_path_buf();
+        let (laptop, desktop) = (base.join("laptop"), base.join("desktop"));
+        let epoch = std::time::SystemTime::UNIX_EPOCH;
+        for (dir, name, secs) in [
+            (&laptop, "a", 100),
+            (&desktop, "b", 300),
+            (&laptop, "c", 200),
+        ] {
+            std::fs::create_dir_all(dir).unwrap();
+            let path = dir.join(format!("{name}-2025-01-01T00-00-00-abcd.jsonl"));
+            std::fs::write(&path, r#"{"role":"user","content":"hello"}"#).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(epoch + std::time::Duration::from_secs(secs))
+                .unwrap();
+        }
+        let options = DiscoveryOptions {
+            roots: vec![laptop.clone(), desktop, laptop],
+            scan_limit: 2,
+            ..options()
+        };
+
+        let names: Vec<String> = collect_session_paths(&options)
+            .unwrap()
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy()[..1].to_string())
+            .collect();
+        assert_eq!(names, ["b", "c"]);
+    }
+
+    #[test]
+    fn watcher_loads_sessions_written_after_it_started() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().to_path_buf();
+        std::fs::create_dir_all(&root).unwrap();
+        let options = DiscoveryOptions {
+            roots: vec![root.clone()],
+            ..options()
+        };
+        let watcher = watch_sessions(options).unwrap();
+        std::fs::write(
+            root.join("fresh-2025-01-01T00-00-00-abcd.jsonl"),
+            r#"{"role":"user","content":"just started"}"#,
+        )
+        .unwrap();
+
+        let session = watcher
+            .receiver()
+            .recv_timeout(Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(session.label, "fresh");
+        assert!(session.search_blob_ws_lower.contains("just started"));
+    }
+
+    #[test]
+    fn raw_search_matches_fields_the_extractor_drops() {
+        let tmp = tempfile::tempdir().unwrap();
+        let base = tmp.path().to_path_buf();
+        std::fs::create_dir_all(&base).unwrap();
+        std::fs::write(
+            base.join("raw-2025-01-01T00-00-00-abcd.jsonl"),
+            [
+                r#"{"role":"user","content":"run the tool"}"#,
+                r#"{"type":"function_call","name":"apply_patch","arguments":"{}"}"#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        let options = DiscoveryOptions {
+            roots: vec![base],
+            ..options()
+        };
+
+        let session = &collect_sessions(&options).unwrap()[0];
+        assert!(!session.search_blob_ws_lower.contains("apply_patch"));
+
+        let results = search_raw(&options, "APPLY_PATCH", 10, &SearchOptions::default()).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].score, 1);
+        assert!(
+            results[0]
+                .snippet
+                .segments
+                .iter()
+                .any(|s| s.highlighted && s.text == "apply_patch")
+        );
+    }
+
+    #[test]
+    fn truncated_final_line_keeps_the_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_session(
+            dir.path(),
+            "live-2025-01-01T00-00-00-dead.jsonl",
+            &[
+                r#"{"role":"user","content":"still searchable"}"#,
+                r#"{"role":"assistant","content":"half wri"#,
+            ],
+        );
+        let mut bytes = std::fs::read(&path).unwrap();
+        // A multi-byte character cut in half by the writer.
+        bytes.extend_from_slice(&"é".as_bytes()[..1]);
+        std::fs::write(&path, bytes).unwrap();
+
+        let session = load_session_from_path(path.clone(), &options())
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.messages.len(), 1);
+        let (_, invalid) = load_session_checked(path, &options(), true).unwrap();
+        assert!(invalid.is_none());
+    }
+
+    #[test]
+    fn builtin_filename_parser_handles_real_world_shapes() {
+        let uuid = "0199a1b2-c3d4-7e5f-8a9b-0c1d2e3f4a5b";
+        let parse = |name: &str| {
+            let (label, created_at, parsed) =
+                parse_from_filename(Path::new(&format!("/x/{name}.jsonl")), None).unwrap();
+            (label, created_at.map(|dt| (dt.date(), dt.time())), parsed)
+        };
+        let when = Some((
+            time::macros::date!(2025 - 01 - 02),
+            time::macros::time!(03:04:05),
+        ));
+
+        let cases = [
+            (
+                format!("rollout-2025-01-02T03-04-05-{uuid}"),
+                "rollout",
+                when,
+            ),
+            (format!("my-fix_2025-01-02_03-04-05_{uuid}"), "my fix", when),
+            (format!("2025-01-02T03-04-05-{uuid}"), "session", when),
+            (format!("rollout-{uuid}"), "rollout", None),
+            (format!("rollout.{uuid}"), "rollout", None),
+            (uuid.to_string(), "session", None),
+            // Not a valid date: the uuid and label are still taken from the name.
+            (
+                format!("rollout-2025-13-45T03-04-05-{uuid}"),
+                "rollout",
+                None,
+            ),
+        ];
+        for (name, label, created_at) in cases {
+            assert_eq!(
+                parse(&name),
+                (label.into(), created_at, uuid.into()),
+                "{name}"
+            );
+        }
+
+        // Anything else falls back to the whole stem.
+        assert_eq!(
+            parse("notes-deadbeef"),
+            ("notes-deadbeef".into(), None, "notes-deadbeef".into())
+        );
+    }
+
+    #[test]
+    fn custom_filename_regex_extracts_uuid_without_datetime() {
+        let regex = compile_filename_regex(r"^(?P<label>[a-z]+)_(?P<uuid>[0-9a-f]{8})$").unwrap();
+        let (label, created_at, uuid) =
+            parse_from_filename(Path::new("/x/fork_deadbeef.jsonl"), Some(&regex)).unwrap();
+        assert_eq!((label.as_str(), uuid.as_str()), ("fork", "deadbeef"));
+        assert!(created_at.is_none());
+
+        assert!(compile_filename_regex(r"^(?P<label>.+)$").is_err());
+        assert!(compile_filename_regex(r"(?P<uuid>").is_err());
+    }
+
+    #[test]
+    fn restrict_uuids_prunes_paths_by_filename() {
+        let dir = tempfile::tempdir().unwrap();
+        let keep = write_session(
+            dir.path(),
+            "rollout-2025-01-02T03-04-05-aaaaaaaa-0000-0000-0000-000000000001.jsonl",
+            &[],
+        );
+        let skip = write_session(
+            dir.path(),
+            "rollout-2025-01-02T03-04-05-bbbbbbbb-0000-0000-0000-000000000002.jsonl",
+            &[],
+        );
+        let options = DiscoveryOptions {
+            roots: vec![keep.parent().unwrap().to_path_buf()],
+            scan_limit: usize::MAX,
+            restrict_uuids: Some(HashSet::from([
+                "aaaaaaaa-0000-0000-0000-000000000001".to_string()
+            ])),
+            ..options()
+        };
+        let paths = collect_session_paths(&options).unwrap();
+        assert!(paths.contains(&keep));
+        assert!(!paths.contains(&skip));
+    }
+
+    #[test]
+    fn excluded_directories_and_cwds_never_load() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().to_path_buf();
+        let write = |dir: &str, name: &str, cwd: &str| {
+            let dir = root.join(dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            let path = dir.join(name);
+            std::fs::write(
+                &path,
+                format!(
+                    "{}\n{}",
+                    format_args!(r#"{{"type":"session_meta","payload":{{"cwd":"{cwd}"}}}}"#),
+                    r#"{"role":"user","content":"hello"}"#
+                ),
+            )
+            .unwrap();
+            path
+        };
+        let kept = write(
+            "2025/01/01",
+            "a-2025-01-01T00-00-00-aaaa.jsonl",
+            "/work/app",
+        );
+        write(
+            "scratch/2025",
+            "b-2025-01-01T00-00-00-bbbb.jsonl",
+            "/work/app",
+        );
+        write(
+            "2025/01/02",
+            "c-2025-01-02T00-00-00-cccc.jsonl",
+            "/tmp/scratch/try",
+        );
+
+        let options = DiscoveryOptions {
+            roots: vec![root.clone()],
+            scan_limit: usize::MAX,
+            exclude: Some(ExcludeGlobs::new(&["**/scratch".into()]).unwrap()),
+            ..options()
+        };
+        let paths = collect_session_paths(&options).unwrap();
+        assert_eq!(paths.len(), 2, "{paths:?}");
+        let sessions = collect_sessions(&options).unwrap();
+        let loaded: Vec<&Path> = sessions.iter().map(|s| s.path.as_path()).collect();
+        assert_eq!(loaded, [kept.as_path()]);
+        assert!(ExcludeGlobs::new(&["[".into()]).is_err());
+    }
+
+    #[test]
+    fn merge_turns_folds_adjacent_same_role_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_session(
+            dir.path(),
+            "merge-2025-01-01T00-00-00-abcd.jsonl",
+            &[
+                r#"{"timestamp":"2025-01-01T00:00:01Z","role":"user","content":[{"type":"input_text","text":"fix the"}]}"#,
+                r#"{"timestamp":"2025-01-01T00:00:02Z","role":"user","content":[{"type":"input_text","text":"flaky test"}]}"#,
+                r#"{"timestamp":"2025-01-01T00:00:03Z","role":"assistant","content":[{"type":"output_text","text":"done"}]}"#,
+            ],
+        );
+        let split = load_session_from_path(path.clone(), &options())
+            .unwrap()
+            .unwrap();
+        assert_eq!(split.messages.len(), 3);
+
+        let merged = DiscoveryOptions {
+            merge_turns: true,
+            ..options()
+        };
+        let session = load_session_from_path(path, &merged).unwrap().unwrap();
+        assert_eq!(session.messages.len(), 2);
+        let first = &session.messages[0];
+        assert_eq!(first.full_text, "fix the\nflaky test");
+        assert!(first.full_text_ws_lower.contains("fix the flaky test"));
+        assert_eq!(
+            first.timestamp,
+            Some(OffsetDateTime::parse("2025-01-01T00:00:01Z", &Rfc3339).unwrap())
+        );
+    }
+
+    #[test]
+    fn windows_cwd_is_extracted_and_home_expanded() {
+        let text =
+            "<environment_context>\n  <cwd>C:\\Users\\me\\repo</cwd>\n</environment_context>";
+        assert_eq!(
+            extract_cwd_from_text(text),
+            Some(PathBuf::from(r"C:\Users\me\repo"))
+        );
+        assert_eq!(
+            expand_home(r"%USERPROFILE%\repo", r"C:\Users\me"),
+            r"C:\Users\me\repo"
+        );
+        assert_eq!(expand_home(r"~\repo", r"C:\Users\me\"), r"C:\Users\me\repo");
+        assert_eq!(expand_home("~/repo", "/home/me"), "/home/me/repo");
+        assert_eq!(expand_home("/srv/repo", "/home/me"), "/srv/repo");
+    }
+
+    #[test]
+    fn json_array_export_is_parsed_like_jsonl() {
+        let dir = tempfile::tempdir().unwrap();
+        let records = [
+            r#"{"role":"user","content":[{"type":"input_text","text":"export the ledger"}]}"#,
+            r#"{"role":"assistant","content":[{"type":"output_text","text":"ledger exported"}]}"#,
+        ];
+        let array = format!("[\n  {}\n]\n", records.join(",\n  "));
+        let path = write_session(
+            dir.path(),
+            "export-2025-01-01T00-00-00-beef.json",
+            &[&array],
+        );
+        let session = load_session_from_path(path.clone(), &options())
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.messages.len(), 2);
+        assert_eq!(session.messages[1].full_text, "ledger exported");
+        let (_, invalid) = load_session_checked(path, &options(), false).unwrap();
+        assert!(invalid.is_none());
+
+        let broken = write_session(
+            dir.path(),
+            "broken-2025-01-01T00-00-00-f00d.json",
+            &["[", "{}", "oops"],
+        );
+        let (_, invalid) = load_session_checked(broken, &options(), false).unwrap();
+        assert_eq!(invalid.unwrap().0, 3);
+    }
+
+    #[test]
+    fn stray_json_files_are_not_sessions_even_with_keep_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let keep_empty = DiscoveryOptions {
+            keep_empty: true,
+            ..options()
+        };
+        let config = write_session(
+            dir.path(),
+            "config-2025-01-01T00-00-00-aaaa.json",
+            &[r#"{"theme":"dark"}"#],
+        );
+        let listing = write_session(
+            dir.path(),
+            "deps-2025-01-01T00-00-00-bbbb.json",
+            &[r#"[{"name":"serde"},{"name":"tokio"}]"#],
+        );
+        for path in [&config, &listing] {
+            let session = load_session_from_path(path.clone(), &keep_empty).unwrap();
+            assert!(session.is_none(), "{}", path.display());
+        }
+
+        // Non-array JSON is reported where invalid lines are.
+        let strict = DiscoveryOptions {
+            strict: true,
+            ..keep_empty
+        };
+        let err = load_session_from_path(config, &strict).unwrap_err();
+        assert!(err.to_string().starts_with("line 1:"), "{err}");
+        assert!(load_session_from_path(listing, &strict).unwrap().is_none());
+    }
+
+    #[test]
+    fn messages_point_back_at_their_raw_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_session(
+            dir.path(),
+            "raw-2025-01-01T00-00-00-cafe.jsonl",
+            &[
+                r#"{"type":"session_meta","payload":{"cwd":"/tmp"}}"#,
+                "not json",
+                r#"{"role":"user","content":[{"type":"input_text","text":"find the gremlin"}]}"#,
+            ],
+        );
+        let session = load_session_from_path(path.clone(), &options())
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.messages[0].source_line, 2);
+        let raw = raw_record(&path, session.messages[0].source_line).unwrap();
+        assert!(raw.contains("\"text\": \"find the gremlin\""), "{raw}");
+        assert_eq!(raw_record(&path, 1).unwrap(), "not json");
+        assert!(raw_record(&path, 9).is_err());
+    }
+
+    #[test]
+    fn gzipped_sessions_load_like_plain_ones() {
+        use flate2::Compression;
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().to_path_buf();
+        std::fs::create_dir_all(&root).unwrap();
+        let path = root.join("rollout-2025-01-01T00-00-00-abcd.jsonl.gz");
+        // Two gzip members, as appending to a compressed log produces.
+        let mut file = File::create(&path).unwrap();
+        for text in ["compressed history", "appended later"] {
+            let mut encoder = GzEncoder::new(&mut file, Compression::default());
+            writeln!(
+                encoder,
+                r#"{{"role":"user","content":[{{"type":"input_text","text":"{text}"}}]}}"#
+            )
+            .unwrap();
+            encoder.finish().unwrap();
+        }
+
+        let options = DiscoveryOptions {
+            roots: vec![root.clone()],
+            ..options()
+        };
+        assert_eq!(collect_session_paths(&options).unwrap(), vec![path.clone()]);
+        let session = load_session_from_path(path.clone(), &options)
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.label, "rollout");
+        assert_eq!(session.uuid, "abcd");
+        assert_eq!(session.messages[0].text, "compressed history");
+        assert_eq!(session.messages[1].text, "appended later");
+        assert!(raw_record(&path, 0).unwrap().contains("compressed history"));
+    }
+
+    #[test]
+    fn model_comes_from_the_latest_context_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let message = r#"{"role":"user","content":[{"type":"input_text","text":"hi"}]}"#;
+        let lines = [
+            r#"{"type":"session_meta","payload":{"cwd":"/tmp","model":"gpt-5"}}"#,
+            message,
+            r#"{"type":"turn_context","payload":{"cwd":"/tmp","model":"gpt-5-codex"}}"#,
+            message,
+        ];
+        let path = write_session(dir.path(), "model-2025-01-01T00-00-00-abcd.jsonl", &lines);
+        let session = load_session_from_path(path, &options()).unwrap().unwrap();
+        assert_eq!(session.model.as_deref(), Some("gpt-5-codex"));
+        assert!(
+            serde_json::to_string(&session)
+                .unwrap()
+                .contains(r#""model":"gpt-5-codex""#)
+        );
+
+        let path = write_session(
+            dir.path(),
+            "nomodel-2025-01-01T00-00-00-abcd.jsonl",
+            &[message],
+        );
+        let session = load_session_from_path(path, &options()).unwrap().unwrap();
+        assert_eq!(session.model, None);
+    }
+
+    #[test]
+    fn metadata_title_replaces_the_filename_label() {
+        let dir = tempfile::tempdir().unwrap();
+        let lines = [
+            r#"{"type":"session_meta","payload":{"cwd":"/tmp","title":"Fix the flaky upload test"}}"#,
+            r#"{"role":"user","content":[{"type":"input_text","text":"uploads fail"}]}"#,
+        ];
+        let path = write_session(dir.path(), "rollout-2025-01-01T00-00-00-abcd.jsonl", &lines);
+        let session = load_session_from_path(path.clone(), &options())
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.label, "Fix the flaky upload test");
+
+        let filename_labels = DiscoveryOptions {
+            filename_labels: true,
+            ..options()
+        };
+        let session = load_session_from_path(path, &filename_labels)
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.label, "rollout");
+        assert_eq!(
+            session.title_meta.as_deref(),
+            Some("Fix the flaky upload test")
+        );
+        assert!(session.search_blob.contains("flaky upload"));
+    }
+
+    #[test]
+    fn strict_mode_reports_every_broken_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().to_path_buf();
+        std::fs::create_dir_all(&root).unwrap();
+        let message = r#"{"role":"user","content":[{"type":"input_text","text":"hi"}]}"#;
+        std::fs::write(
+            root.join("good-2025-01-01T00-00-00-aaaa.jsonl"),
+            format!("{message}\n{{\"partial"),
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("bad-2025-01-02T00-00-00-bbbb.jsonl"),
+            format!("{message}\n{{oops\n{message}\n"),
+        )
+        .unwrap();
+        let lenient = DiscoveryOptions {
+            roots: vec![root.clone()],
+            ..options()
+        };
+        assert_eq!(collect_sessions(&lenient).unwrap().len(), 2);
+
+        let strict = DiscoveryOptions {
+            strict: true,
+            ..lenient
+        };
+        let err = collect_sessions(&strict).unwrap_err().to_string();
+        assert!(err.starts_with("1 session file failed to parse"), "{err}");
+        assert!(
+            err.contains("bad-2025-01-02T00-00-00-bbbb.jsonl: line 2"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn sniffing_rules_out_only_clear_non_sessions() {
+        let telemetry = r#"{"event":"startup","ms":12}"#.to_string() + "\n";
+        assert!(!looks_like_conversation(telemetry.repeat(10).as_bytes()));
+        assert!(!looks_like_conversation(b"\x7fELF\x02\x01\x00\x00\n"));
+        // Too few lines to judge, or a conversation record within the head.
+        assert!(looks_like_conversation(telemetry.repeat(3).as_bytes()));
+        let legacy = r#"{"id":"x","timestamp":"2025-01-01"}"#.to_string()
+            + "\n"
+            + &telemetry.repeat(4)
+            + r#"{"record_type":"state"}"#
+            + "\n"
+            + &telemetry.repeat(4);
+        assert!(looks_like_conversation(legacy.as_bytes()));
+        let session = r#"{"type":"session_meta","payload":{}}"#.to_string() + "\n";
+        assert!(looks_like_conversation(
+            (session + &telemetry.repeat(9)).as_bytes()
+        ));
+    }
+
+    #[test]
+    fn stdin_stream_splits_on_session_meta() {
+        let stream = [
+            r#"{"type":"session_meta","payload":{"id":"aaaa-1111","timestamp":"2025-01-01T00:00:00Z"}}"#,
+            r#"{"role":"user","content":[{"type":"input_text","text":"first session"}]}"#,
+            "garbage",
+            r#"{"type":"session_meta","payload":{"id":"bbbb-2222"}}"#,
+            r#"{"role":"user","content":[{"type":"input_text","text":"second session"}]}"#,
+        ]
+        .join("\n");
+        let sessions = sessions_from_reader(stream.as_bytes(), &options()).unwrap();
+        let uuids: Vec<&str> = sessions.iter().map(|s| s.uuid.as_str()).collect();
+        assert_eq!(uuids, ["aaaa-1111", "bbbb-2222"]);
+        assert_eq!(sessions[1].messages[0].full_text, "second session");
+        assert!(sessions[0].created_at.is_some());
+
+        let bare = r#"{"role":"user","content":[{"type":"input_text","text":"hi"}]}"#;
+        let sessions = sessions_from_reader(bare.as_bytes(), &options()).unwrap();
+        assert_eq!(sessions[0].uuid, "stdin-1");
+    }
+
+    #[test]
+    fn unicode_normalization_matches_decomposed_text() {
+        // "café" stored with a combining acute accent, queried precomposed.
+        let record =
+            r#"{"role":"user","content":[{"type":"input_text","text":"cafe\u0301 menu"}]}"#;
+        let query = "caf\u{e9}";
+        let search = |normalize: bool| {
+            let options = DiscoveryOptions {
+                unicode_normalize: normalize,
+                ..options()
+            };
+            let sessions = sessions_from_reader(record.as_bytes(), &options).unwrap();
+            let search = SearchOptions {
+                unicode_normalize: normalize,
+                ..SearchOptions::default()
+            };
+            crate::search::search_sessions(&sessions, query, 10, &search)
+                .unwrap()
+                .len()
+        };
+        assert_eq!(search(false), 0);
+        assert_eq!(search(true), 1);
+    }
+
+    #[test]
+    fn index_reuses_unchanged_files_and_drops_missing_ones() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().to_path_buf();
+        std::fs::create_dir_all(&root).unwrap();
+        let line = |text: &str| {
+            format!(r#"{{"role":"user","content":[{{"type":"input_text","text":"{text}"}}]}}"#)
+        };
+        let first = root.join("a-2025-01-01T00-00-00-aaaa.jsonl");
+        let second = root.join("b-2025-01-02T00-00-00-bbbb.jsonl");
+        std::fs::write(&first, line("first")).unwrap();
+        std::fs::write(&second, line("second")).unwrap();
+        let options = DiscoveryOptions {
+            roots: vec![root.clone()],
+            index_path: Some(root.join("cache").join("index.bin")),
+            ..options()
+        };
+        let texts = |options: &DiscoveryOptions| {
+            let mut texts: Vec<String> = collect_sessions(options)
+                .unwrap()
+                .into_iter()
+                .map(|s| s.messages[0].full_text.clone())
+                .collect();
+            texts.sort();
+            texts
+        };
+        assert_eq!(texts(&options), ["first", "second"]);
+        assert_eq!(load_index(&options).entries.len(), 2);
+        assert_eq!(texts(&options), ["first", "second"]);
+
+        std::fs::write(&first, line("first, edited")).unwrap();
+        std::fs::remove_file(&second).unwrap();
+        assert_eq!(texts(&options), ["first, edited"]);
+        assert_eq!(load_index(&options).entries.len(), 1);
+
+        let other = DiscoveryOptions {
+            include_reasoning: true,
+            ..options.clone()
+        };
+        assert!(load_index(&other).entries.is_empty());
+    }
+
+    #[test]
+    fn corrupt_index_is_ignored() {
+        let tmp = tempfile::tempdir().unwrap();
+        let options = DiscoveryOptions {
+            index_path: Some(tmp.path().join("index.bin")),
+            ..options()
+        };
+        // A version and options key, then a huge entry count with no entries behind it.
+        let key = index_options_key(&options);
+        let mut bytes = INDEX_VERSION.to_le_bytes().to_vec();
+        bytes.extend((key.len() as u64).to_le_bytes());
+        bytes.extend(key.as_bytes());
+        bytes.extend(u64::MAX.to_le_bytes());
+        std::fs::write(tmp.path().join("index.bin"), bytes).unwrap();
+        let index = load_index(&options);
+        assert!(index.entries.is_empty());
+        assert!(index.changed);
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use time::OffsetDateTime;
+
+use crate::search::{Scorer, normalize_query};
+use crate::session::Session;
+
+/// Inverted index from lowercase word tokens to the sessions containing them, so a
+/// query only scores sessions that can match instead of every loaded session.
+///
+/// A session can match a query by text only if every query term occurs in its
+/// blob, reasoning, label or uuid, so every run of word characters in each term
+/// occurs inside one of its tokens. Tokens containing a fragment are found through
+/// a trigram index over the vocabulary, so a lookup touches only the tokens sharing
+/// the fragment's rarest trigram. Fuzzy label/uuid matches cannot be looked up and
+/// are checked on every session by `candidates`.
+#[derive(Debug, Default)]
+pub struct SessionIndex {
+    /// Sessions are identified by path, with the file time they were indexed at;
+    /// ids are handed out in insertion order.
+    ids: HashMap<PathBuf, (u32, OffsetDateTime)>,
+    next_id: u32,
+    /// Session id -> its token ids, to purge its postings when it is re-indexed.
+    session_tokens: HashMap<u32, Vec<u32>>,
+    /// Token id -> token text; empty for ids on `free_tokens`.
+    tokens: Vec<String>,
+    token_ids: HashMap<String, u32>,
+    free_tokens: Vec<u32>,
+    /// Token id -> ids of the sessions containing it, ascending.
+    postings: Vec<Vec<u32>>,
+    /// Trigram -> ids of the tokens containing it.
+    trigrams: HashMap<[char; 3], Vec<u32>>,
+}
+
+impl SessionIndex {
+    /// Indexes `session` unless its path is already indexed at the same file time.
+    /// A reloaded session gets a fresh id and the postings of the old one are removed.
+    pub fn insert(&mut self, session: &Session) {
+        match self.ids.get(&session.path) {
+            Some((_, updated_at)) if *updated_at == session.updated_at => return,
+            Some(&(old, _)) => self.purge(old),
+            None => {}
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.ids
+            .insert(session.path.clone(), (id, session.updated_at));
+
+        let title = session.title_meta.as_deref().map(str::to_lowercase);
+        let fields = [
+            session.search_blob_ws_lower.as_str(),
+            session.reasoning_ws_lower.as_str(),
+            session.label_lower.as_str(),
+            session.uuid_lower.as_str(),
+            title.as_deref().unwrap_or_default(),
+        ];
+        let words: HashSet<&str> = fields.into_iter().flat_map(words).collect();
+        let mut token_ids = Vec::with_capacity(words.len());
+        for word in words {
+            let token = self.token_id(word);
+            self.postings[token as usize].push(id);
+            token_ids.push(token);
+        }
+        self.session_tokens.insert(id, token_ids);
+    }
+
+    /// Id of `word`, adding it to the vocabulary and the trigram index when new.
+    fn token_id(&mut self, word: &str) -> u32 {
+        if let Some(&token) = self.token_ids.get(word) {
+            return token;
+        }
+        let token = match self.free_tokens.pop() {
+            Some(token) => {
+                self.tokens[token as usize] = word.to_string();
+                token
+            }
+            None => {
+                self.tokens.push(word.to_string());
+                self.postings.push(Vec::new());
+                (self.tokens.len() - 1) as u32
+            }
+        };
+        self.token_ids.insert(word.to_string(), token);
+        for trigram in trigrams(word) {
+            let ids = self.trigrams.entry(trigram).or_default();
+            if ids.last() != Some(&token) {
+                ids.push(token);
+            }
+        }
+        token
+    }
+
+    /// Drops session `id` from the postings, and tokens no session contains any more.
+    fn purge(&mut self, id: u32) {
+        for token in self.session_tokens.remove(&id).unwrap_or_default() {
+            let postings = &mut self.postings[token as usize];
+            if let Ok(at) = postings.binary_search(&id) {
+                postings.remove(at);
+            }
+            if !postings.is_empty() {
+                continue;
+            }
+            let word = std::mem::take(&mut self.tokens[token as usize]);
+            for trigram in trigrams(&word) {
+                if let Some(ids) = self.trigrams.get_mut(&trigram) {
+                    ids.retain(|&t| t != token);
+                    if ids.is_empty() {
+                        self.trigrams.remove(&trigram);
+                    }
+                }
+            }
+            self.token_ids.remove(&word);
+            self.free_tokens.push(token);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Ids of the tokens containing `fragment`, or `None` when it is shorter than a
+    /// trigram and cannot be looked up.
+    fn tokens_containing(&self, fragment: &str) -> Option<Vec<u32>> {
+        let mut rarest: Option<&Vec<u32>> = None;
+        for trigram in trigrams(fragment) {
+            let ids = self.trigrams.get(&trigram)?;
+            if rarest.is_none_or(|r| ids.len() < r.len()) {
+                rarest = Some(ids);
+            }
+        }
+        Some(
+            rarest?
+                .iter()
+                .copied()
+                .filter(|&token| self.tokens[token as usize].contains(fragment))
+                .collect(),
+        )
+    }
+
+    /// Ids of the sessions containing every word of `query`, or `None` when the
+    /// query cannot be narrowed (empty, a term without word characters, or only
+    /// fragments shorter than three characters).
+    fn text_matches(&self, query: &str) -> Option<HashSet<u32>> {
+        let query_lower = normalize_query(query).to_lowercase();
+        let mut fragments: Vec<&str> = Vec::new();
+        for term in query_lower.split_whitespace() {
+            let before = fragments.len();
+            fragments.extend(words(term));
+            if fragments.len() == before {
+                return None;
+            }
+        }
+        // Shorter fragments would need a vocabulary scan; the others narrow enough.
+        fragments.retain(|f| f.chars().count() >= 3);
+        if fragments.is_empty() {
+            return None;
+        }
+        // Longest first: rarer fragments shrink the set before the others are scanned.
+        fragments.sort_by_key(|f| std::cmp::Reverse(f.len()));
+        fragments.dedup();
+
+        let mut matched: Option<HashSet<u32>> = None;
+        for fragment in fragments {
+            let ids: HashSet<u32> = self
+                .tokens_containing(fragment)
+                .unwrap_or_default()
+                .into_iter()
+                .flat_map(|token| self.postings[token as usize].iter().copied())
+                .filter(|id| matched.as_ref().is_none_or(|m| m.contains(id)))
+                .collect();
+            if ids.is_empty() {
+                return Some(ids);
+            }
+            matched = Some(ids);
+        }
+        matched
+    }
+
+    /// The sessions of `sessions` that `scorer` can match: text hits from the index,
+    /// fuzzy label/uuid hits, and any session not indexed yet. Falls back to all of
+    /// `sessions` when the query cannot be narrowed (including `--regex` queries).
+    pub fn candidates(&self, sessions: Vec<Arc<Session>>, scorer: &Scorer) -> Vec<Arc<Session>> {
+        if scorer.is_regex() {
+            return sessions;
+        }
+        let Some(matched) = self.text_matches(scorer.text_query()) else {
+            return sessions;
+        };
+        sessions
+            .into_iter()
+            .filter(|session| match self.ids.get(&session.path) {
+                Some((id, updated_at)) if *updated_at == session.updated_at => {
+                    matched.contains(id) || scorer.matches_metadata(session)
+                }
+                _ => true,
+            })
+            .collect()
+    }
+}
+
+/// Distinct runs of three chars in `text`, in order of first appearance.
+fn trigrams(text: &str) -> Vec<[char; 3]> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut seen = Vec::new();
+    for window in chars.windows(3) {
+        let trigram = [window[0], window[1], window[2]];
+        if !seen.contains(&trigram) {
+            seen.push(trigram);
+        }
+    }
+    seen
+}
+
+/// Runs of word characters (alphanumerics and `_`) in `text`.
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchOptions;
+    use crate::session::{Message, MessageRole};
+
+    fn session(uuid: &str, label: &str, text: &str) -> Session {
+        Session::for_test(
+            uuid,
+            label,
+            vec![Message::for_test(MessageRole::User, text)],
+        )
+    }
+
+    #[test]
+    fn candidates_match_a_linear_scan() {
+        let sessions: Vec<Arc<Session>> = [
+            session("aaaa", "rollout", "Fix the tokio runtime panic in main.rs"),
+            session("bbbb", "rollout", "deploy checklist for the runtime"),
+            session("cccc", "deploy-notes", "nothing relevant here"),
+            session("dddd", "rollout", "unrelated chatter"),
+        ]
+        .into_iter()
+        .map(Arc::new)
+        .collect();
+        let mut index = SessionIndex::default();
+        for s in &sessions {
+            index.insert(s);
+        }
+        assert_eq!(index.len(), 4);
+
+        for query in ["runtime", "tok run", "main.rs", "deploy", "->", "zzz", ""] {
+            let scorer = Scorer::with_options(query, SearchOptions::default());
+            let uuids = |list: Vec<Arc<Session>>| -> Vec<String> {
+                let mut scorer = Scorer::with_options(query, SearchOptions::default());
+                list.into_iter()
+                    .filter_map(|s| scorer.score_session_arc(s))
+                    .map(|r| r.session.uuid.clone())
+                    .collect()
+            };
+            let narrowed = index.candidates(sessions.clone(), &scorer);
+            assert!(narrowed.len() <= sessions.len());
+            assert_eq!(uuids(narrowed), uuids(sessions.clone()), "query {query:?}");
+        }
+        let scorer = Scorer::with_options("checklist", SearchOptions::default());
+        assert_eq!(index.candidates(sessions, &scorer).len(), 1);
+    }
+
+    #[test]
+    fn reindexing_a_session_drops_its_old_words() {
+        let mut index = SessionIndex::default();
+        let mut first = session("aaaa", "notes", "flaky websocket test");
+        index.insert(&first);
+        index.insert(&session("bbbb", "notes", "websocket reconnect"));
+        let ids =
+            |index: &SessionIndex, query: &str| index.text_matches(query).map(|ids| ids.len());
+        assert_eq!(ids(&index, "flaky"), Some(1));
+        assert_eq!(ids(&index, "sock"), Some(2));
+
+        first.updated_at += time::Duration::seconds(1);
+        first.search_blob_ws_lower = "stable websocket test".into();
+        index.insert(&first);
+        assert_eq!(ids(&index, "flaky"), Some(0));
+        assert_eq!(ids(&index, "stable"), Some(1));
+        assert_eq!(ids(&index, "sock"), Some(2));
+        assert!(!index.token_ids.contains_key("flaky"));
+        assert_eq!(index.session_tokens.len(), 2);
+        // Too short to look up: the caller scans linearly.
+        assert_eq!(ids(&index, "ws"), None);
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+/// Something a TUI key can do. Names are the kebab-case keys of the `[keys]` table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Action {
+    /// Quit (or leave the focused sidebar)
+    Quit,
+    /// Resume the selected session (expand a day when browsing, filter in the sidebar)
+    Open,
+    ClearQuery,
+    DeleteWord,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    /// Move the query cursor to the start, or jump to the first result when it is there
+    First,
+    /// Move the query cursor to the end, or jump to the last result when it is there
+    Last,
+    /// Expand the selected day when browsing; otherwise move the query cursor right
+    Expand,
+    /// Collapse the selected day when browsing; otherwise move the query cursor left
+    Collapse,
+    Export,
+    LoadMore,
+    Related,
+    RawRecord,
+    ToggleSidebar,
+    /// Show or hide the full-session preview pane
+    TogglePreview,
+    /// Switch between recency and relevance order
+    ToggleSort,
+    /// Move focus between the results, the sidebar and the preview
+    SwitchFocus,
+    /// Copy the selected session's UUID to the clipboard
+    CopyUuid,
+    /// Copy the selected session's file path to the clipboard
+    CopyPath,
+    /// Delete the selected session's file, after a y/n confirmation
+    DeleteSession,
+    /// Show or hide the selected session's details (file, cwd, model, times)
+    ToggleDetail,
+    /// Show or hide the list of key bindings
+    Help,
+}
+
+impl Action {
+    fn name(self) -> String {
+        self.to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default()
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["esc"],
+            Action::Open => &["enter"],
+            Action::ClearQuery => &["ctrl-u"],
+            Action::DeleteWord => &["ctrl-w"],
+            Action::Up => &["up", "ctrl-p", "ctrl-k"],
+            Action::Down => &["down", "ctrl-n", "ctrl-j"],
+            Action::PageUp => &["pageup"],
+            Action::PageDown => &["pagedown"],
+            Action::First => &["home"],
+            Action::Last => &["end"],
+            Action::Expand => &["right"],
+            Action::Collapse => &["left"],
+            Action::Export => &["ctrl-e"],
+            Action::LoadMore => &["ctrl-l"],
+            Action::Related => &["ctrl-r"],
+            Action::RawRecord => &["ctrl-o"],
+            Action::ToggleSidebar => &["ctrl-g"],
+            Action::TogglePreview => &["ctrl-t"],
+            Action::ToggleSort => &["ctrl-s"],
+            Action::SwitchFocus => &["tab"],
+            Action::CopyUuid => &["ctrl-y"],
+            Action::CopyPath => &["alt-y"],
+            Action::DeleteSession => &["ctrl-x"],
+            Action::ToggleDetail => &["alt-d"],
+            Action::Help => &["f1"],
+        }
+    }
+}
+
+/// One key or a list of keys for an action in the `[keys]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeySpec {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeySpec {
+    fn keys(&self) -> &[String] {
+        match self {
+            KeySpec::One(key) => std::slice::from_ref(key),
+            KeySpec::Many(keys) => keys,
+        }
+    }
+}
+
+/// A key with its modifiers, compared after `normalize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Binding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Binding {
+    /// Parses `ctrl-u`, `alt-x`, `pagedown`, `f5`, …; modifiers are `ctrl-`, `alt-`
+    /// and `shift-` prefixes. Printable characters need Ctrl or Alt, since on their own
+    /// they type into the query.
+    fn parse(spec: &str) -> Result<Self> {
+        let lower = spec.trim().to_lowercase();
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = lower.as_str();
+        loop {
+            if let Some(tail) = rest.strip_prefix("ctrl-") {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = tail;
+            } else if let Some(tail) = rest.strip_prefix("alt-") {
+                modifiers |= KeyModifiers::ALT;
+                rest = tail;
+            } else if let Some(tail) = rest.strip_prefix("shift-") {
+                modifiers |= KeyModifiers::SHIFT;
+                rest = tail;
+            } else {
+                break;
+            }
+        }
+        let code = match rest {
+            "esc" | "escape" => KeyCode::Esc,
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "space" => KeyCode::Char(' '),
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => match rest.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                        Some(n @ 1..=12) => KeyCode::F(n),
+                        _ => bail!("unknown key {spec:?}"),
+                    },
+                }
+            }
+        };
+        let binding = Self::normalize(code, modifiers);
+        if let KeyCode::Char(c) = binding.code
+            && binding.modifiers.is_empty()
+        {
+            bail!("key {spec:?} types {c:?} into the query; add a ctrl- or alt- modifier");
+        }
+        Ok(binding)
+    }
+
+    /// Shift only matters for non-character keys (the character already carries it),
+    /// and Ctrl/Alt letters match in either case.
+    fn normalize(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        match code {
+            KeyCode::Char(c) => {
+                let modifiers = modifiers - KeyModifiers::SHIFT;
+                let c = if modifiers.is_empty() {
+                    c
+                } else {
+                    c.to_ascii_lowercase()
+                };
+                Self {
+                    code: KeyCode::Char(c),
+                    modifiers,
+                }
+            }
+            code => Self { code, modifiers },
+        }
+    }
+
+    /// `Ctrl-U`, `Esc`, `PageDown`, `Alt-Y`: the style used in hints and errors.
+    fn describe(self) -> String {
+        let mut out = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            out.push_str("Ctrl-");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            out.push_str("Alt-");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            out.push_str("Shift-");
+        }
+        match self.code {
+            KeyCode::Char(' ') => out.push_str("Space"),
+            KeyCode::Char(c) if !self.modifiers.is_empty() => out.push(c.to_ascii_uppercase()),
+            KeyCode::Char(c) => out.push(c),
+            KeyCode::F(n) => out.push_str(&format!("F{n}")),
+            code => out.push_str(&format!("{code:?}")),
+        }
+        out
+    }
+}
+
+/// Key → action table for the TUI: the defaults with `[keys]` overrides applied.
+/// Rebinding an action replaces all of its default keys.
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    bindings: Vec<(Binding, Action)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new()).expect("default key bindings are valid")
+    }
+}
+
+impl KeyMap {
+    /// Applies `overrides` (action name → keys) to the defaults. Unknown actions or
+    /// keys, and keys bound to more than one action, are errors.
+    pub fn new(overrides: &BTreeMap<String, KeySpec>) -> Result<Self> {
+        let mut actions: Vec<(Action, Vec<String>)> = Action::value_variants()
+            .iter()
+            .map(|action| {
+                let keys = action
+                    .default_keys()
+                    .iter()
+                    .map(|k| k.to_string())
+                    .collect();
+                (*action, keys)
+            })
+            .collect();
+        for (name, spec) in overrides {
+            let action = Action::from_str(name, true).map_err(|_| {
+                let known: Vec<String> =
+                    Action::value_variants().iter().map(|a| a.name()).collect();
+                anyhow::anyhow!(
+                    "unknown action {name:?} in [keys] (available: {})",
+                    known.join(", ")
+                )
+            })?;
+            if let Some((_, keys)) = actions.iter_mut().find(|(a, _)| *a == action) {
+                *keys = spec.keys().to_vec();
+            }
+        }
+
+        let mut bindings: Vec<(Binding, Action)> = Vec::new();
+        for (action, keys) in actions {
+            for key in keys {
+                let binding =
+                    Binding::parse(&key).with_context(|| format!("in [keys] {}", action.name()))?;
+                if let Some((_, other)) = bindings.iter().find(|(b, _)| *b == binding) {
+                    bail!(
+                        "key {} is bound to both {} and {} in [keys]",
+                        binding.describe(),
+                        other.name(),
+                        action.name()
+                    );
+                }
+                bindings.push((binding, action));
+            }
+        }
+        Ok(Self { bindings })
+    }
+
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        let pressed = Binding::normalize(key.code, key.modifiers);
+        self.bindings
+            .iter()
+            .find(|(binding, _)| *binding == pressed)
+            .map(|(_, action)| *action)
+    }
+
+    /// Every bound action with its keys, e.g. `("quit", "Esc, Ctrl-Q")`, in
+    /// declaration order.
+    pub fn describe(&self) -> Vec<(String, String)> {
+        Action::value_variants()
+            .iter()
+            .filter_map(|&action| {
+                let keys: Vec<String> = self
+                    .bindings
+                    .iter()
+                    .filter(|(_, a)| *a == action)
+                    .map(|(binding, _)| binding.describe())
+                    .collect();
+                (!keys.is_empty()).then(|| (action.name(), keys.join(", ")))
+            })
+            .collect()
+    }
+
+    /// The first key bound to `action`, for hints (e.g. `Ctrl-L`).
+    pub fn key_for(&self, action: Action) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|(_, a)| *a == action)
+            .map(|(binding, _)| binding.describe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn overrides_replace_defaults_and_conflicts_are_reported() {
+        let mut overrides = BTreeMap::new();
+        overrides.insert(
+            "quit".to_string(),
+            KeySpec::Many(vec!["alt-q".into(), "ctrl-q".into()]),
+        );
+        let keys = KeyMap::new(&overrides).unwrap();
+        assert_eq!(
+            keys.action(&press(KeyCode::Char('q'), KeyModifiers::ALT)),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            keys.action(&press(KeyCode::Char('q'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(
+            keys.action(&press(
+                KeyCode::Char('Q'),
+                KeyModifiers::CONTROL | KeyModifiers::SHIFT
+            )),
+            Some(Action::Quit)
+        );
+        assert_eq!(keys.action(&press(KeyCode::Esc, KeyModifiers::NONE)), None);
+        assert_eq!(
+            keys.action(&press(KeyCode::Char('u'), KeyModifiers::CONTROL)),
+            Some(Action::ClearQuery)
+        );
+        for (key, action) in [
+            ('n', Action::Down),
+            ('j', Action::Down),
+            ('p', Action::Up),
+            ('k', Action::Up),
+        ] {
+            assert_eq!(
+                keys.action(&press(KeyCode::Char(key), KeyModifiers::CONTROL)),
+                Some(action)
+            );
+            assert_eq!(
+                keys.action(&press(KeyCode::Char(key), KeyModifiers::NONE)),
+                None
+            );
+        }
+
+        overrides.insert("export".to_string(), KeySpec::One("Ctrl-U".into()));
+        let err = KeyMap::new(&overrides).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "key Ctrl-U is bound to both clear-query and export in [keys]"
+        );
+
+        let mut unknown = BTreeMap::new();
+        unknown.insert("explode".to_string(), KeySpec::One("ctrl-x".into()));
+        assert!(KeyMap::new(&unknown).is_err());
+
+        // Bare printable keys would shadow typing, so they are rejected.
+        for key in ["q", "shift-q", "space", "?"] {
+            let mut typed = BTreeMap::new();
+            typed.insert("quit".to_string(), KeySpec::One(key.into()));
+            let err = format!("{:#}", KeyMap::new(&typed).unwrap_err());
+            assert!(err.contains("into the query"), "{key}: {err}");
+        }
+    }
+}
@@ -1,14 +1,73 @@
 pub mod cli;
+pub mod config;
 pub mod discovery;
+pub mod index;
+pub mod keys;
 pub mod search;
 pub mod session;
+pub mod state;
 pub mod tui;
 pub mod util;
 
 use anyhow::Result;
 
+pub use discovery::DiscoveryOptions;
+pub use search::SearchOptions;
+pub use session::{SearchResult, Session, Snippet};
+
 pub const DEFAULT_LIMIT: usize = 20;
 
 pub fn run() -> Result<()> {
     cli::run()
 }
+
+/// Loads the sessions `options` selects and returns the best `limit` matches for
+/// `query`, ranked as `cdxs --no-tui` ranks them. Writes nothing to stdout and only
+/// touches the on-disk index when `options.index_path` is set.
+///
+/// ```no_run
+/// let options = codex_search_rust::DiscoveryOptions::with_defaults()?;
+/// for result in codex_search_rust::search(&options, "tokio panic", 5)? {
+///     println!("{} {}", result.session.uuid, result.session.label);
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn search(options: &DiscoveryOptions, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+    let sessions = discovery::collect_sessions(options)?;
+    search::search_sessions(&sessions, query, limit, &SearchOptions::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_ranks_sessions_from_a_directory() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().to_path_buf();
+        std::fs::create_dir_all(&root).unwrap();
+        for (name, text) in [
+            (
+                "a-2025-01-01T00-00-00-aaaa.jsonl",
+                "the tokio runtime panicked",
+            ),
+            (
+                "b-2025-01-02T00-00-00-bbbb.jsonl",
+                "write the release notes",
+            ),
+        ] {
+            let line =
+                format!(r#"{{"role":"user","content":[{{"type":"input_text","text":"{text}"}}]}}"#);
+            std::fs::write(root.join(name), line).unwrap();
+        }
+        let options = DiscoveryOptions {
+            roots: vec![root.clone()],
+            ..DiscoveryOptions::with_defaults().unwrap()
+        };
+
+        let results = search(&options, "tokio", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].session.uuid, "aaaa");
+        assert_eq!(search(&options, "", 10).unwrap().len(), 2);
+    }
+}
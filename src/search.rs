@@ -1,81 +1,454 @@
 use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use fuzzy_matcher::FuzzyMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
-use time::OffsetDateTime;
+use regex::Regex;
+use time::{Duration, OffsetDateTime};
 
-use crate::session::{Message, SearchResult, Session, Snippet, SnippetSegment};
+use crate::session::{
+    Message, MessageMatch, MessageRole, SearchResult, Session, Snippet, SnippetSegment, TimeBasis,
+};
+use crate::util::{Glyphs, nfc};
 
 const RECENCY_BASE: i64 = 50_000;
-const RECENCY_MAX_PENALTY: i64 = 45_000;
-const SNIPPET_CONTEXT_CHARS: usize = 60;
+const DEFAULT_RECENCY_HALF_LIFE: Duration = Duration::days(7);
+pub(crate) const SNIPPET_CONTEXT_CHARS: usize = 60;
+/// Regex hits per message beyond which more do not raise its score.
+const REGEX_HIT_CAP: usize = 10;
+/// Word-set overlap (Jaccard) at or above which two snippets count as duplicates.
+const SNIPPET_DUPLICATE_OVERLAP: f64 = 0.8;
+
+/// How results are ordered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortMode {
+    /// Newest matching message first, score as tiebreak; recency also boosts the score
+    #[default]
+    #[value(alias = "time")]
+    Recent,
+    /// Textual relevance only: no recency boost, newest first among equal scores
+    Score,
+}
+
+/// Which speaker's messages a search considers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum RoleFilter {
+    #[default]
+    Any,
+    /// Only your prompts
+    User,
+    /// Only Codex's answers
+    Assistant,
+    /// Only tool calls and their output (needs --include-tools)
+    Tool,
+}
+
+impl RoleFilter {
+    pub fn allows(self, role: &MessageRole) -> bool {
+        match self {
+            RoleFilter::Any => true,
+            RoleFilter::User => *role == MessageRole::User,
+            RoleFilter::Assistant => *role == MessageRole::Assistant,
+            RoleFilter::Tool => *role == MessageRole::Tool,
+        }
+    }
+}
+
+/// Tunables shared by the CLI and TUI scoring paths.
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    /// Age at which the recency bonus drops to half of `RECENCY_BASE`.
+    pub recency_half_life: Duration,
+    /// Marker inserted where snippets are truncated.
+    pub ellipsis: String,
+    /// Drop sessions that lack any whitespace-separated query term (AND semantics).
+    pub all_terms: bool,
+    pub sort: SortMode,
+    /// Points added to a matching message scaled by how late it is in its session
+    /// (0 for the first message, the full weight for the latest). 0 disables it.
+    pub message_recency_weight: i64,
+    /// Timestamp that anchors the `Recent` ordering (and what the UIs display).
+    pub time_basis: TimeBasis,
+    pub matcher: MatcherKind,
+    /// Bring the query to Unicode NFC, matching sessions loaded with the same flag.
+    pub unicode_normalize: bool,
+    /// Only messages of this role can match or be previewed; with a role, sessions
+    /// need a matching message of it (label/uuid matches alone do not count).
+    pub role: RoleFilter,
+    /// Only each session's first user message can match or be previewed; label/uuid
+    /// matches alone do not count.
+    pub first_message: bool,
+    /// The query is a regular expression matched against message text (case-sensitive
+    /// unless it opts out with `(?i)`) instead of fuzzy terms.
+    pub regex: bool,
+    /// Sessions scoring below this are dropped before sorting and truncation. Text
+    /// matches start at 10,000; fuzzy-only label/uuid hits score in the hundreds; the
+    /// `Recent` sort adds up to `RECENCY_BASE` on top.
+    pub min_score: i64,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            recency_half_life: DEFAULT_RECENCY_HALF_LIFE,
+            ellipsis: Glyphs::default().ellipsis,
+            all_terms: false,
+            sort: SortMode::default(),
+            message_recency_weight: 0,
+            time_basis: TimeBasis::default(),
+            matcher: MatcherKind::default(),
+            unicode_normalize: false,
+            role: RoleFilter::default(),
+            first_message: false,
+            regex: false,
+            min_score: 0,
+        }
+    }
+}
+
+/// Scores how well `query` matches `text`; `None` means no match. Substring and
+/// term bonuses are added on top by the scorer, whichever matcher is used.
+pub trait Matcher {
+    fn score(&self, text: &str, query: &str) -> Option<i64>;
+}
+
+/// Available `Matcher` implementations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum MatcherKind {
+    /// Skim's fuzzy matcher (smart case): characters in order, gaps allowed
+    #[default]
+    Skim,
+    /// Case-insensitive substring; scores by query length and occurrence count
+    Literal,
+}
+
+impl MatcherKind {
+    pub fn build(self) -> Box<dyn Matcher> {
+        match self {
+            MatcherKind::Skim => Box::new(SkimMatcher(
+                SkimMatcherV2::default()
+                    .ignore_case()
+                    .use_cache(true)
+                    .smart_case(),
+            )),
+            MatcherKind::Literal => Box::new(LiteralMatcher),
+        }
+    }
+}
+
+struct SkimMatcher(SkimMatcherV2);
+
+impl Matcher for SkimMatcher {
+    fn score(&self, text: &str, query: &str) -> Option<i64> {
+        self.0.fuzzy_match(text, query)
+    }
+}
+
+struct LiteralMatcher;
+
+impl Matcher for LiteralMatcher {
+    fn score(&self, text: &str, query: &str) -> Option<i64> {
+        let query = query.to_lowercase();
+        if query.is_empty() {
+            return None;
+        }
+        let occurrences = text.to_lowercase().matches(&query).count().min(10) as i64;
+        // Roughly skim's score for a contiguous match, plus a little per repeat.
+        (occurrences > 0).then(|| query.chars().count() as i64 * 16 + occurrences)
+    }
+}
+
+/// One query term: a word, or a `"quoted phrase"` that must occur as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryTerm {
+    pub text: String,
+    pub lower: String,
+    pub phrase: bool,
+}
+
+/// Splits `query` into whitespace-separated words and `"quoted phrases"` (an
+/// unterminated quote runs to the end). Phrases keep single inner spaces.
+pub fn parse_query(query: &str) -> Vec<QueryTerm> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut in_phrase = false;
+    let mut flush = |current: &mut String, phrase: bool| {
+        let text = collapse_ws(current).trim().to_string();
+        current.clear();
+        if !text.is_empty() {
+            terms.push(QueryTerm {
+                lower: text.to_lowercase(),
+                text,
+                phrase,
+            });
+        }
+    };
+    for ch in query.chars() {
+        if ch == '"' {
+            flush(&mut current, in_phrase);
+            in_phrase = !in_phrase;
+        } else if ch.is_whitespace() && !in_phrase {
+            flush(&mut current, false);
+        } else {
+            current.push(ch);
+        }
+    }
+    flush(&mut current, in_phrase);
+    terms
+}
+
+/// Pulls `path:FRAGMENT` words (outside quotes) out of `query`, returning the rest of
+/// the query and the lowercased fragments a session's cwd must contain.
+pub fn split_path_filters(query: &str) -> (String, Vec<String>) {
+    let mut rest: Vec<&str> = Vec::new();
+    let mut fragments = Vec::new();
+    let mut in_phrase = false;
+    for word in query.split_whitespace() {
+        if !in_phrase
+            && let Some(fragment) = word
+                .get(..5)
+                .filter(|prefix| prefix.eq_ignore_ascii_case("path:"))
+                .map(|_| &word[5..])
+            && !fragment.is_empty()
+        {
+            fragments.push(fragment.to_lowercase().replace('\\', "/"));
+            continue;
+        }
+        if word.matches('"').count() % 2 == 1 {
+            in_phrase = !in_phrase;
+        }
+        rest.push(word);
+    }
+    (rest.join(" "), fragments)
+}
+
+/// True when `session`'s cwd contains every fragment (either slash direction).
+fn cwd_matches(session: &Session, fragments: &[String]) -> bool {
+    if fragments.is_empty() {
+        return true;
+    }
+    let Some(cwd) = &session.cwd else {
+        return false;
+    };
+    let cwd = cwd.to_string_lossy().to_lowercase().replace('\\', "/");
+    fragments
+        .iter()
+        .all(|fragment| cwd.contains(fragment.as_str()))
+}
+
+/// Messages each session had matching the previous query, so a query that only grows
+/// (each term extended, none added, as while typing) rescans just those: a message no
+/// term of a query matches cannot match a longer version of it. Carried from one
+/// `Scorer` to the next with `Scorer::with_cache` / `Scorer::into_cache`.
+#[derive(Debug, Default)]
+pub struct MessageCache {
+    key: Option<MessageCacheKey>,
+    /// Session path -> (file time, indices of its matching messages, ascending).
+    hits: HashMap<PathBuf, (OffsetDateTime, Vec<usize>)>,
+}
+
+/// What decides whether a message matches at all.
+#[derive(Debug, Clone, PartialEq)]
+struct MessageCacheKey {
+    terms: Vec<QueryTerm>,
+    matcher: MatcherKind,
+    role: RoleFilter,
+    first_message: bool,
+}
+
+impl MessageCacheKey {
+    /// True when a message `self` does not match cannot match `next` either. Terms
+    /// are compared as typed: smart case only gets stricter as uppercase is added.
+    fn narrows_to(&self, next: &Self) -> bool {
+        self.matcher == next.matcher
+            && self.role == next.role
+            && self.first_message == next.first_message
+            && !self.terms.is_empty()
+            && self.terms.len() == next.terms.len()
+            && self
+                .terms
+                .iter()
+                .zip(&next.terms)
+                .all(|(old, new)| old.phrase == new.phrase && new.text.starts_with(&old.text))
+    }
+}
+
+/// Outcome of scoring one session; `text_score` excludes the recency bonus.
+#[derive(Debug, Clone)]
+pub struct SessionScore {
+    pub score: i64,
+    pub text_score: i64,
+    pub matched_message: Option<Message>,
+    pub snippet: Snippet,
+}
 
 pub struct Scorer {
-    matcher: SkimMatcherV2,
+    matcher: Box<dyn Matcher>,
+    options: SearchOptions,
     query: String,
     query_lower: String,
     query_ws: String,
-    query_terms: Vec<String>,
+    query_terms: Vec<QueryTerm>,
+    /// `path:` fragments; sessions whose cwd lacks one are dropped before scoring.
+    path_filters: Vec<String>,
+    /// Every term must occur in the session: several terms, a phrase, or `--all-terms`.
+    require_all_terms: bool,
+    /// The compiled query with `--regex`; `None` there means it is invalid and
+    /// nothing matches.
+    regex: Option<Regex>,
     now: OffsetDateTime,
     is_empty_query: bool,
+    /// Hits of the query this one extends, when `with_cache` got a usable cache.
+    prior_hits: Option<HashMap<PathBuf, (OffsetDateTime, Vec<usize>)>>,
+    /// Hits recorded for this query, when caching.
+    cache: Option<MessageCache>,
 }
 
 impl Scorer {
     pub fn new(query: &str) -> Self {
-        let trimmed = query.trim().to_owned();
+        Self::with_options(query, SearchOptions::default())
+    }
+
+    pub fn with_options(query: &str, options: SearchOptions) -> Self {
+        let (mut trimmed, path_filters) = split_path_filters(&normalize_query(query));
+        if options.unicode_normalize {
+            trimmed = nfc(&trimmed);
+        }
+        let regex = options
+            .regex
+            .then(|| compile_regex(&trimmed).ok())
+            .flatten();
+        let query_terms = if options.regex {
+            Vec::new()
+        } else {
+            parse_query(&trimmed)
+        };
+        // Quotes only group; matching and highlighting see the bare text.
+        if !options.regex {
+            trimmed = query_terms
+                .iter()
+                .map(|term| term.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+        }
         let query_lower = trimmed.to_lowercase();
-        let query_ws = collapse_ws(&query_lower);
-        let query_terms: Vec<String> = query_lower
-            .split_whitespace()
-            .filter(|s| !s.is_empty())
-            .map(|s| s.to_string())
-            .collect();
+        let query_ws = query_lower.clone();
+        let require_all_terms = options.all_terms
+            || query_terms.len() > 1
+            || query_terms.iter().any(|term| term.phrase);
         let is_empty_query = trimmed.is_empty();
-        let matcher = SkimMatcherV2::default()
-            .ignore_case()
-            .use_cache(true)
-            .smart_case();
-
         Self {
-            matcher,
+            matcher: options.matcher.build(),
+            options,
             query: trimmed,
             query_lower,
             query_ws,
             query_terms,
+            path_filters,
+            require_all_terms,
+            regex,
             now: OffsetDateTime::now_utc(),
             is_empty_query,
+            prior_hits: None,
+            cache: None,
         }
     }
 
+    /// Like `with_options`, but skips messages `cache` shows cannot match when this
+    /// query extends the cached one, and records this query's hits for the next.
+    /// Results are identical to an uncached scorer.
+    pub fn with_cache(query: &str, options: SearchOptions, cache: MessageCache) -> Self {
+        let mut scorer = Self::with_options(query, options);
+        if scorer.options.regex || scorer.is_empty_query {
+            return scorer;
+        }
+        let key = MessageCacheKey {
+            terms: scorer.query_terms.clone(),
+            matcher: scorer.options.matcher,
+            role: scorer.options.role,
+            first_message: scorer.options.first_message,
+        };
+        if cache.key.as_ref().is_some_and(|old| old.narrows_to(&key)) {
+            scorer.prior_hits = Some(cache.hits);
+        }
+        scorer.cache = Some(MessageCache {
+            key: Some(key),
+            hits: HashMap::new(),
+        });
+        scorer
+    }
+
+    /// The hits recorded by a `with_cache` scorer, for the next query's scorer.
+    pub fn into_cache(self) -> MessageCache {
+        self.cache.unwrap_or_default()
+    }
+
     pub fn score_session_arc(&mut self, session: Arc<Session>) -> Option<SearchResult> {
         self.score_session(session.as_ref())
-            .map(|(score, matched_message, snippet)| SearchResult {
+            .map(|scored| SearchResult {
                 session,
-                matched_message,
-                score,
-                snippet,
+                matched_message: scored.matched_message,
+                score: scored.score,
+                text_score: scored.text_score,
+                snippet: scored.snippet,
             })
     }
 
-    pub fn score_session(&mut self, session: &Session) -> Option<(i64, Option<Message>, Snippet)> {
+    /// Scores `session`, dropping it when the score falls below `--min-score`. Empty
+    /// queries list every session, so the cut-off applies only to real queries.
+    pub fn score_session(&mut self, session: &Session) -> Option<SessionScore> {
+        let min_score = self.options.min_score;
+        let listing = self.is_empty_query;
+        self.score_unfiltered(session)
+            .filter(|scored| listing || scored.score >= min_score)
+    }
+
+    fn score_unfiltered(&mut self, session: &Session) -> Option<SessionScore> {
+        if !cwd_matches(session, &self.path_filters) {
+            return None;
+        }
         if self.is_empty_query {
             let anchor = session.latest_message_time.unwrap_or(session.updated_at);
-            let score = recency_bonus(anchor, self.now);
-            let preview = session.preview().cloned();
+            let score = recency_bonus(anchor, self.now, self.options.recency_half_life);
+            let role = self.options.role;
+            let first_user = self
+                .options
+                .first_message
+                .then(|| first_user_index(session));
+            let preview = session
+                .messages
+                .iter()
+                .enumerate()
+                .find(|(index, m)| {
+                    role.allows(&m.role) && first_user.is_none_or(|first| first == Some(*index))
+                })
+                .map(|(_, m)| m.clone());
             let source = preview
                 .as_ref()
                 .map(|m| m.full_text.as_str())
                 .unwrap_or_else(|| session.label.as_str());
-            let snippet = snippet_from_text(source, "", SNIPPET_CONTEXT_CHARS);
-            return Some((score, preview, snippet));
+            let snippet =
+                snippet_from_text(source, "", SNIPPET_CONTEXT_CHARS, &self.options.ellipsis);
+            return Some(SessionScore {
+                score,
+                text_score: 0,
+                matched_message: preview,
+                snippet,
+            });
+        }
+
+        if self.options.regex {
+            return self
+                .regex
+                .as_ref()
+                .and_then(|regex| self.score_session_regex(session, regex));
         }
 
         // Avoid fuzzy matching on the entire blob (costly). Use contains on the pre-lowered blob.
-        let blob_score = None;
-        let label_score = self.matcher.fuzzy_match(&session.label, &self.query);
-        let uuid_score = self.matcher.fuzzy_match(&session.uuid, &self.query);
+        let (label_score, uuid_score) = self.metadata_scores(session);
 
         let label_lower = &session.label_lower;
         let uuid_lower = &session.uuid_lower;
@@ -83,45 +456,124 @@ impl Scorer {
         let terms_match = self
             .query_terms
             .iter()
-            .all(|t| session.search_blob_ws_lower.contains(t));
+            .all(|t| session.search_blob_ws_lower.contains(&t.lower));
+        if self.require_all_terms
+            && !self.query_terms.iter().all(|t| {
+                session.search_blob_ws_lower.contains(&t.lower)
+                    || label_lower.contains(&t.lower)
+                    || uuid_lower.contains(&t.lower)
+                    || session.reasoning_ws_lower.contains(&t.lower)
+            })
+        {
+            return None;
+        }
+
+        let matches_reasoning = !session.reasoning_ws_lower.is_empty()
+            && (session.reasoning_ws_lower.contains(&self.query_ws)
+                || self
+                    .query_terms
+                    .iter()
+                    .all(|t| session.reasoning_ws_lower.contains(&t.lower)));
 
         let matches_text = session.search_blob_ws_lower.contains(&self.query_ws)
             || terms_match
+            || matches_reasoning
             || label_lower.contains(&self.query_lower)
             || uuid_lower.contains(&self.query_lower);
 
-        if blob_score.is_none() && label_score.is_none() && uuid_score.is_none() && !matches_text {
+        if label_score.is_none() && uuid_score.is_none() && !matches_text {
             return None;
         }
 
-        let (best_message, best_message_score) =
-            best_message_for_session(&mut self.matcher, session, &self.query, &self.query_lower);
+        let prior = self
+            .prior_hits
+            .as_ref()
+            .and_then(|hits| hits.get(&session.path))
+            .filter(|(updated_at, _)| *updated_at == session.updated_at)
+            .map(|(_, indices)| indices.as_slice());
+        let (best_message, best_message_score, hits) = best_message_for_session(
+            self.matcher.as_ref(),
+            session,
+            &self.query,
+            &self.query_terms,
+            &self.options,
+            prior,
+        );
+        // A compacted session only has previews to match, so its hits would wrongly
+        // prune the full text of the same session reloaded for the next query.
+        if let Some(cache) = &mut self.cache
+            && !session.compacted
+        {
+            cache
+                .hits
+                .insert(session.path.clone(), (session.updated_at, hits));
+        }
+        if (self.options.role != RoleFilter::Any || self.options.first_message)
+            && best_message_score == 0
+        {
+            return None;
+        }
+        if self.options.first_message
+            && self.require_all_terms
+            && let Some(message) = &best_message
+            && !self
+                .query_terms
+                .iter()
+                .all(|t| message.full_text_ws_lower.contains(&t.lower))
+        {
+            return None;
+        }
+        // A message that does not match would hide why the session did (e.g. a label
+        // match), so the snippet falls through to the label or blob instead.
+        let best_message = best_message.filter(|_| best_message_score > 0);
 
-        let snippet = if let Some(ref message) = best_message {
-            snippet_from_text(&message.full_text, &self.query_lower, SNIPPET_CONTEXT_CHARS)
+        let snippet = if best_message_score == 0 && matches_reasoning {
+            snippet_from_text(
+                &session.reasoning,
+                &self.query_lower,
+                SNIPPET_CONTEXT_CHARS,
+                &self.options.ellipsis,
+            )
+        } else if let Some(ref message) = best_message {
+            snippet_from_text(
+                &message.full_text,
+                &self.query_lower,
+                SNIPPET_CONTEXT_CHARS,
+                &self.options.ellipsis,
+            )
         } else if label_lower.contains(&self.query_lower) {
-            snippet_from_text(&session.label, &self.query_lower, SNIPPET_CONTEXT_CHARS)
+            snippet_from_text(
+                &session.label,
+                &self.query_lower,
+                SNIPPET_CONTEXT_CHARS,
+                &self.options.ellipsis,
+            )
         } else if session.search_blob_ws_lower.contains(&self.query_ws) {
             snippet_from_text(
                 &session.search_blob,
                 &self.query_lower,
                 SNIPPET_CONTEXT_CHARS,
+                &self.options.ellipsis,
             )
         } else {
-            snippet_from_text(&session.label, &self.query_lower, SNIPPET_CONTEXT_CHARS)
+            snippet_from_text(
+                &session.label,
+                &self.query_lower,
+                SNIPPET_CONTEXT_CHARS,
+                &self.options.ellipsis,
+            )
         };
 
         let mut score = 0i64;
-        score += blob_score.unwrap_or(0) as i64 * 2;
-        score += label_score.unwrap_or(0) as i64 * 3;
-        score += uuid_score.unwrap_or(0) as i64;
+        score += label_score.unwrap_or(0) * 3;
+        score += uuid_score.unwrap_or(0);
 
         if matches_text {
             score += 10_000;
         }
 
         if best_message_score > 0 {
-            score += best_message_score as i64;
+            score += best_message_score;
         }
 
         let anchor = best_message
@@ -130,25 +582,134 @@ impl Scorer {
             .or(session.latest_message_time)
             .unwrap_or(session.updated_at);
 
-        score += recency_bonus(anchor, self.now);
+        let text_score = score;
+        if self.options.sort == SortMode::Recent {
+            score += recency_bonus(anchor, self.now, self.options.recency_half_life);
+        }
+
+        Some(SessionScore {
+            score,
+            text_score,
+            matched_message: best_message,
+            snippet,
+        })
+    }
+
+    /// `--regex` scoring: the message with the most hits (capped) wins, and a session
+    /// whose messages do not match can still match through its search blob.
+    fn score_session_regex(&self, session: &Session, regex: &Regex) -> Option<SessionScore> {
+        let mut best: Option<(&Message, usize)> = None;
+        let first_user = self
+            .options
+            .first_message
+            .then(|| first_user_index(session));
+        for (index, message) in session.messages.iter().enumerate() {
+            if !self.options.role.allows(&message.role)
+                || first_user.is_some_and(|first| first != Some(index))
+            {
+                continue;
+            }
+            let hits = regex_hits(regex, &message.full_text);
+            if hits > best.map_or(0, |(_, most)| most) {
+                best = Some((message, hits));
+            }
+        }
+        let (matched, hits, source) = match best {
+            Some((message, hits)) => (Some(message.clone()), hits, &message.full_text),
+            None if self.options.role == RoleFilter::Any
+                && !self.options.first_message
+                && regex.is_match(&session.search_blob) =>
+            {
+                (None, 1, &session.search_blob)
+            }
+            None => return None,
+        };
+        let snippet = regex_snippet(source, regex, SNIPPET_CONTEXT_CHARS, &self.options.ellipsis);
+
+        let text_score = 10_000 + hits as i64 * 100;
+        let anchor = matched
+            .as_ref()
+            .and_then(|m| m.timestamp)
+            .or(session.latest_message_time)
+            .unwrap_or(session.updated_at);
+        let mut score = text_score;
+        if self.options.sort == SortMode::Recent {
+            score += recency_bonus(anchor, self.now, self.options.recency_half_life);
+        }
+        Some(SessionScore {
+            score,
+            text_score,
+            matched_message: matched,
+            snippet,
+        })
+    }
+
+    pub fn is_regex(&self) -> bool {
+        self.options.regex
+    }
+
+    /// Fuzzy scores of the label (or a metadata title kept out of it by
+    /// `--filename-labels`, which gets the same boost) and of the uuid.
+    fn metadata_scores(&self, session: &Session) -> (Option<i64>, Option<i64>) {
+        let label_score = session
+            .title_meta
+            .as_deref()
+            .filter(|title| *title != session.label)
+            .and_then(|title| self.matcher.score(title, &self.query))
+            .max(self.matcher.score(&session.label, &self.query));
+        (label_score, self.matcher.score(&session.uuid, &self.query))
+    }
 
-        Some((score, best_message, snippet))
+    /// Whether the label, title or uuid alone makes `session` a match, without
+    /// looking at its text.
+    pub fn matches_metadata(&self, session: &Session) -> bool {
+        let (label_score, uuid_score) = self.metadata_scores(session);
+        label_score.is_some() || uuid_score.is_some()
+    }
+
+    /// Orders results for display; archived sessions lose ties. An empty query has
+    /// no relevance to rank by, so it always falls back to recency order.
+    pub fn sort(&self, results: &mut [SearchResult]) {
+        let basis = self.options.time_basis;
+        if self.options.sort == SortMode::Score && !self.is_empty_query {
+            results.sort_by(|a, b| {
+                b.text_score
+                    .cmp(&a.text_score)
+                    .then_with(|| b.timestamp(basis).cmp(&a.timestamp(basis)))
+                    .then_with(|| a.session.archived.cmp(&b.session.archived))
+                    .then_with(|| a.session.uuid.cmp(&b.session.uuid))
+            });
+        } else {
+            results.sort_by(|a, b| match b.timestamp(basis).cmp(&a.timestamp(basis)) {
+                Ordering::Equal => b
+                    .score
+                    .cmp(&a.score)
+                    .then_with(|| a.session.archived.cmp(&b.session.archived)),
+                other => other,
+            });
+        }
     }
 
     pub fn is_query_empty(&self) -> bool {
         self.is_empty_query
     }
+
+    /// The query text that is matched, without quotes or `path:` filters.
+    pub fn text_query(&self) -> &str {
+        &self.query
+    }
 }
 
 pub fn search_sessions(
     sessions: &[Session],
     query: &str,
     limit: usize,
+    options: &SearchOptions,
 ) -> Result<Vec<SearchResult>> {
     let mut ordered: Vec<&Session> = sessions.iter().collect();
-    ordered.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    ordered.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
 
-    let mut scorer = Scorer::new(query);
+    let mut scorer = Scorer::with_options(query, options.clone());
 
     let mut scored: Vec<SearchResult> = ordered
         .into_iter()
@@ -158,55 +719,366 @@ pub fn search_sessions(
         })
         .collect();
 
-    scored.sort_by(|a, b| match b.match_timestamp().cmp(&a.match_timestamp()) {
-        Ordering::Equal => b.score.cmp(&a.score),
-        other => other,
-    });
+    scorer.sort(&mut scored);
 
     scored.truncate(limit);
     Ok(scored)
 }
 
-pub fn recency_bonus(updated_at: OffsetDateTime, now: OffsetDateTime) -> i64 {
-    let age = now - updated_at;
-    let minutes = age.whole_minutes().clamp(0, RECENCY_MAX_PENALTY);
-    RECENCY_BASE - minutes
+/// Searches each cwd as one unit: sessions sharing a cwd are concatenated into a
+/// project session, so terms spread across sessions still match together. Each
+/// result's uuid and path point at the session holding the matched message (the
+/// newest one for label-only matches). Sessions without a cwd are left out.
+pub fn search_projects(
+    sessions: &[Session],
+    query: &str,
+    limit: usize,
+    options: &SearchOptions,
+) -> Result<Vec<SearchResult>> {
+    let mut groups: Vec<(&Path, Vec<&Session>)> = Vec::new();
+    for session in sessions {
+        let Some(cwd) = session.cwd.as_deref() else {
+            continue;
+        };
+        match groups.iter_mut().find(|(path, _)| *path == cwd) {
+            Some((_, members)) => members.push(session),
+            None => groups.push((cwd, vec![session])),
+        }
+    }
+    let projects: Vec<Session> = groups
+        .into_iter()
+        .map(|(cwd, mut members)| {
+            members.sort_by_key(|s| s.updated_at);
+            merge_project(cwd, &members)
+        })
+        .collect();
+
+    let mut results = search_sessions(&projects, query, limit, options)?;
+    for result in &mut results {
+        let Some(cwd) = result.session.cwd.clone() else {
+            continue;
+        };
+        let holder = sessions
+            .iter()
+            .filter(|s| s.cwd.as_ref() == Some(&cwd))
+            .filter(|s| {
+                result.matched_message.as_ref().is_none_or(|matched| {
+                    s.messages.iter().any(|m| {
+                        m.timestamp == matched.timestamp && m.full_text == matched.full_text
+                    })
+                })
+            })
+            .max_by_key(|s| s.updated_at);
+        if let Some(holder) = holder {
+            let project = Arc::make_mut(&mut result.session);
+            project.uuid = holder.uuid.clone();
+            project.uuid_lower = holder.uuid_lower.clone();
+            project.path = holder.path.clone();
+        }
+    }
+    Ok(results)
 }
 
+/// One session standing for every session in `cwd`, oldest first.
+fn merge_project(cwd: &Path, members: &[&Session]) -> Session {
+    let newest = members[members.len() - 1];
+    let join = |field: fn(&Session) -> &str, sep: &str| {
+        members
+            .iter()
+            .map(|s| field(s))
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(sep)
+    };
+    let search_blob = join(|s| &s.search_blob, "\n");
+    let label = format!("{} ({} sessions)", cwd.display(), members.len());
+    Session {
+        uuid: newest.uuid.clone(),
+        label_lower: label.to_lowercase(),
+        label,
+        title_meta: None,
+        path: newest.path.clone(),
+        created_at: members.iter().filter_map(|s| s.created_at).min(),
+        updated_at: newest.updated_at,
+        earliest_message_time: members.iter().filter_map(|s| s.earliest_message_time).min(),
+        latest_message_time: members.iter().filter_map(|s| s.latest_message_time).max(),
+        cwd: Some(cwd.to_path_buf()),
+        model: newest.model.clone(),
+        messages: members
+            .iter()
+            .flat_map(|s| s.messages.iter().cloned())
+            .collect(),
+        search_blob_lower: search_blob.to_lowercase(),
+        search_blob_ws_lower: join(|s| &s.search_blob_ws_lower, " "),
+        search_blob,
+        uuid_lower: newest.uuid_lower.clone(),
+        reasoning: join(|s| &s.reasoning, "\n"),
+        reasoning_ws_lower: join(|s| &s.reasoning_ws_lower, " "),
+        archived: members.iter().all(|s| s.archived),
+        // Rehydration would reload only one member file, so project results keep
+        // whatever message text discovery kept.
+        compacted: false,
+    }
+}
+
+/// Exponentially decaying bonus: `RECENCY_BASE` for brand-new activity, halving every `half_life`.
+pub fn recency_bonus(updated_at: OffsetDateTime, now: OffsetDateTime, half_life: Duration) -> i64 {
+    if !half_life.is_positive() {
+        return 0;
+    }
+    let age = (now - updated_at).max(Duration::ZERO);
+    let half_lives = age.as_seconds_f64() / half_life.as_seconds_f64();
+    (RECENCY_BASE as f64 * 0.5f64.powf(half_lives)).round() as i64
+}
+
+/// Ranks the messages of a single session against `query`, best match first.
+pub fn search_messages(
+    session: &Session,
+    query_arg: &str,
+    limit: usize,
+    options: &SearchOptions,
+) -> Vec<MessageMatch> {
+    let (query_arg, path_filters) = split_path_filters(&normalize_query(query_arg));
+    if !cwd_matches(session, &path_filters) {
+        return Vec::new();
+    }
+    let terms = parse_query(&query_arg);
+    let query = terms
+        .iter()
+        .map(|term| term.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let query = query.as_str();
+    let query_lower = query.to_lowercase();
+    let matcher = options.matcher.build();
+    let regex = if options.regex && !query.is_empty() {
+        match compile_regex(&query_arg) {
+            Ok(regex) => Some(regex),
+            Err(_) => return Vec::new(),
+        }
+    } else {
+        None
+    };
+
+    let first_user = options.first_message.then(|| first_user_index(session));
+    let mut matches: Vec<MessageMatch> = session
+        .messages
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| options.role.allows(&message.role))
+        .filter(|(index, _)| first_user.is_none_or(|first| first == Some(*index)))
+        .filter_map(|(index, message)| {
+            let (score, snippet) = if let Some(regex) = &regex {
+                let hits = regex_hits(regex, &message.full_text);
+                let snippet = regex_snippet(
+                    &message.full_text,
+                    regex,
+                    SNIPPET_CONTEXT_CHARS,
+                    &options.ellipsis,
+                );
+                (hits as i64, snippet)
+            } else {
+                let score = if query.is_empty() {
+                    0
+                } else {
+                    score_message(matcher.as_ref(), message, query, &terms)
+                };
+                let snippet = snippet_from_text(
+                    &message.full_text,
+                    &query_lower,
+                    SNIPPET_CONTEXT_CHARS,
+                    &options.ellipsis,
+                );
+                (score, snippet)
+            };
+            if !query.is_empty() && score <= 0 {
+                return None;
+            }
+            Some(MessageMatch {
+                index,
+                role: message.role.clone(),
+                timestamp: message.timestamp,
+                score,
+                snippet,
+            })
+        })
+        .collect();
+
+    matches.sort_by(|a, b| b.score.cmp(&a.score).then(a.index.cmp(&b.index)));
+    dedup_snippets(&mut matches, &options.ellipsis);
+    matches.truncate(limit);
+    matches
+}
+
+/// Drops matches whose snippet repeats an earlier (higher-ranked) one: identical after
+/// normalization, or sharing most of its words.
+fn dedup_snippets(matches: &mut Vec<MessageMatch>, ellipsis: &str) {
+    let mut kept: Vec<(String, HashSet<String>)> = Vec::new();
+    matches.retain(|m| {
+        let text: String = m.snippet.segments.iter().map(|s| s.text.as_str()).collect();
+        let normalized = collapse_ws(&text.replace(ellipsis, " ").to_lowercase())
+            .trim()
+            .to_owned();
+        let words: HashSet<String> = normalized
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_owned)
+            .collect();
+        let duplicate = kept.iter().any(|(other, other_words)| {
+            if *other == normalized {
+                return true;
+            }
+            let shared = words.intersection(other_words).count();
+            let union = words.len() + other_words.len() - shared;
+            union > 0 && shared as f64 / union as f64 >= SNIPPET_DUPLICATE_OVERLAP
+        });
+        if !duplicate {
+            kept.push((normalized, words));
+        }
+        !duplicate
+    });
+}
+
+/// The best message and its score, plus the indices of every message that matched
+/// at all. With `prior`, only those messages are scored (see `MessageCache`); the
+/// rest could only score 0, which never wins.
 fn best_message_for_session(
-    matcher: &mut SkimMatcherV2,
+    matcher: &dyn Matcher,
     session: &Session,
     query: &str,
-    query_lower: &str,
-) -> (Option<Message>, i64) {
+    terms: &[QueryTerm],
+    options: &SearchOptions,
+    prior: Option<&[usize]>,
+) -> (Option<Message>, i64, Vec<usize>) {
+    let recency_weight = options.message_recency_weight;
     let mut best_message = None;
     let mut best_score: i64 = i64::MIN;
+    let mut hits = Vec::new();
 
-    for message in &session.messages {
-        let fuzzy = matcher.fuzzy_match(&message.full_text, query).unwrap_or(0) as i64;
-        let contains = {
-            let qws = collapse_ws(query_lower);
-            let tokens_ok = query_lower
-                .split_whitespace()
-                .filter(|s| !s.is_empty())
-                .all(|t| message.full_text_ws_lower.contains(t));
-            message.full_text_ws_lower.contains(&qws) || tokens_ok
+    let first_user = options.first_message.then(|| first_user_index(session));
+    let span = if recency_weight != 0 {
+        time_span(session)
+    } else {
+        None
+    };
+    let mut consider = |index: usize| {
+        let Some(message) = session.messages.get(index) else {
+            return;
+        };
+        if !options.role.allows(&message.role)
+            || first_user.is_some_and(|first| first != Some(index))
+        {
+            return;
+        }
+        let mut total = match message_score(matcher, message, query, terms) {
+            Some(score) => {
+                hits.push(index);
+                score
+            }
+            None => 0,
         };
-        let total = fuzzy + if contains { 6_000 } else { 0 };
+        if total > 0 && recency_weight != 0 {
+            let position = message_position(index, session.messages.len(), message, span);
+            total += (recency_weight as f64 * position) as i64;
+        }
         if total > best_score {
             best_score = total;
             best_message = Some(message.clone());
         }
+    };
+    match prior {
+        Some(indices) => indices.iter().copied().for_each(&mut consider),
+        None => (0..session.messages.len()).for_each(&mut consider),
     }
 
     if best_score > 0 {
-        (best_message, best_score)
+        (best_message, best_score, hits)
+    } else {
+        (best_message, 0, hits)
+    }
+}
+
+/// Index of the session's opening prompt.
+fn first_user_index(session: &Session) -> Option<usize> {
+    session
+        .messages
+        .iter()
+        .position(|m| m.role == MessageRole::User)
+}
+
+/// The earliest and latest message timestamps of `session`, when they differ.
+fn time_span(session: &Session) -> Option<(OffsetDateTime, OffsetDateTime)> {
+    let timestamps = session.messages.iter().filter_map(|m| m.timestamp);
+    let (first, last) = (timestamps.clone().min()?, timestamps.max()?);
+    (last > first).then_some((first, last))
+}
+
+/// How late `message` (at `index` of `count` messages) is within its session, from
+/// 0.0 (first) to 1.0 (latest). Uses timestamps when the session has a time `span`
+/// (see `time_span`), otherwise the message index.
+fn message_position(
+    index: usize,
+    count: usize,
+    message: &Message,
+    span: Option<(OffsetDateTime, OffsetDateTime)>,
+) -> f64 {
+    if let (Some(ts), Some((first, last))) = (message.timestamp, span) {
+        return (ts - first) / (last - first);
+    }
+    let last_index = count.saturating_sub(1);
+    if last_index == 0 {
+        1.0
+    } else {
+        index as f64 / last_index as f64
+    }
+}
+
+/// Fuzzy score of `message` plus a bonus when it contains the whole query or every
+/// term. Several terms are scored one by one and summed, so words far apart still
+/// rank; `query` is the terms joined by spaces.
+fn score_message(
+    matcher: &dyn Matcher,
+    message: &Message,
+    query: &str,
+    terms: &[QueryTerm],
+) -> i64 {
+    message_score(matcher, message, query, terms).unwrap_or(0)
+}
+
+/// `score_message`, or `None` when no term matches and the text does not contain
+/// the query, as opposed to a match that happens to score 0.
+fn message_score(
+    matcher: &dyn Matcher,
+    message: &Message,
+    query: &str,
+    terms: &[QueryTerm],
+) -> Option<i64> {
+    let fuzzy: Option<i64> = if terms.len() > 1 {
+        terms
+            .iter()
+            .filter_map(|term| matcher.score(&message.full_text, &term.text))
+            .reduce(|a, b| a + b)
     } else {
-        (best_message, 0)
+        matcher.score(&message.full_text, query)
+    };
+    let contains = message
+        .full_text_ws_lower
+        .contains(&collapse_ws(&query.to_lowercase()))
+        || terms
+            .iter()
+            .all(|t| message.full_text_ws_lower.contains(&t.lower));
+    if fuzzy.is_none() && !contains {
+        return None;
     }
+    Some(fuzzy.unwrap_or(0) + if contains { 6_000 } else { 0 })
 }
 
-fn snippet_from_text(text: &str, query_lower: &str, context: usize) -> Snippet {
+pub(crate) fn snippet_from_text(
+    text: &str,
+    query_lower: &str,
+    context: usize,
+    ellipsis: &str,
+) -> Snippet {
     if text.is_empty() {
         return Snippet {
             segments: vec![SnippetSegment {
@@ -232,15 +1104,60 @@ fn snippet_from_text(text: &str, query_lower: &str, context: usize) -> Snippet {
 
     let text_chars: Vec<char> = normalized.chars().collect();
     let lowercase = normalized.to_lowercase();
-    let pos = lowercase.find(query_lower);
+    // Every non-overlapping occurrence as a char range; the first one centers the window.
+    let match_len = query_lower.chars().count();
+    let mut occurrences = Vec::new();
+    let (mut byte, mut chars) = (0, 0);
+    for (byte_idx, _) in lowercase.match_indices(query_lower) {
+        chars += lowercase[byte..byte_idx].chars().count();
+        byte = byte_idx;
+        let start = chars.min(text_chars.len());
+        occurrences.push((start, (chars + match_len).min(text_chars.len())));
+    }
 
-    let (start_char, end_char) = match pos {
-        Some(byte_idx) => {
-            let start_char = lowercase[..byte_idx].chars().count();
-            let match_len = query_lower.chars().count();
-            let end_char = (start_char + match_len).min(text_chars.len());
-            (start_char, end_char)
-        }
+    snippet_around(&text_chars, occurrences, context, ellipsis)
+}
+
+/// Compiles a `--regex` query; the error names the flag and shows the parse error.
+pub fn compile_regex(query: &str) -> Result<Regex> {
+    Regex::new(&normalize_query(query)).map_err(|err| anyhow!("invalid --regex pattern: {err}"))
+}
+
+/// Non-empty matches of `regex` in `text`, up to `REGEX_HIT_CAP`.
+fn regex_hits(regex: &Regex, text: &str) -> usize {
+    regex
+        .find_iter(text)
+        .filter(|m| !m.is_empty())
+        .take(REGEX_HIT_CAP)
+        .count()
+}
+
+/// Like `snippet_from_text`, with `regex` matches (on the whitespace-normalized text)
+/// highlighted instead of query occurrences.
+fn regex_snippet(text: &str, regex: &Regex, context: usize, ellipsis: &str) -> Snippet {
+    let normalized = normalize_snippet_text(text);
+    let text_chars: Vec<char> = normalized.chars().collect();
+    let mut occurrences = Vec::new();
+    let (mut byte, mut chars) = (0, 0);
+    for found in regex.find_iter(&normalized).filter(|m| !m.is_empty()) {
+        chars += normalized[byte..found.start()].chars().count();
+        byte = found.start();
+        occurrences.push((chars, chars + found.as_str().chars().count()));
+    }
+    snippet_around(&text_chars, occurrences, context, ellipsis)
+}
+
+/// Up to `context` chars either side of the first of `occurrences` (char ranges into
+/// `text_chars`), with every occurrence inside that window highlighted. Without
+/// occurrences, the start of the text.
+fn snippet_around(
+    text_chars: &[char],
+    occurrences: Vec<(usize, usize)>,
+    context: usize,
+    ellipsis: &str,
+) -> Snippet {
+    let (start_char, end_char) = match occurrences.first() {
+        Some(&first) => first,
         None => {
             let snippet: String = text_chars.iter().take(context * 2).collect();
             let snippet = normalize_snippet_text(&snippet).trim().to_string();
@@ -276,27 +1193,33 @@ fn snippet_from_text(text: &str, query_lower: &str, context: usize) -> Snippet {
 
     if start_snip > 0 {
         segments.push(SnippetSegment {
-            text: "…".to_string(),
+            text: ellipsis.to_string(),
             highlighted: false,
         });
     }
 
-    if start_snip < start_char {
-        let segment: String = text_chars[start_snip..start_char].iter().collect();
+    let mut cursor = start_snip;
+    for (start, end) in occurrences
+        .into_iter()
+        .filter(|&(start, end)| start >= start_snip && end <= end_snip)
+    {
+        if cursor < start {
+            let segment: String = text_chars[cursor..start].iter().collect();
+            segments.push(SnippetSegment {
+                text: normalize_snippet_text(&segment),
+                highlighted: false,
+            });
+        }
+        let matched: String = text_chars[start..end].iter().collect();
         segments.push(SnippetSegment {
-            text: normalize_snippet_text(&segment),
-            highlighted: false,
+            text: normalize_snippet_text(&matched).trim().to_string(),
+            highlighted: true,
         });
+        cursor = end;
     }
 
-    let matched: String = text_chars[start_char..end_char].iter().collect();
-    segments.push(SnippetSegment {
-        text: normalize_snippet_text(&matched).trim().to_string(),
-        highlighted: true,
-    });
-
-    if end_char < end_snip {
-        let segment: String = text_chars[end_char..end_snip].iter().collect();
+    if cursor < end_snip {
+        let segment: String = text_chars[cursor..end_snip].iter().collect();
         segments.push(SnippetSegment {
             text: normalize_snippet_text(&segment),
             highlighted: false,
@@ -305,7 +1228,7 @@ fn snippet_from_text(text: &str, query_lower: &str, context: usize) -> Snippet {
 
     if end_snip < text_chars.len() {
         segments.push(SnippetSegment {
-            text: "…".to_string(),
+            text: ellipsis.to_string(),
             highlighted: false,
         });
     }
@@ -335,15 +1258,185 @@ fn normalize_snippet_text(text: &str) -> String {
 
     normalized
 }
+/// Char ranges `(start, end, term_index)` of every case-insensitive occurrence of each
+/// distinct query term (word or quoted phrase) in `text`, sorted by start.
+pub fn term_matches(text: &str, query: &str) -> Vec<(usize, usize, usize)> {
+    let mut terms: Vec<String> = Vec::new();
+    for term in parse_query(&split_path_filters(query).0) {
+        if !terms.contains(&term.lower) {
+            terms.push(term.lower);
+        }
+    }
+    let lower = text.to_lowercase();
+    // Lowercasing can change lengths for a few scripts; positions would not line up.
+    if terms.is_empty() || lower.chars().count() != text.chars().count() {
+        return Vec::new();
+    }
+
+    let mut matches = Vec::new();
+    for (term_index, term) in terms.iter().enumerate() {
+        let term_chars = term.chars().count();
+        for (byte_idx, _) in lower.match_indices(term.as_str()) {
+            let start = lower[..byte_idx].chars().count();
+            matches.push((start, start + term_chars, term_index));
+        }
+    }
+    matches.sort_unstable();
+    matches
+}
+
+/// Common words that say nothing about a session's topic.
+const STOPWORDS: &[&str] = &[
+    "about", "after", "also", "been", "before", "being", "could", "does", "don't", "each", "file",
+    "from", "have", "here", "into", "just", "like", "make", "more", "need", "only", "other",
+    "should", "some", "than", "that", "their", "them", "then", "there", "these", "they", "this",
+    "those", "using", "want", "were", "what", "when", "where", "which", "will", "with", "would",
+    "your",
+];
+
+/// The `count` most frequent topical words in the session's searchable text (at least four
+/// letters, no digits, no stopwords); ties go alphabetically so the result is stable.
+pub fn salient_terms(session: &Session, count: usize) -> Vec<String> {
+    let mut frequencies: std::collections::HashMap<&str, usize> = Default::default();
+    for word in session
+        .search_blob_ws_lower
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '\''))
+        .map(|word| word.trim_matches('\''))
+    {
+        if word.chars().count() >= 4
+            && !word.chars().any(|c| c.is_ascii_digit())
+            && !STOPWORDS.contains(&word)
+        {
+            *frequencies.entry(word).or_default() += 1;
+        }
+    }
+    let mut ranked: Vec<(&str, usize)> = frequencies.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    ranked
+        .into_iter()
+        .take(count)
+        .map(|(word, _)| word.to_owned())
+        .collect()
+}
+
+/// The effective search query: trimmed, with internal whitespace runs collapsed to one space.
+pub fn normalize_query(query: &str) -> String {
+    collapse_ws(query)
+}
+
+fn collapse_ws(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut last_space = false;
+    for ch in s.chars() {
+        if ch.is_whitespace() {
+            if !last_space {
+                out.push(' ');
+                last_space = true;
+            }
+        } else {
+            out.push(ch);
+            last_space = false;
+        }
+    }
+    out.trim().to_string()
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::session::MessageRole;
+    use clap::ValueEnum;
+
+    fn session(label: &str, messages: Vec<Message>) -> Session {
+        Session::for_test("0000", label, messages)
+    }
+
+    #[test]
+    fn role_filter_excludes_sessions_matching_only_the_other_role() {
+        let answered = session(
+            "rollout",
+            vec![
+                Message::for_test(MessageRole::User, "how do I deploy"),
+                Message::for_test(MessageRole::Assistant, "use the kubectl rollout command"),
+            ],
+        );
+        let sessions = vec![answered];
+        let only = |role| SearchOptions {
+            role,
+            ..SearchOptions::default()
+        };
+        let hits = search_sessions(&sessions, "kubectl", 10, &only(RoleFilter::User)).unwrap();
+        assert!(hits.is_empty());
+        let hits = search_sessions(&sessions, "kubectl", 10, &only(RoleFilter::Assistant)).unwrap();
+        assert_eq!(hits.len(), 1);
+        // The label matches, but no user message does.
+        let hits = search_sessions(&sessions, "rollout", 10, &only(RoleFilter::User)).unwrap();
+        assert!(hits.is_empty());
+
+        let hits = search_sessions(&sessions, "", 10, &only(RoleFilter::Assistant)).unwrap();
+        let preview = hits[0].matched_message.as_ref().unwrap();
+        assert_eq!(preview.role, MessageRole::Assistant);
+    }
+
+    #[test]
+    fn recency_bonus_halves_every_half_life() {
+        let now = OffsetDateTime::now_utc();
+        let half_life = Duration::days(7);
+
+        assert_eq!(recency_bonus(now, now, half_life), RECENCY_BASE);
+        assert_eq!(
+            recency_bonus(now - half_life, now, half_life),
+            RECENCY_BASE / 2
+        );
+        assert_eq!(
+            recency_bonus(now - half_life * 2, now, half_life),
+            RECENCY_BASE / 4
+        );
+    }
+
+    #[test]
+    fn recency_bonus_decays_smoothly_past_a_month() {
+        let now = OffsetDateTime::now_utc();
+        let half_life = Duration::days(7);
+        let day_old = recency_bonus(now - Duration::days(1), now, half_life);
+        let month_old = recency_bonus(now - Duration::days(31), now, half_life);
+        let two_months_old = recency_bonus(now - Duration::days(62), now, half_life);
+
+        assert!(day_old > month_old);
+        assert!(month_old > two_months_old);
+        assert!(two_months_old > 0);
+    }
+
+    #[test]
+    fn recency_bonus_clamps_future_timestamps() {
+        let now = OffsetDateTime::now_utc();
+        let bonus = recency_bonus(now + Duration::hours(3), now, Duration::days(7));
+        assert_eq!(bonus, RECENCY_BASE);
+    }
+
+    #[test]
+    fn search_messages_ranks_matching_messages_within_session() {
+        let session = session(
+            "demo",
+            vec![
+                Message::for_test(MessageRole::User, "set up the build"),
+                Message::for_test(MessageRole::Assistant, "the shader flickers"),
+                Message::for_test(MessageRole::User, "fix the shader flicker please"),
+            ],
+        );
+
+        let matches = search_messages(&session, "shader flicker", 10, &SearchOptions::default());
+
+        let indices: Vec<usize> = matches.iter().map(|m| m.index).collect();
+        assert!(indices.contains(&1) && indices.contains(&2));
+        assert!(!indices.contains(&0));
+        assert!(matches.windows(2).all(|w| w[0].score >= w[1].score));
+    }
 
     #[test]
     fn snippet_with_match_normalizes_whitespace() {
         let text = "alpha\nbeta   gamma\t\ndelta";
-        let snippet = snippet_from_text(text, "beta", 30);
+        let snippet = snippet_from_text(text, "beta", 30, "…");
         let combined: String = snippet
             .segments
             .iter()
@@ -359,22 +1452,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn snippet_highlights_every_occurrence_in_the_window() {
+        let text = format!("Beta one\nbeta two beta{}beta", " x".repeat(40));
+        let snippet = snippet_from_text(&text, "beta", 15, "…");
+        let highlighted: Vec<&str> = snippet
+            .segments
+            .iter()
+            .filter(|segment| segment.highlighted)
+            .map(|segment| segment.text.as_str())
+            .collect();
+        // The last occurrence falls outside the context window.
+        assert_eq!(highlighted, ["Beta", "beta", "beta"]);
+        let combined: String = snippet.segments.iter().map(|s| s.text.as_str()).collect();
+        assert!(combined.starts_with("Beta one beta two beta x"));
+        assert!(combined.ends_with('…'));
+    }
+
     #[test]
     fn snippet_without_match_normalizes_whitespace() {
         let text = "foo\n\nbar\tbaz";
-        let snippet = snippet_from_text(text, "", 20);
+        let snippet = snippet_from_text(text, "", 20, "…");
 
         assert_eq!(snippet.segments.len(), 1);
         assert_eq!(snippet.segments[0].text, "foo bar baz");
         assert!(!snippet.segments[0].highlighted);
     }
 
+    #[test]
+    fn snippet_uses_configured_ellipsis() {
+        let text = format!("{}needle{}", "a".repeat(50), "b".repeat(50));
+        let snippet = snippet_from_text(&text, "needle", 10, "...");
+
+        assert_eq!(snippet.segments.first().unwrap().text, "...");
+        assert_eq!(snippet.segments.last().unwrap().text, "...");
+        assert!(snippet.segments.iter().all(|s| !s.text.contains('…')));
+    }
+
     #[test]
     fn snippet_balances_context_when_room_on_both_sides() {
         let prefix = "a".repeat(80);
         let suffix = "b".repeat(80);
         let text = format!("{prefix}match{suffix}");
-        let snippet = snippet_from_text(&text, "match", 20);
+        let snippet = snippet_from_text(&text, "match", 20, "…");
 
         let combined: String = snippet
             .segments
@@ -388,20 +1508,561 @@ mod tests {
         assert!(match_idx > total_len / 4, "match too close to start");
         assert!(match_idx < (total_len * 3) / 4, "match too close to end");
     }
-}
-fn collapse_ws(s: &str) -> String {
-    let mut out = String::with_capacity(s.len());
-    let mut last_space = false;
-    for ch in s.chars() {
-        if ch.is_whitespace() {
-            if !last_space {
-                out.push(' ');
-                last_space = true;
-            }
-        } else {
-            out.push(ch);
-            last_space = false;
+
+    #[test]
+    fn term_matches_reports_each_term_separately() {
+        let matches = term_matches("Tokio runtime, then tokio again", "tokio RUNTIME tokio");
+        assert_eq!(matches, vec![(0, 5, 0), (6, 13, 1), (20, 25, 0)]);
+        assert!(term_matches("anything", "  ").is_empty());
+    }
+
+    #[test]
+    fn path_prefix_filters_on_cwd_before_scoring_the_rest() {
+        let in_dir = |label: &str, cwd: Option<&str>, text: &str| Session {
+            cwd: cwd.map(std::path::PathBuf::from),
+            ..session(label, vec![Message::for_test(MessageRole::User, text)])
+        };
+        let sessions = vec![
+            in_dir("api", Some("/work/MyProject/api"), "fix the auth flow"),
+            in_dir("web", Some("/work/other"), "fix the auth flow"),
+            in_dir("notes", Some(r"C:\work\myproject"), "lunch plans"),
+            in_dir("loose", None, "auth everywhere"),
+        ];
+        let labels = |query: &str| {
+            let mut labels: Vec<String> =
+                search_sessions(&sessions, query, 10, &SearchOptions::default())
+                    .unwrap()
+                    .into_iter()
+                    .map(|r| r.session.label.clone())
+                    .collect();
+            labels.sort();
+            labels
+        };
+        assert_eq!(labels("path:myproject auth"), ["api"]);
+        assert_eq!(labels("path:myproject"), ["api", "notes"]);
+        assert_eq!(labels("path:work/myproject path:api"), ["api"]);
+        assert_eq!(labels("auth"), ["api", "loose", "web"]);
+        assert_eq!(
+            split_path_filters(r#"path:App "see path:x" fix"#),
+            (r#""see path:x" fix"#.to_string(), vec!["app".to_string()])
+        );
+    }
+
+    #[test]
+    fn phrases_match_as_written_and_terms_all_need_to_appear() {
+        let sessions = vec![
+            session(
+                "adjacent",
+                vec![Message::for_test(
+                    MessageRole::User,
+                    "the async runtime is fast",
+                )],
+            ),
+            session(
+                "apart",
+                vec![Message::for_test(
+                    MessageRole::User,
+                    "runtime configured first, then the async code",
+                )],
+            ),
+            session(
+                "partial",
+                vec![Message::for_test(MessageRole::User, "only async here")],
+            ),
+        ];
+        let labels = |query: &str| {
+            let mut labels: Vec<String> =
+                search_sessions(&sessions, query, 10, &SearchOptions::default())
+                    .unwrap()
+                    .into_iter()
+                    .map(|r| r.session.label.clone())
+                    .collect();
+            labels.sort();
+            labels
+        };
+        assert_eq!(labels("async runtime"), ["adjacent", "apart"]);
+        assert_eq!(labels("\"async runtime\""), ["adjacent"]);
+        assert_eq!(labels("\"runtime configured\" code"), ["apart"]);
+
+        let terms = parse_query(r#"fix "Async  Runtime" "unterminated"#);
+        let parsed: Vec<(&str, bool)> =
+            terms.iter().map(|t| (t.lower.as_str(), t.phrase)).collect();
+        assert_eq!(
+            parsed,
+            [
+                ("fix", false),
+                ("async runtime", true),
+                ("unterminated", true)
+            ]
+        );
+    }
+
+    #[test]
+    fn regex_mode_matches_patterns_and_highlights_the_match() {
+        let sessions = vec![
+            session(
+                "todo",
+                vec![Message::for_test(
+                    MessageRole::User,
+                    "left a TODO(alice) in parser.rs",
+                )],
+            ),
+            session(
+                "plain",
+                vec![Message::for_test(MessageRole::User, "todo list for alice")],
+            ),
+        ];
+        let options = SearchOptions {
+            regex: true,
+            ..SearchOptions::default()
+        };
+        let results = search_sessions(&sessions, r"TODO\(\w+\)", 10, &options).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].session.label, "todo");
+        let highlighted: Vec<&str> = results[0]
+            .snippet
+            .segments
+            .iter()
+            .filter(|s| s.highlighted)
+            .map(|s| s.text.as_str())
+            .collect();
+        assert_eq!(highlighted, ["TODO(alice)"]);
+
+        assert_eq!(
+            search_sessions(&sessions, "(?i)todo", 10, &options)
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(compile_regex("TODO(").is_err());
+        assert!(
+            search_sessions(&sessions, "TODO(", 10, &options)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn internal_whitespace_in_query_is_collapsed() {
+        let sessions = vec![
+            session(
+                "one",
+                vec![Message::for_test(MessageRole::User, "tokio async runtime")],
+            ),
+            session(
+                "two",
+                vec![Message::for_test(MessageRole::User, "async tokio setup")],
+            ),
+        ];
+        let options = SearchOptions::default();
+        let spaced = search_sessions(&sessions, "  tokio   async ", 10, &options).unwrap();
+        let single = search_sessions(&sessions, "tokio async", 10, &options).unwrap();
+        let summary = |results: &[SearchResult]| {
+            results
+                .iter()
+                .map(|r| (r.session.label.clone(), r.text_score))
+                .collect::<Vec<_>>()
+        };
+        assert!(!single.is_empty());
+        assert_eq!(summary(&spaced), summary(&single));
+    }
+
+    #[test]
+    fn all_terms_drops_sessions_missing_a_term() {
+        let sessions = vec![
+            session(
+                "both",
+                vec![Message::for_test(MessageRole::User, "tokio runtime")],
+            ),
+            // Fuzzy-matches the query through the label but never contains "runtime".
+            session(
+                "tokio rx untime",
+                vec![Message::for_test(MessageRole::User, "tokio only")],
+            ),
+        ];
+        // A single term keeps fuzzy label matches unless `--all-terms` is set.
+        let labels = |options: &SearchOptions| {
+            search_sessions(&sessions, "runtime", 10, options)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.session.label.clone())
+                .collect::<Vec<_>>()
+        };
+        assert!(labels(&SearchOptions::default()).contains(&"tokio rx untime".to_string()));
+        let strict = SearchOptions {
+            all_terms: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(labels(&strict), vec!["both".to_string()]);
+    }
+
+    #[test]
+    fn score_sort_ignores_recency() {
+        let mut old = session(
+            "tokio",
+            vec![Message::for_test(MessageRole::User, "tokio tokio")],
+        );
+        old.uuid = "b".into();
+        old.updated_at -= Duration::days(365);
+        let mut recent = session(
+            "notes",
+            vec![Message::for_test(MessageRole::User, "about tokio")],
+        );
+        recent.uuid = "a".into();
+        let sessions = vec![recent, old];
+
+        let labels = |sort: SortMode| {
+            let options = SearchOptions {
+                sort,
+                ..SearchOptions::default()
+            };
+            search_sessions(&sessions, "tokio", 10, &options)
+                .unwrap()
+                .into_iter()
+                .map(|r| (r.session.label.clone(), r.score == r.text_score))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(labels(SortMode::Recent)[0].0, "notes");
+        assert_eq!(
+            labels(SortMode::Score),
+            vec![("tokio".to_string(), true), ("notes".to_string(), true)]
+        );
+
+        // Equal relevance falls back to the newer session.
+        let mut older = session("older", vec![Message::for_test(MessageRole::User, "tokio")]);
+        older.uuid = "a".into();
+        older.updated_at -= Duration::days(3);
+        let mut newer = session("newer", vec![Message::for_test(MessageRole::User, "tokio")]);
+        newer.uuid = "b".into();
+        let options = SearchOptions {
+            sort: SortMode::Score,
+            ..SearchOptions::default()
+        };
+        let results = search_sessions(&[older, newer], "tokio", 10, &options).unwrap();
+        assert_eq!(results[0].session.label, "newer");
+        assert_eq!(SortMode::from_str("time", true), Ok(SortMode::Recent));
+    }
+
+    #[test]
+    fn created_time_basis_orders_by_creation() {
+        let now = OffsetDateTime::now_utc();
+        let mut touched = session(
+            "touched",
+            vec![Message::for_test(MessageRole::User, "tokio")],
+        );
+        touched.created_at = Some(now - Duration::days(30));
+        let mut fresh = session("fresh", vec![Message::for_test(MessageRole::User, "tokio")]);
+        fresh.created_at = Some(now - Duration::days(1));
+        fresh.updated_at = now - Duration::days(2);
+        let sessions = vec![touched, fresh];
+
+        let first = |time_basis: TimeBasis| {
+            let options = SearchOptions {
+                time_basis,
+                ..SearchOptions::default()
+            };
+            search_sessions(&sessions, "tokio", 10, &options).unwrap()[0]
+                .session
+                .label
+                .clone()
+        };
+        assert_eq!(first(TimeBasis::Match), "touched");
+        assert_eq!(first(TimeBasis::Created), "fresh");
+    }
+
+    #[test]
+    fn message_recency_weight_prefers_later_matches() {
+        let sessions = vec![session(
+            "notes",
+            vec![
+                Message::for_test(MessageRole::User, "tokio question"),
+                Message::for_test(MessageRole::Assistant, "unrelated"),
+                Message::for_test(MessageRole::User, "tokio follow-up"),
+            ],
+        )];
+        let matched = |weight: i64| {
+            let options = SearchOptions {
+                message_recency_weight: weight,
+                ..SearchOptions::default()
+            };
+            search_sessions(&sessions, "tokio", 10, &options).unwrap()[0]
+                .matched_message
+                .as_ref()
+                .unwrap()
+                .full_text
+                .clone()
+        };
+        assert_eq!(matched(0), "tokio question");
+        assert_eq!(matched(1_000), "tokio follow-up");
+    }
+
+    #[test]
+    fn in_session_matches_skip_repeated_snippets() {
+        let boilerplate = "Run cargo test before committing the migration";
+        let session = session(
+            "ci",
+            vec![
+                Message::for_test(MessageRole::User, boilerplate),
+                Message::for_test(MessageRole::Assistant, "ok, running the migration now"),
+                Message::for_test(
+                    MessageRole::User,
+                    &format!("  {}  ", boilerplate.to_uppercase()),
+                ),
+                Message::for_test(
+                    MessageRole::User,
+                    "Run cargo test before committing the migration!",
+                ),
+            ],
+        );
+        let matches = search_messages(&session, "migration", 10, &SearchOptions::default());
+        let indexes: Vec<usize> = matches.iter().map(|m| m.index).collect();
+        assert_eq!(matches.len(), 2, "{indexes:?}");
+        assert!(indexes.contains(&1));
+    }
+
+    #[test]
+    fn salient_terms_prefer_frequent_topical_words() {
+        let session = session(
+            "rollout",
+            vec![
+                Message::for_test(
+                    MessageRole::User,
+                    "the tokio runtime panics with tokio spawn",
+                ),
+                Message::for_test(
+                    MessageRole::Assistant,
+                    "that tokio runtime needs a spawn_blocking",
+                ),
+            ],
+        );
+        assert_eq!(salient_terms(&session, 2), vec!["tokio", "runtime"]);
+    }
+
+    #[test]
+    fn literal_matcher_rejects_scattered_characters() {
+        let session = session(
+            "the matrix compiler",
+            vec![Message::for_test(MessageRole::User, "notes")],
+        );
+        let matched = |matcher: MatcherKind, query: &str| {
+            let options = SearchOptions {
+                matcher,
+                ..SearchOptions::default()
+            };
+            Scorer::with_options(query, options)
+                .score_session(&session)
+                .is_some()
+        };
+        // "tmc" is a fuzzy subsequence of the label but not a substring.
+        assert!(matched(MatcherKind::Skim, "tmc"));
+        assert!(!matched(MatcherKind::Literal, "tmc"));
+        assert!(matched(MatcherKind::Literal, "Matrix"));
+    }
+
+    #[test]
+    fn projects_match_terms_spread_across_their_sessions() {
+        let now = OffsetDateTime::now_utc();
+        let in_dir = |uuid: &str, cwd: &str, text: &str, age_days: i64| {
+            let mut s = session(uuid, vec![Message::for_test(MessageRole::User, text)]);
+            s.uuid = uuid.to_string();
+            s.cwd = Some(cwd.into());
+            s.updated_at = now - Duration::days(age_days);
+            s
+        };
+        let sessions = vec![
+            in_dir("old", "/proj", "tokio runtime setup", 3),
+            in_dir("new", "/proj", "websocket handshake", 1),
+            in_dir("elsewhere", "/other", "tokio only", 2),
+        ];
+        let options = SearchOptions {
+            all_terms: true,
+            ..SearchOptions::default()
+        };
+        let results = search_projects(&sessions, "tokio websocket", 10, &options).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].session.label, "/proj (2 sessions)");
+
+        let results = search_projects(&sessions, "runtime", 10, &options).unwrap();
+        assert_eq!(results[0].session.uuid, "old");
+    }
+
+    #[test]
+    fn label_only_matches_snippet_the_label() {
+        let sessions = vec![session(
+            "deploy checklist",
+            vec![Message::for_test(
+                MessageRole::User,
+                "unrelated opening prompt",
+            )],
+        )];
+        let result =
+            &search_sessions(&sessions, "checklist", 10, &SearchOptions::default()).unwrap()[0];
+        assert!(result.matched_message.is_none());
+        let highlighted: Vec<&str> = result
+            .snippet
+            .segments
+            .iter()
+            .filter(|s| s.highlighted)
+            .map(|s| s.text.as_str())
+            .collect();
+        assert_eq!(highlighted, ["checklist"]);
+    }
+
+    #[test]
+    fn message_cache_matches_uncached_scoring_as_the_query_grows() {
+        let sessions: Vec<Session> = [
+            (
+                "a",
+                vec!["fix the tokio runtime", "runtime panicked in main"],
+            ),
+            ("b", vec!["deploy checklist", "run the migrations first"]),
+            ("c", vec!["Runtime tuning notes", "tokio worker threads"]),
+            (
+                "d",
+                vec!["notes", "long notes ending with the tokio runtime"],
+            ),
+        ]
+        .into_iter()
+        .map(|(name, texts)| {
+            let messages = texts
+                .into_iter()
+                .map(|t| Message::for_test(MessageRole::User, t))
+                .collect();
+            let mut s = session(name, messages);
+            s.path = std::path::PathBuf::from(format!("/sessions/{name}.jsonl"));
+            s
+        })
+        .collect();
+        let scores = |scorer: &mut Scorer| -> Vec<(i64, Option<String>)> {
+            sessions
+                .iter()
+                .map(|s| match scorer.score_session(s) {
+                    Some(r) => (r.score, r.matched_message.map(|m| m.text)),
+                    None => (i64::MIN, None),
+                })
+                .collect()
+        };
+
+        let mut cache = MessageCache::default();
+        for query in [
+            "r",
+            "ru",
+            "run",
+            "runt",
+            "runtime",
+            "runtime t",
+            "runtime tok",
+        ] {
+            let mut cached = Scorer::with_cache(query, SearchOptions::default(), cache);
+            let mut uncached = Scorer::with_options(query, SearchOptions::default());
+            assert_eq!(
+                scores(&mut cached),
+                scores(&mut uncached),
+                "query {query:?}"
+            );
+            // Under --low-memory the worker also scores the compacted copy of a
+            // session whose full text the TUI reloaded; only previews match there.
+            let mut compacted = sessions[3].clone();
+            compacted.compacted = true;
+            compacted.messages[1].text = "long notes".into();
+            compacted.messages[1].compact();
+            cached.score_session(&compacted);
+            cache = cached.into_cache();
         }
     }
-    out.trim().to_string()
+
+    #[test]
+    fn first_message_ignores_matches_after_the_opening_prompt() {
+        let opened = session(
+            "opened",
+            vec![
+                Message::for_test(MessageRole::User, "refactor the websocket reconnect logic"),
+                Message::for_test(MessageRole::Assistant, "done, also bumped tokio"),
+            ],
+        );
+        let buried = session(
+            "buried",
+            vec![
+                Message::for_test(MessageRole::User, "fix the failing ci job"),
+                Message::for_test(MessageRole::Assistant, "the websocket test was flaky"),
+                Message::for_test(
+                    MessageRole::User,
+                    "now refactor the websocket reconnect logic",
+                ),
+            ],
+        );
+        let options = SearchOptions {
+            first_message: true,
+            ..SearchOptions::default()
+        };
+        let matched = |query: &str, options: &SearchOptions| -> Vec<String> {
+            let mut scorer = Scorer::with_options(query, options.clone());
+            [&opened, &buried]
+                .into_iter()
+                .filter(|s| scorer.score_session(s).is_some())
+                .map(|s| s.label.clone())
+                .collect()
+        };
+
+        assert_eq!(
+            matched("websocket reconnect", &SearchOptions::default()).len(),
+            2
+        );
+        assert_eq!(matched("websocket reconnect", &options), ["opened"]);
+        assert_eq!(matched("tokio", &options), Vec::<String>::new());
+        assert_eq!(matched("ci job", &options), ["buried"]);
+        // Every term must be in the opening prompt, not just somewhere in the session.
+        assert_eq!(matched("ci websocket", &options), Vec::<String>::new());
+        assert_eq!(matched("buried", &options), Vec::<String>::new());
+
+        let scored = Scorer::with_options("websocket", options.clone())
+            .score_session(&opened)
+            .unwrap();
+        assert_eq!(scored.matched_message.unwrap().role, MessageRole::User);
+        let snippet: String = scored
+            .snippet
+            .segments
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect();
+        assert!(snippet.contains("websocket reconnect"));
+
+        let regex = SearchOptions {
+            regex: true,
+            ..options
+        };
+        assert_eq!(matched("reconnect", &regex), ["opened"]);
+    }
+
+    #[test]
+    fn min_score_drops_weak_fuzzy_matches() {
+        let sessions = vec![
+            session(
+                "rollout",
+                vec![Message::for_test(MessageRole::User, "deploy checklist")],
+            ),
+            session(
+                "dxeployyment",
+                vec![Message::for_test(MessageRole::User, "unrelated")],
+            ),
+        ];
+        let options = |min_score| SearchOptions {
+            sort: SortMode::Score,
+            min_score,
+            ..SearchOptions::default()
+        };
+        let labels = |min_score| -> Vec<String> {
+            search_sessions(&sessions, "deploy", 10, &options(min_score))
+                .unwrap()
+                .into_iter()
+                .map(|r| r.session.label.clone())
+                .collect()
+        };
+        assert_eq!(labels(0), ["rollout", "dxeployyment"]);
+        assert_eq!(labels(10_000), ["rollout"]);
+        assert!(labels(i64::MAX).is_empty());
+
+        let listed = search_sessions(&sessions, "", 10, &options(i64::MAX)).unwrap();
+        assert_eq!(listed.len(), 2);
+    }
 }
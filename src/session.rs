@@ -1,14 +1,16 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum MessageRole {
     User,
     Assistant,
+    /// A tool call's command or arguments, or its output (`--include-tools`).
+    Tool,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -23,12 +25,48 @@ pub struct Message {
     pub full_text_lower: String,
     #[serde(skip_serializing)]
     pub full_text_ws_lower: String,
+    /// Record the message was extracted from: the 0-based line in a JSONL file, or
+    /// the element index in a JSON array export. Merged turns keep the first one.
+    #[serde(skip_serializing)]
+    pub source_line: usize,
 }
 
+impl Message {
+    /// Brings every text form to Unicode NFC (`--unicode-normalize`).
+    pub fn normalize_unicode(&mut self) {
+        for text in [
+            &mut self.text,
+            &mut self.full_text,
+            &mut self.full_text_lower,
+            &mut self.full_text_ws_lower,
+        ] {
+            *text = crate::util::nfc(text);
+        }
+    }
+
+    /// Replaces the full text with the short preview so only the preview stays in memory.
+    pub fn compact(&mut self) {
+        self.full_text = self.text.clone();
+        self.full_text_lower = self.full_text.to_lowercase();
+        self.full_text_ws_lower = self
+            .full_text_lower
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+}
+
+/// One parsed session file. Part of the library API: fields may be added, but
+/// existing ones keep their names and meaning within a minor version.
 #[derive(Debug, Clone, Serialize)]
 pub struct Session {
     pub uuid: String,
+    /// Display label: the Codex title when present (unless `--filename-labels`), else
+    /// the label parsed from the filename.
     pub label: String,
+    /// Title or summary from the session's metadata records, when Codex set one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title_meta: Option<String>,
     #[serde(skip_serializing)]
     pub label_lower: String,
     #[serde(skip_serializing)]
@@ -36,10 +74,17 @@ pub struct Session {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub created_at: Option<OffsetDateTime>,
     pub updated_at: OffsetDateTime,
+    /// Timestamp of the first message that has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub earliest_message_time: Option<OffsetDateTime>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub latest_message_time: Option<OffsetDateTime>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cwd: Option<PathBuf>,
+    /// Model named by the session's `turn_context`/`session_meta` records; the latest
+    /// one when the session switched models.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
     pub messages: Vec<Message>,
     #[serde(skip_serializing)]
     pub search_blob: String,
@@ -49,6 +94,16 @@ pub struct Session {
     pub search_blob_ws_lower: String,
     #[serde(skip_serializing)]
     pub uuid_lower: String,
+    /// Loaded from the archive directory (`--include-archive`).
+    pub archived: bool,
+    /// Assistant reasoning text, only filled with `--include-reasoning`; searched but not previewed.
+    #[serde(skip_serializing)]
+    pub reasoning: String,
+    #[serde(skip_serializing)]
+    pub reasoning_ws_lower: String,
+    /// Messages only hold previews; reload from `path` for full text.
+    #[serde(skip_serializing)]
+    pub compacted: bool,
 }
 
 impl Session {
@@ -61,27 +116,125 @@ impl Session {
     }
 }
 
+/// Whitespace runs collapsed to single spaces, as in the `*_ws_lower` fields.
+#[cfg(test)]
+fn collapse_ws(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+impl Message {
+    /// A message whose every text form holds `text`.
+    pub(crate) fn for_test(role: MessageRole, text: &str) -> Self {
+        let lower = text.to_lowercase();
+        Self {
+            role,
+            text: text.to_owned(),
+            timestamp: None,
+            full_text: text.to_owned(),
+            full_text_ws_lower: collapse_ws(&lower),
+            full_text_lower: lower,
+            source_line: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Session {
+    /// A session of `messages` updated now, with its search blob built from them and
+    /// `label`; tests set any other field with struct update syntax.
+    pub(crate) fn for_test(uuid: &str, label: &str, messages: Vec<Message>) -> Self {
+        let search_blob = messages
+            .iter()
+            .map(|m| m.full_text.as_str())
+            .chain([label])
+            .collect::<Vec<_>>()
+            .join("\n");
+        let search_blob_lower = search_blob.to_lowercase();
+        Self {
+            uuid: uuid.to_owned(),
+            label: label.to_owned(),
+            title_meta: None,
+            label_lower: label.to_lowercase(),
+            path: PathBuf::from(format!("/sessions/{uuid}.jsonl")),
+            created_at: None,
+            updated_at: OffsetDateTime::now_utc(),
+            earliest_message_time: None,
+            latest_message_time: None,
+            cwd: None,
+            model: None,
+            messages,
+            search_blob_ws_lower: collapse_ws(&search_blob_lower),
+            search_blob_lower,
+            search_blob,
+            uuid_lower: uuid.to_lowercase(),
+            archived: false,
+            reasoning: String::new(),
+            reasoning_ws_lower: String::new(),
+            compacted: false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct SnippetSegment {
     pub text: String,
     pub highlighted: bool,
 }
 
+/// Matched text split into highlighted and plain runs. Stable like `Session`.
 #[derive(Debug, Clone, Serialize)]
 pub struct Snippet {
     pub segments: Vec<SnippetSegment>,
 }
 
+/// A single message inside one session, ranked by how well it matches the query.
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageMatch {
+    pub index: usize,
+    pub role: MessageRole,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<OffsetDateTime>,
+    pub score: i64,
+    pub snippet: Snippet,
+}
+
+/// Which timestamp a result displays and sorts by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TimeBasis {
+    /// Matched message, else latest message, else file modification time
+    #[default]
+    Match,
+    /// Creation time from the filename, else file modification time
+    Created,
+    /// File modification time
+    Modified,
+}
+
+/// A ranked session, as returned by `codex_search_rust::search`. Stable like `Session`.
 #[derive(Debug, Clone, Serialize)]
 pub struct SearchResult {
     pub session: Arc<Session>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub matched_message: Option<Message>,
     pub score: i64,
+    /// Textual part of `score`, without the recency bonus. Only meaningful relative to
+    /// the other results of the same search, so it is left out of the JSON output.
+    #[serde(skip)]
+    pub text_score: i64,
     pub snippet: Snippet,
 }
 
 impl SearchResult {
+    /// Textual relevance as a percentage of the best `text_score` in the result set.
+    pub fn relevance_percent(&self, best_text_score: i64) -> Option<u8> {
+        if best_text_score <= 0 {
+            return None;
+        }
+        let ratio = self.text_score.max(0) as f64 / best_text_score as f64;
+        Some((ratio * 100.0).round().clamp(0.0, 100.0) as u8)
+    }
+
     pub fn match_timestamp(&self) -> OffsetDateTime {
         self.matched_message
             .as_ref()
@@ -89,4 +242,13 @@ impl SearchResult {
             .or(self.session.latest_message_time)
             .unwrap_or(self.session.updated_at)
     }
+
+    /// The timestamp shown for this result and used to order it under `basis`.
+    pub fn timestamp(&self, basis: TimeBasis) -> OffsetDateTime {
+        match basis {
+            TimeBasis::Match => self.match_timestamp(),
+            TimeBasis::Created => self.session.created_at.unwrap_or(self.session.updated_at),
+            TimeBasis::Modified => self.session.updated_at,
+        }
+    }
 }
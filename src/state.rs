@@ -0,0 +1,115 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use directories::BaseDirs;
+
+const APP_DIR: &str = "codex-search";
+
+/// Persisted state that `--clear-state` can remove. `History` and `Bookmarks` are
+/// accepted for symmetry with other tools but nothing is stored for them yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StateKind {
+    History,
+    Bookmarks,
+    Cache,
+    All,
+}
+
+impl StateKind {
+    fn name(self) -> &'static str {
+        match self {
+            StateKind::History => "history",
+            StateKind::Bookmarks => "bookmarks",
+            StateKind::Cache => "cache",
+            StateKind::All => "all",
+        }
+    }
+}
+
+/// Where each kind of persisted state lives. The cache dir only holds data that can
+/// be rebuilt from the session files (the parsed-session index).
+#[derive(Debug, Clone)]
+pub struct StatePaths {
+    pub cache: PathBuf,
+}
+
+impl StatePaths {
+    pub fn resolve() -> Result<Self> {
+        let base = BaseDirs::new().context("failed to determine home directory")?;
+        Ok(Self {
+            cache: base.cache_dir().join(APP_DIR),
+        })
+    }
+
+    /// `(kind, path)` pairs selected by `kind`; `All` expands to every kind.
+    pub fn entries(&self, kind: StateKind) -> Vec<(StateKind, &PathBuf)> {
+        let all = [(StateKind::Cache, &self.cache)];
+        all.into_iter()
+            .filter(|(candidate, _)| kind == StateKind::All || kind == *candidate)
+            .collect()
+    }
+}
+
+/// Prints every state location and whether it currently exists.
+pub fn print_paths(paths: &StatePaths) {
+    for (kind, path) in paths.entries(StateKind::All) {
+        let status = if path.exists() { "" } else { " (missing)" };
+        println!("{:<10}{}{}", kind.name(), path.display(), status);
+    }
+}
+
+/// Deletes the selected state files/directories and reports each removal.
+pub fn clear(paths: &StatePaths, kind: StateKind) -> Result<()> {
+    let entries = paths.entries(kind);
+    if entries.is_empty() {
+        println!("nothing stored for {}", kind.name());
+        return Ok(());
+    }
+    let mut removed = 0usize;
+    for (entry, path) in entries {
+        if path.is_dir() {
+            std::fs::remove_dir_all(path)
+                .with_context(|| format!("failed to remove {}", path.display()))?;
+        } else if path.exists() {
+            std::fs::remove_file(path)
+                .with_context(|| format!("failed to remove {}", path.display()))?;
+        } else {
+            continue;
+        }
+        println!("removed {} ({})", path.display(), entry.name());
+        removed += 1;
+    }
+    if removed == 0 {
+        println!("nothing to remove ({})", kind.name());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clear_removes_the_selected_kind() {
+        let tmp = tempfile::tempdir().unwrap();
+        let paths = StatePaths {
+            cache: tmp.path().join("cache"),
+        };
+        std::fs::create_dir_all(&paths.cache).unwrap();
+        std::fs::write(paths.cache.join("index.bin"), "").unwrap();
+        clear(&paths, StateKind::Cache).unwrap();
+        assert!(!paths.cache.exists());
+
+        std::fs::create_dir_all(&paths.cache).unwrap();
+        clear(&paths, StateKind::All).unwrap();
+        assert!(!paths.cache.exists());
+        // Nothing left to remove is not an error.
+        clear(&paths, StateKind::All).unwrap();
+
+        std::fs::create_dir_all(&paths.cache).unwrap();
+        assert!(paths.entries(StateKind::History).is_empty());
+        clear(&paths, StateKind::Bookmarks).unwrap();
+        assert!(paths.cache.exists());
+    }
+}
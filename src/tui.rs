@@ -1,11 +1,11 @@
-use std::cmp::Ordering;
-use std::io::{self, Stdout};
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Stdout, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
-use crossbeam_channel::{Receiver, TryRecvError, unbounded, Sender};
+use crossbeam_channel::{Receiver, Sender, TryRecvError, unbounded};
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 use crossterm::{execute, terminal};
@@ -15,42 +15,215 @@ use ratatui::backend::CrosstermBackend;
 use ratatui::layout::{Constraint, Direction, Layout};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span, Text};
-use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState};
+use ratatui::widgets::{
+    Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table, TableState, Wrap,
+};
 use time::OffsetDateTime;
 
 use nucleo::pattern::{CaseMatching, Normalization};
 use nucleo::{Config, Injector, Nucleo, Utf32String};
 
-use crate::cli::spawn_resume_command;
-use crate::discovery::SessionStream;
-use crate::search::Scorer;
-use crate::session::{SearchResult, Session};
-use crate::util::format_relative;
+use crate::cli::{
+    ResumePrompt, ResumeTarget, Selection, fill_resume_prompts, resume_prompts, resume_session,
+};
+use crate::discovery::{self, DiscoveryOptions, SessionStream, SessionWatcher};
+use crate::index::SessionIndex;
+use crate::keys::{Action, KeyMap};
+use crate::search::{
+    MessageCache, RoleFilter, Scorer, SearchOptions, SortMode, compile_regex, normalize_query,
+    salient_terms, term_matches,
+};
+use crate::session::{Message, MessageRole, SearchResult, Session, TimeBasis};
+use crate::util::{
+    Glyphs, format_day, format_duration, format_relative, local_date, nfc, normalize_path,
+    paths_related,
+};
 
-// Limit how many nucleo matches we rescore per refresh.
-// Keep this small to avoid blocking the UI thread.
-const MAX_TUI_CANDIDATES: usize = 100;
 // Limit how many sessions we ingest from the stream each UI tick.
 const MAX_INGEST_PER_TICK: usize = 20;
 // Coalesce rescoring to at most once per interval to avoid UI spikes.
 const MIN_REBUILD_INTERVAL_MS: u64 = 80;
+// Advance the indexing spinner this often; the event loop redraws every ~30 ms.
+const SPINNER_FRAME_MS: u128 = 100;
+// Below this terminal width the Updated column is hidden so the preview gets the space.
+const MIN_WIDTH_FOR_UPDATED_COLUMN: u16 = 60;
+// Terms taken from the selected session for a Ctrl-R related-sessions query; every term
+// must appear, so more terms quickly narrow the results to the session itself.
+const RELATED_TERM_COUNT: usize = 3;
+// Cells in the `--progress-bar` indexing bar.
+const PROGRESS_BAR_WIDTH: usize = 20;
+// Lines scrolled by PgUp/PgDn in a popup.
+const POPUP_PAGE: u16 = 10;
+// Seconds each rotating footer hint stays before the next one.
+const FOOTER_HINT_SECS: u64 = 4;
+// At this width the preview pane sits right of the results; below it, underneath.
+const MIN_WIDTH_FOR_SIDE_PREVIEW: u16 = 100;
+// `--low-memory`: full-text sessions kept for displayed results before the cache is reset.
+const MAX_FULL_SESSIONS: usize = 256;
 
 pub struct TuiConfig {
     pub limit: usize,
     pub resume_command: String,
+    pub resume_in: ResumeTarget,
     pub dry_run: bool,
     pub initial_query: String,
     pub empty_status: Option<String>,
     pub total_expected: usize,
     pub filter_cwd: Option<std::path::PathBuf>,
+    pub search: SearchOptions,
+    pub glyphs: Glyphs,
+    pub browse: bool,
+    /// Prefix previews with a colored marker for the matched message's role.
+    pub role_glyphs: bool,
+    /// Draw a progress bar in the results title until indexing finishes.
+    pub progress_bar: bool,
+    /// Key bindings, from the config file's `[keys]` table.
+    pub keys: KeyMap,
+    /// Narrow each query's candidates with an inverted index (`--index`).
+    pub index: bool,
+    /// Pick up sessions created or changed while the TUI is open (`--watch`).
+    pub watch: bool,
+    pub discovery: DiscoveryOptions,
+    /// Where Ctrl-E writes the current results as JSON.
+    pub export_path: PathBuf,
+    pub theme: Theme,
+}
+
+/// Colors and emphasis for the TUI.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// Applied to the whole screen first.
+    pub base: Style,
+    /// Status bar, column headers and counts.
+    pub muted: Style,
+    /// Row separators and de-emphasized markers.
+    pub subtle: Style,
+    /// The selected row.
+    pub selection: Style,
+    /// Prompt, day headers and focused borders.
+    pub accent: Style,
+    /// Colors cycled across query terms in previews.
+    pub terms: [Color; 5],
+    /// Extra emphasis on query terms, kept distinct from the selection bar.
+    pub term_modifier: Modifier,
+    /// Relevance percentage colors: strong, medium, weak.
+    pub relevance: [Color; 3],
+    /// Role marker colors: user, assistant, tool.
+    pub roles: [Color; 3],
+}
+
+impl Theme {
+    pub fn standard() -> Self {
+        Self {
+            base: Style::default(),
+            muted: Style::default().fg(Color::Gray),
+            subtle: Style::default().fg(Color::DarkGray),
+            selection: Style::default().bg(Color::DarkGray).fg(Color::Yellow),
+            accent: Style::default().fg(Color::Cyan),
+            terms: [
+                Color::Yellow,
+                Color::Cyan,
+                Color::Magenta,
+                Color::Green,
+                Color::LightRed,
+            ],
+            term_modifier: Modifier::empty(),
+            relevance: [Color::Green, Color::Yellow, Color::DarkGray],
+            roles: [Color::Cyan, Color::Magenta, Color::Yellow],
+        }
+    }
+
+    /// White on black with bright accents and no gray-on-gray elements. The selection
+    /// bar is blue and leaves the foreground alone, so bright, underlined query terms
+    /// keep their colors on it.
+    pub fn high_contrast() -> Self {
+        Self {
+            base: Style::default().fg(Color::White).bg(Color::Black),
+            muted: Style::default().fg(Color::White),
+            subtle: Style::default().fg(Color::White),
+            selection: Style::default()
+                .bg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+            accent: Style::default().fg(Color::LightCyan),
+            terms: [
+                Color::LightYellow,
+                Color::LightCyan,
+                Color::LightMagenta,
+                Color::LightGreen,
+                Color::LightRed,
+            ],
+            term_modifier: Modifier::UNDERLINED,
+            relevance: [Color::LightGreen, Color::LightYellow, Color::White],
+            roles: [Color::LightCyan, Color::LightMagenta, Color::LightYellow],
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+/// One visible row of the date-grouped browse view.
+#[derive(Clone, Copy)]
+enum BrowseEntry {
+    Day {
+        date: time::Date,
+        count: usize,
+        expanded: bool,
+    },
+    /// Index into `App::results`.
+    Session(usize),
 }
 
 enum AppOutcome {
     Exit,
-    Selected(String),
+    /// The picked session and the resume template with its prompts answered.
+    Selected(Box<Selection>, String),
+}
+
+/// Answers being collected for the resume template's `{prompt:…}` placeholders.
+struct PromptState {
+    selection: Selection,
+    values: Vec<String>,
+    input: String,
+}
+
+/// Which pane receives navigation keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Results,
+    Sidebar,
+    Preview,
+}
+
+/// The wrapped preview of one result, rebuilt only when the selection, pane width or
+/// query changes.
+struct PreviewCache {
+    /// Session path and matched record; the scroll resets when they change.
+    key: (PathBuf, Option<usize>),
+    width: usize,
+    query_lower: String,
+    lines: Vec<Line<'static>>,
+    match_line: Option<usize>,
+}
+
+/// Scrollable text over the results: the raw JSON record behind the selected result's
+/// matched message (Ctrl-O), the selected session's details or the key bindings.
+struct Popup {
+    title: String,
+    text: String,
+    scroll: u16,
 }
 
 pub fn run(config: TuiConfig, stream: SessionStream) -> Result<()> {
+    // Started before the loaded files are streamed, so nothing written meanwhile is missed.
+    let watcher = if config.watch {
+        Some(discovery::watch_sessions(config.discovery.clone())?)
+    } else {
+        None
+    };
     enable_raw_mode().context("failed to enable raw mode")?;
     let mut stdout = io::stdout();
     execute!(
@@ -68,11 +241,8 @@ pub fn run(config: TuiConfig, stream: SessionStream) -> Result<()> {
     let outcome = run_app(
         &mut terminal,
         session_rx,
-        config.limit,
-        config.initial_query.clone(),
-        config.empty_status.clone(),
-        config.total_expected,
-        config.filter_cwd.clone(),
+        watcher.as_ref().map(SessionWatcher::receiver),
+        &config,
     );
 
     terminal.show_cursor()?;
@@ -85,17 +255,13 @@ pub fn run(config: TuiConfig, stream: SessionStream) -> Result<()> {
     )
     .context("failed to leave alternate screen")?;
 
-    stream.join();
-
     match outcome? {
-        AppOutcome::Exit => Ok(()),
-        AppOutcome::Selected(uuid) => {
-            if config.dry_run {
-                println!("{}", config.resume_command.replace("{uuid}", &uuid));
-                Ok(())
-            } else {
-                spawn_resume_command(&config.resume_command, &uuid)
-            }
+        AppOutcome::Exit => stream.join(),
+        AppOutcome::Selected(selection, template) => {
+            // Resume first: `--strict` load failures are reported once the resumed
+            // session ends instead of waiting for the loader and dropping the selection.
+            resume_session(&template, &selection, config.resume_in, config.dry_run)?;
+            stream.join()
         }
     }
 }
@@ -103,20 +269,11 @@ pub fn run(config: TuiConfig, stream: SessionStream) -> Result<()> {
 fn run_app(
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
     session_rx: Receiver<Session>,
-    limit: usize,
-    initial_query: String,
-    empty_status: Option<String>,
-    total_expected: usize,
-    filter_cwd: Option<PathBuf>,
+    watch_rx: Option<Receiver<Session>>,
+    config: &TuiConfig,
 ) -> Result<AppOutcome> {
-    let mut app = App::new(
-        session_rx,
-        limit,
-        initial_query,
-        empty_status,
-        total_expected,
-        filter_cwd,
-    );
+    let mut app = App::new(session_rx, config);
+    app.watch_rx = watch_rx;
     loop {
         let ingested = app.ingest_new_sessions();
         if ingested || app.query_dirty || app.results_dirty {
@@ -131,10 +288,10 @@ fn run_app(
 
         match event::read()? {
             Event::Key(key) => {
-                if key.kind == KeyEventKind::Press {
-                    if let Some(outcome) = app.on_key(key)? {
-                        return Ok(outcome);
-                    }
+                if key.kind == KeyEventKind::Press
+                    && let Some(outcome) = app.on_key(key)?
+                {
+                    return Ok(outcome);
                 }
             }
             Event::Resize(_, _) => {
@@ -147,15 +304,32 @@ fn run_app(
 
 struct App {
     sessions: Vec<Arc<Session>>,
+    /// Paths of `sessions`, to spot a file delivered twice (stream and watcher).
+    loaded_paths: HashSet<PathBuf>,
+    /// Latest worker output, already narrowed to `cwd_focus` and capped at `limit`.
     results: Vec<SearchResult>,
+    /// Matches before the cwd focus and the cap; shown as the sidebar's "All" count.
+    match_count: usize,
     query: String,
+    /// Char index of the query cursor, `0..=query.chars().count()`.
+    cursor: usize,
     limit: usize,
     selected: usize,
     table_state: TableState,
     message: Option<String>,
+    /// One-off feedback (e.g. export result) shown until the next key press.
+    notice: Option<String>,
+    export_path: PathBuf,
+    /// Opened on the first copy and kept, since on X11 the copied text is only
+    /// served while the clipboard handle is alive.
+    clipboard: Option<arboard::Clipboard>,
     empty_state_message: Option<String>,
     session_rx: Receiver<Session>,
+    /// Sessions from `--watch`: new ones are added, reloaded ones replace theirs.
+    watch_rx: Option<Receiver<Session>>,
     stream_finished: bool,
+    /// The running stream was started by `load_more` rather than at startup.
+    expanding: bool,
     total_expected: usize,
     nucleo: Nucleo<Arc<Session>>,
     injector: Injector<Arc<Session>>,
@@ -163,7 +337,51 @@ struct App {
     query_dirty: bool,
     results_dirty: bool,
     last_rebuild_at: Instant,
+    /// Drives the indexing spinner frame.
+    started_at: Instant,
+    /// When the running stream started and how many sessions were loaded before it,
+    /// for the indexing rate behind the status line's ETA.
+    stream_started_at: Instant,
+    stream_base: usize,
     filter_cwd: Option<std::path::PathBuf>,
+    search: SearchOptions,
+    glyphs: Glyphs,
+    theme: Theme,
+    browse: bool,
+    role_glyphs: bool,
+    progress_bar: bool,
+    keys: KeyMap,
+    discovery: DiscoveryOptions,
+    /// How many more files each "load more" scans; the initial scan limit.
+    scan_step: usize,
+    expanded_days: HashSet<time::Date>,
+    /// `browse_entries`, built on first use after the results or expanded days change.
+    browse_cache: std::cell::OnceCell<Vec<BrowseEntry>>,
+    sidebar: bool,
+    /// Full-session preview pane of the selected result.
+    preview: bool,
+    focus: Focus,
+    /// First preview line shown; reset to the matched message when the selection changes.
+    preview_scroll: usize,
+    preview_cache: Option<PreviewCache>,
+    /// `--low-memory`: sessions reloaded with full text for displayed results, by path,
+    /// with the file time they were loaded for; `None` when the file could not be read.
+    full_sessions: HashMap<PathBuf, (OffsetDateTime, Option<Arc<Session>>)>,
+    /// Text rows of the preview pane at the last draw, for half-page scrolling.
+    preview_height: usize,
+    /// 0 is the "All" entry; `i > 0` is `cwd_groups[i - 1]`.
+    sidebar_selected: usize,
+    /// Distinct cwds among all matches with their counts, most common first.
+    cwd_groups: Vec<(PathBuf, usize)>,
+    cwd_focus: Option<PathBuf>,
+    /// First result drawn in the (virtualized) results table.
+    scroll_offset: usize,
+    resume_command: String,
+    resume_prompts: Vec<ResumePrompt>,
+    prompt: Option<PromptState>,
+    /// Session awaiting a y/n answer before its file is deleted.
+    confirm_delete: Option<Arc<Session>>,
+    popup: Option<Popup>,
     worker_tx: Sender<ScoreJob>,
     worker_rx: Receiver<ScoreResult>,
     next_job_id: u64,
@@ -171,26 +389,18 @@ struct App {
 }
 
 impl App {
-    fn new(
-        session_rx: Receiver<Session>,
-        limit: usize,
-        initial_query: String,
-        empty_state_message: Option<String>,
-        total_expected: usize,
-        filter_cwd: Option<std::path::PathBuf>,
-    ) -> Self {
+    fn new(session_rx: Receiver<Session>, config: &TuiConfig) -> Self {
+        let empty_state_message = config.empty_status.clone();
+        let total_expected = config.total_expected;
         let (job_tx, job_rx) = unbounded::<ScoreJob>();
         let (res_tx, res_rx) = unbounded::<ScoreResult>();
-        std::thread::spawn(move || worker_loop(job_rx, res_tx));
+        let index = config.index.then(SessionIndex::default);
+        std::thread::spawn(move || worker_loop(job_rx, res_tx, index));
         let mut table_state = TableState::default();
         table_state.select(Some(0));
-        let initial_message = empty_state_message.clone().or_else(|| {
-            if total_expected > 0 {
-                Some("Indexing sessions…".into())
-            } else {
-                None
-            }
-        });
+        // With sessions expected, leave the message unset so the very first frame
+        // already shows the spinner and "Indexing 0/N" progress from `status_widget`.
+        let initial_message = empty_state_message.clone();
 
         let notify: Arc<dyn Fn() + Sync + Send> = Arc::new(|| {});
         let nucleo: Nucleo<Arc<Session>> = Nucleo::new(Config::DEFAULT, notify, None, 1);
@@ -198,23 +408,61 @@ impl App {
 
         Self {
             sessions: Vec::new(),
+            loaded_paths: HashSet::new(),
+            match_count: 0,
             results: Vec::new(),
-            query: initial_query.clone(),
-            limit,
+            query: config.initial_query.clone(),
+            cursor: config.initial_query.chars().count(),
+            limit: config.limit,
             selected: 0,
             table_state,
             message: initial_message,
+            notice: None,
+            export_path: config.export_path.clone(),
+            clipboard: None,
             empty_state_message,
             session_rx,
+            watch_rx: None,
             stream_finished: false,
+            expanding: false,
             total_expected,
             nucleo,
             injector,
-            last_query: initial_query,
+            last_query: config.initial_query.clone(),
             query_dirty: true,
             results_dirty: true,
             last_rebuild_at: Instant::now(),
-            filter_cwd,
+            started_at: Instant::now(),
+            stream_started_at: Instant::now(),
+            stream_base: 0,
+            filter_cwd: config.filter_cwd.clone(),
+            search: config.search.clone(),
+            glyphs: config.glyphs.clone(),
+            theme: config.theme.clone(),
+            browse: config.browse,
+            role_glyphs: config.role_glyphs,
+            progress_bar: config.progress_bar,
+            keys: config.keys.clone(),
+            discovery: config.discovery.clone(),
+            scan_step: config.discovery.scan_limit.max(1),
+            expanded_days: HashSet::new(),
+            browse_cache: std::cell::OnceCell::new(),
+            sidebar: false,
+            preview: false,
+            focus: Focus::Results,
+            preview_scroll: 0,
+            preview_cache: None,
+            full_sessions: HashMap::new(),
+            preview_height: 0,
+            sidebar_selected: 0,
+            cwd_groups: Vec::new(),
+            cwd_focus: None,
+            scroll_offset: 0,
+            resume_command: config.resume_command.clone(),
+            resume_prompts: resume_prompts(&config.resume_command),
+            prompt: None,
+            confirm_delete: None,
+            popup: None,
             worker_tx: job_tx,
             worker_rx: res_rx,
             next_job_id: 1,
@@ -227,11 +475,23 @@ impl App {
         while let Ok(result) = self.worker_rx.try_recv() {
             if Some(result.id) == self.pending_job {
                 self.results = result.results;
+                self.browse_cache.take();
+                self.match_count = result.match_count;
+                self.cwd_groups = result.cwd_groups;
+                self.sidebar_selected = self.sidebar_selected.min(self.cwd_groups.len());
+                self.selected = self.selected.min(self.visible_len().saturating_sub(1));
                 self.pending_job = None;
                 self.results_dirty = false;
+                self.update_message();
             }
         }
         if self.query_dirty {
+            // Typed combining marks compose with the previous char, so highlighting
+            // sees the same form as the NFC session text.
+            if self.search.unicode_normalize {
+                self.query = nfc(&self.query);
+                self.cursor = self.cursor.min(self.query.chars().count());
+            }
             let append = self.query.len() > self.last_query.len()
                 && self.query.starts_with(&self.last_query);
             self.nucleo.pattern.reparse(
@@ -250,25 +510,59 @@ impl App {
 
         if self.results_dirty || status.changed {
             let now = Instant::now();
-            if now.duration_since(self.last_rebuild_at) >= Duration::from_millis(MIN_REBUILD_INTERVAL_MS) {
+            if now.duration_since(self.last_rebuild_at)
+                >= Duration::from_millis(MIN_REBUILD_INTERVAL_MS)
+            {
                 self.schedule_rebuild()?;
                 self.last_rebuild_at = now;
             }
         }
 
-        if self.stream_finished && self.sessions.is_empty() {
-            if let Some(message) = self.empty_state_message.clone() {
-                self.message = Some(message);
-            }
-        }
-
+        self.update_message();
         Ok(())
     }
 
+    /// Only settled states get a message; while indexing (including before the
+    /// first session arrives) `status_widget` shows live progress instead.
+    fn update_message(&mut self) {
+        self.message = if !self.stream_finished {
+            None
+        } else if self.sessions.is_empty() {
+            self.empty_state_message
+                .clone()
+                .or_else(|| Some("No matches".into()))
+        } else if self.results.is_empty() && self.pending_job.is_none() {
+            let invalid = if self.search.regex && !self.query.trim().is_empty() {
+                compile_regex(&self.query).err()
+            } else {
+                None
+            };
+            // The parse error's last line names the problem; the rest draws a caret.
+            Some(invalid.map_or_else(
+                || "No matches".into(),
+                |err| {
+                    let message = err.to_string();
+                    let reason = message.lines().last().unwrap_or_default();
+                    format!("Invalid regex: {}", reason.trim_start_matches("error: "))
+                },
+            ))
+        } else {
+            None
+        };
+    }
+
     fn schedule_rebuild(&mut self) -> Result<()> {
         // Build candidate list quickly on UI thread
         let mut candidates: Vec<Arc<Session>>;
-        if self.query.is_empty() {
+        let limit = if self.browse_active() {
+            // Browsing groups every loaded session by day, so skip the result cap.
+            usize::MAX
+        } else {
+            self.limit
+        };
+        let query = normalize_query(&self.query);
+        // The sidebar counts and the cwd focus need every session, not just the newest `limit`.
+        if query.is_empty() && !self.browse_active() && !self.sidebar {
             candidates = Vec::with_capacity(self.limit.min(self.sessions.len()));
             for s in self.sessions.iter().take(self.limit) {
                 candidates.push(Arc::clone(s));
@@ -285,29 +579,101 @@ impl App {
         let id = self.next_job_id;
         self.next_job_id += 1;
         self.pending_job = Some(id);
-        let job = ScoreJob { id, query: self.query.clone(), candidates, limit: self.limit };
+        let job = ScoreJob {
+            id,
+            query,
+            candidates,
+            limit,
+            cwd_focus: self.cwd_focus.clone(),
+            options: self.search.clone(),
+        };
         // best-effort: ignore send error if worker died
         let _ = self.worker_tx.send(job);
 
-        // If nothing scheduled, keep message state
         if self.results.is_empty() {
             self.selected = 0;
             self.table_state.select(None);
-            self.message = if self.sessions.is_empty() {
-                self.empty_state_message.clone().or_else(|| {
-                    if self.stream_finished {
-                        Some("No matches".into())
-                    } else {
-                        Some("Indexing sessions…".into())
-                    }
-                })
-            } else if self.stream_finished {
-                Some("No matches".into())
+        }
+        Ok(())
+    }
+
+    /// Widens the scan window by another `scan_step` files and streams in the
+    /// sessions that are not loaded yet; the status line tracks them as they arrive.
+    fn load_more(&mut self) -> Result<Option<String>> {
+        if !self.stream_finished {
+            return Ok(Some(
+                "Still indexing; try again when indexing finishes".into(),
+            ));
+        }
+        let mut widened = self.discovery.clone();
+        widened.scan_limit = widened.scan_limit.saturating_add(self.scan_step);
+        let paths: Vec<PathBuf> = discovery::collect_session_paths(&widened)?
+            .into_iter()
+            .filter(|path| !self.loaded_paths.contains(path))
+            .collect();
+        self.discovery.scan_limit = widened.scan_limit;
+        if paths.is_empty() {
+            return Ok(Some(format!(
+                "No more sessions under {}",
+                self.discovery.roots_display()
+            )));
+        }
+        let count = paths.len();
+        // The previous stream has finished, so its loader thread is already done.
+        let stream = discovery::stream_sessions(paths, self.discovery.clone());
+        self.session_rx = stream.receiver();
+        self.total_expected = self.sessions.len() + count;
+        self.stream_finished = false;
+        self.expanding = true;
+        self.stream_started_at = Instant::now();
+        self.stream_base = self.sessions.len();
+        self.update_message();
+        Ok(None)
+    }
+
+    fn draw_sidebar(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let query_lower = normalize_query(&self.query).to_lowercase();
+        let entry = |label: String, count: usize, active: bool| {
+            let style = if active {
+                self.theme.accent.add_modifier(Modifier::BOLD)
             } else {
-                Some("Indexing sessions…".into())
+                Style::default()
             };
+            let mut spans = highlighted_spans(&label, &query_lower, style, &self.theme);
+            spans.push(Span::styled(format!(" ({count})"), self.theme.muted));
+            ListItem::new(Line::from(spans))
+        };
+        let mut items = vec![entry(
+            "All".into(),
+            self.match_count,
+            self.cwd_focus.is_none(),
+        )];
+        items.extend(self.cwd_groups.iter().map(|(path, count)| {
+            entry(
+                dir_name(path),
+                *count,
+                self.cwd_focus.as_ref() == Some(path),
+            )
+        }));
+
+        let border_style = if self.focus == Focus::Sidebar {
+            self.theme.accent
+        } else {
+            Style::default()
+        };
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(border_style)
+                    .title("Directories"),
+            )
+            .highlight_style(self.theme.selection);
+        let mut state = ListState::default();
+        if self.focus == Focus::Sidebar {
+            state.select(Some(self.sidebar_selected));
         }
-        Ok(())
+        frame.render_stateful_widget(list, area, &mut state);
     }
 
     fn ingest_new_sessions(&mut self) -> bool {
@@ -316,20 +682,13 @@ impl App {
         loop {
             match self.session_rx.try_recv() {
                 Ok(session) => {
-                    if let Some(filter) = &self.filter_cwd {
-                        if let Some(scwd) = &session.cwd {
-                            if !paths_related(&normalize_path(scwd), &normalize_path(filter)) {
-                                continue; // skip non-matching sessions
-                            }
-                        } else {
-                            continue; // no cwd info: skip when filtering is on
-                        }
+                    if !self.passes_cwd_filter(&session) {
+                        continue;
+                    }
+                    // The watcher got to this file first and counted it; so did the stream.
+                    if !self.upsert(session) {
+                        self.total_expected = self.total_expected.saturating_sub(1);
                     }
-                    let session_arc = Arc::new(session);
-                    self.injector.push(session_arc.clone(), |item, columns| {
-                        columns[0] = Utf32String::from(item.search_blob.as_str());
-                    });
-                    self.sessions.push(session_arc);
                     updated = true;
                     processed += 1;
                     if processed >= MAX_INGEST_PER_TICK {
@@ -339,18 +698,69 @@ impl App {
                 Err(TryRecvError::Empty) => break,
                 Err(TryRecvError::Disconnected) => {
                     self.stream_finished = true;
+                    self.expanding = false;
                     updated = true;
                     break;
                 }
             }
         }
+        if let Some(watch_rx) = &self.watch_rx {
+            let watched: Vec<Session> = watch_rx.try_iter().collect();
+            for session in watched {
+                if !self.passes_cwd_filter(&session) {
+                    continue;
+                }
+                if self.upsert(session) && self.total_expected > 0 {
+                    self.total_expected += 1;
+                }
+                updated = true;
+            }
+        }
         if updated {
             self.results_dirty = true;
         }
         updated
     }
 
+    /// Under `--cwd`, only sessions related to that directory are kept; sessions
+    /// without cwd info are skipped.
+    fn passes_cwd_filter(&self, session: &Session) -> bool {
+        match (&self.filter_cwd, &session.cwd) {
+            (None, _) => true,
+            (Some(filter), Some(cwd)) => {
+                paths_related(&normalize_path(cwd), &normalize_path(filter))
+            }
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Adds `session`, or replaces the loaded session with the same path. Returns
+    /// whether it was new; only new sessions are handed to nucleo.
+    fn upsert(&mut self, session: Session) -> bool {
+        if self.loaded_paths.insert(session.path.clone()) {
+            let session = self.inject(session);
+            self.sessions.push(session);
+            return true;
+        }
+        if let Some(i) = self.sessions.iter().position(|s| s.path == session.path) {
+            self.sessions[i] = Arc::new(session);
+        }
+        self.preview_cache = None;
+        false
+    }
+
+    fn inject(&self, session: Session) -> Arc<Session> {
+        let session = Arc::new(session);
+        self.injector.push(session.clone(), |item, columns| {
+            columns[0] = Utf32String::from(item.search_blob.as_str());
+        });
+        session
+    }
+
     fn draw(&mut self, frame: &mut Frame) {
+        if let Some(index) = self.selected_index() {
+            self.rehydrate(index..index + 1);
+        }
         let size = frame.size();
         let chunks = Layout::default()
             .direction(Direction::Vertical)
@@ -361,68 +771,128 @@ impl App {
             ])
             .split(size);
 
+        frame.render_widget(Block::default().style(self.theme.base), size);
         frame.render_widget(self.search_widget(), chunks[0]);
-        let header_cells = [
-            Cell::from(Span::styled(
-                "Updated",
-                Style::default()
-                    .fg(Color::Gray)
-                    .add_modifier(Modifier::BOLD),
-            )),
-            Cell::from(Span::styled(
-                "Preview",
-                Style::default()
-                    .fg(Color::Gray)
-                    .add_modifier(Modifier::BOLD),
-            )),
-        ];
+        let mut list_area = chunks[1];
+        if self.sidebar {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Length((size.width / 4).clamp(16, 32)),
+                    Constraint::Min(20),
+                ])
+                .split(chunks[1]);
+            self.draw_sidebar(frame, columns[0]);
+            list_area = columns[1];
+        }
+        if self.preview {
+            let direction = if list_area.width >= MIN_WIDTH_FOR_SIDE_PREVIEW {
+                Direction::Horizontal
+            } else {
+                Direction::Vertical
+            };
+            let panes = Layout::default()
+                .direction(direction)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(list_area);
+            list_area = panes[0];
+            self.draw_preview(frame, panes[1]);
+        }
+        let show_updated = list_area.width >= MIN_WIDTH_FOR_UPDATED_COLUMN;
+        let header_style = self.theme.muted.add_modifier(Modifier::BOLD);
+        let mut header_cells = Vec::with_capacity(2);
+        if show_updated {
+            let title = match self.search.time_basis {
+                TimeBasis::Match => "Updated",
+                TimeBasis::Created => "Created",
+                TimeBasis::Modified => "Modified",
+            };
+            header_cells.push(Cell::from(Span::styled(title, header_style)));
+        }
+        header_cells.push(Cell::from(Span::styled("Preview", header_style)));
         let header = Row::new(header_cells).bottom_margin(1);
 
         let now = OffsetDateTime::now_utc();
         // Estimate available character width for the Preview column so we can split
         // the snippet across two visible lines contiguously.
-        let table_inner_width = chunks[1].width.saturating_sub(2); // borders
-        let updated_w: u16 = 12;
+        let table_inner_width = list_area.width.saturating_sub(2); // borders
+        let updated_w: u16 = if show_updated { 12 } else { 0 };
         let preview_w = table_inner_width
             .saturating_sub(updated_w)
             .saturating_sub(3) // spacing/margins
             .max(20);
-        let mut rows: Vec<Row> = self
-            .results
+        if self.browse_active() {
+            let widths = if show_updated {
+                vec![Constraint::Length(updated_w), Constraint::Min(40)]
+            } else {
+                vec![Constraint::Min(20)]
+            };
+            self.draw_browse(frame, list_area, header, widths, show_updated, preview_w);
+            frame.render_widget(self.status_widget(), chunks[2]);
+            self.draw_popup(frame, chunks[1]);
+            return;
+        }
+
+        let best_text_score = self.results.iter().map(|r| r.text_score).max().unwrap_or(0);
+        let query_lower = normalize_query(&self.query).to_lowercase();
+        // Only build rows for the results on screen: borders and header take 4 lines,
+        // and each result is 2 lines plus a separator. `capacity` counts results that
+        // fit entirely; one more fills a partially visible last row.
+        let capacity = ((list_area.height.saturating_sub(4) as usize + 1) / 3).max(1);
+        self.scroll_offset = scroll_offset(
+            self.scroll_offset,
+            self.selected,
+            self.results.len(),
+            capacity,
+        );
+        let window_end = (self.scroll_offset + capacity + 1).min(self.results.len());
+        let window = self.scroll_offset..window_end;
+        self.rehydrate(window.clone());
+        let mut rows: Vec<Row> = self.results[window.clone()]
             .iter()
             .map(|result| {
-                let preview_text = build_preview_text(result, preview_w as usize, &self.query);
-                let updated_rel = format_relative(result.match_timestamp(), now);
-
-                Row::new(vec![
-                    Cell::from(updated_rel),
-                    Cell::from(preview_text),
-                ])
-                .height(2)
+                let preview_text = self.preview_text(result, preview_w as usize, &self.query);
+                let mut cells = Vec::with_capacity(2);
+                if show_updated {
+                    cells.push(updated_cell(
+                        result,
+                        self.search.time_basis,
+                        now,
+                        best_text_score,
+                        &query_lower,
+                        &self.theme,
+                    ));
+                }
+                cells.push(Cell::from(preview_text));
+                Row::new(cells).height(2)
             })
             .collect();
 
         // Insert horizontal separators between rows
         if !rows.is_empty() {
-            let sep1 = "─".repeat(10);
-            let sep2 = "─".repeat(preview_w as usize);
+            let sep_style = self.theme.subtle;
+            let sep1 = self.glyphs.separator.to_string().repeat(10);
+            let sep2 = self.glyphs.separator.to_string().repeat(preview_w as usize);
             let mut with_seps: Vec<Row> = Vec::with_capacity(rows.len() * 2);
             for (i, r) in rows.into_iter().enumerate() {
                 with_seps.push(r);
-                if i + 1 < self.results.len() {
-                    with_seps.push(
-                        Row::new(vec![
-                            Cell::from(Span::styled(sep1.clone(), Style::default().fg(Color::DarkGray))),
-                            Cell::from(Span::styled(sep2.clone(), Style::default().fg(Color::DarkGray))),
-                        ])
-                        .height(1),
-                    );
+                if i + 1 < window.len() {
+                    let mut cells = Vec::with_capacity(2);
+                    if show_updated {
+                        cells.push(Cell::from(Span::styled(sep1.clone(), sep_style)));
+                    }
+                    cells.push(Cell::from(Span::styled(sep2.clone(), sep_style)));
+                    with_seps.push(Row::new(cells).height(1));
                 }
             }
             rows = with_seps;
         }
 
-        let widths = [Constraint::Length(updated_w), Constraint::Min(40)];
+        let widths = if show_updated {
+            vec![Constraint::Length(updated_w), Constraint::Min(40)]
+        } else {
+            vec![Constraint::Min(20)]
+        };
         let shown = self.results.len();
         let indexed = self.sessions.len();
         let total = if self.total_expected == 0 {
@@ -433,301 +903,1361 @@ impl App {
         let table = Table::new(rows, widths)
             .header(header)
             .block(Block::default().borders(Borders::ALL).title(format!(
-                "Results: {shown} shown • {indexed}/{total} indexed"
+                "Results: {shown} shown{focus} • {indexed}/{total} indexed{bar}",
+                bar = self.progress_suffix(),
+                focus = self
+                    .cwd_focus
+                    .as_ref()
+                    .map(|cwd| format!(" in {}", cwd.display()))
+                    .unwrap_or_default()
             )))
-            .highlight_style(Style::default().bg(Color::DarkGray).fg(Color::Yellow))
+            .highlight_style(self.theme.selection)
             .highlight_symbol("▶ ");
 
-        // When separator rows are present, each result occupies 2 rows (height=2),
-        // plus a 1-row separator (except after the last). Map selection accordingly.
-        let rendered_index = self.selected * 2 + self.selected.min(self.results.len().saturating_sub(1));
-        self.table_state.select(Some(rendered_index));
-        frame.render_stateful_widget(table, chunks[1], &mut self.table_state);
+        // Each result row is followed by a separator row (except the last), so result
+        // N of the window sits at table row 2N; the window itself does the scrolling.
+        self.table_state
+            .select(Some((self.selected - self.scroll_offset) * 2));
+        *self.table_state.offset_mut() = 0;
+        frame.render_stateful_widget(table, list_area, &mut self.table_state);
         frame.render_widget(self.status_widget(), chunks[2]);
+        self.draw_popup(frame, chunks[1]);
     }
 
-    fn search_widget(&self) -> Paragraph<'static> {
-        let prompt = Span::styled(
-            "> ",
+    /// Draws every message of the selected session into `area`, scrolled to the
+    /// matched message whenever the selection changes.
+    fn draw_preview(&mut self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let border_style = if self.focus == Focus::Preview {
+            self.theme.accent
+        } else {
             Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(border_style);
+        let Some(result) = self.selected_result() else {
+            self.preview_cache = None;
+            frame.render_widget(block.title("Preview"), area);
+            return;
+        };
+        let session = Arc::clone(&result.session);
+        let matched = result.matched_message.clone();
+        let key = (
+            session.path.clone(),
+            matched.as_ref().map(|m| m.source_line),
         );
-        let query = Span::raw(self.query.clone());
-        let text = Line::from(vec![prompt, query]);
-        Paragraph::new(text).block(Block::default().borders(Borders::ALL).title("Search"))
-    }
+        let query_lower = normalize_query(&self.query).to_lowercase();
+        let width = area.width.saturating_sub(2) as usize;
 
-    fn status_widget(&self) -> Paragraph<'static> {
-        let message = if let Some(msg) = &self.message {
-            msg.clone()
-        } else {
-            let total = if self.total_expected == 0 {
-                self.sessions.len()
-            } else {
-                self.total_expected
-            };
-            let progress = if self.stream_finished || self.sessions.len() >= total {
-                format!("Indexed {}/{} sessions", self.sessions.len(), total)
-            } else {
-                format!("Indexing {}/{} sessions…", self.sessions.len(), total)
-            };
-            format!("Enter: open • Esc: quit • {}", progress)
+        let moved = self.preview_cache.as_ref().is_none_or(|c| c.key != key);
+        let stale = moved
+            || self
+                .preview_cache
+                .as_ref()
+                .is_some_and(|c| c.width != width || c.query_lower != query_lower);
+        if stale {
+            let (lines, match_line) =
+                preview_lines(&session, matched.as_ref(), width, &query_lower, &self.theme);
+            self.preview_cache = Some(PreviewCache {
+                key,
+                width,
+                query_lower,
+                lines,
+                match_line,
+            });
+        }
+        let Some(cache) = &self.preview_cache else {
+            return;
         };
-        Paragraph::new(message).style(Style::default().fg(Color::Gray))
+        if moved {
+            self.preview_scroll = cache.match_line.unwrap_or(0);
+        }
+        self.preview_scroll = self.preview_scroll.min(cache.lines.len().saturating_sub(1));
+        self.preview_height = area.height.saturating_sub(2) as usize;
+        let visible: Vec<Line<'static>> = cache.lines[self.preview_scroll..]
+            .iter()
+            .take(self.preview_height)
+            .cloned()
+            .collect();
+        let paragraph = Paragraph::new(Text::from(visible))
+            .style(self.theme.base)
+            .block(block.title(match &session.model {
+                Some(model) => format!("{} · {model}", session.label),
+                None => session.label.clone(),
+            }));
+        frame.render_widget(paragraph, area);
     }
 
-    fn on_key(&mut self, key: KeyEvent) -> Result<Option<AppOutcome>> {
-        match key.code {
-            KeyCode::Esc => return Ok(Some(AppOutcome::Exit)),
-            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                return Ok(Some(AppOutcome::Exit));
-            }
-            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.query.clear();
-                self.query_dirty = true;
-                self.results_dirty = true;
-                return Ok(None);
-            }
-            KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                truncate_last_word(&mut self.query);
-                self.query_dirty = true;
-                self.results_dirty = true;
-                return Ok(None);
-            }
-            KeyCode::Backspace => {
-                self.query.pop();
-                self.query_dirty = true;
-                self.results_dirty = true;
-                return Ok(None);
-            }
-            KeyCode::Enter => {
-                if let Some(result) = self.results.get(self.selected) {
-                    return Ok(Some(AppOutcome::Selected(result.session.uuid.clone())));
-                }
-            }
-            KeyCode::Down => {
-                if !self.results.is_empty() {
-                    self.selected = (self.selected + 1).min(self.results.len() - 1);
-                }
-            }
-            KeyCode::Up => {
-                if !self.results.is_empty() {
-                    self.selected = self.selected.saturating_sub(1);
-                }
-            }
-            KeyCode::PageDown => {
-                if !self.results.is_empty() {
-                    let jump = (self.results.len() / 5).max(5);
-                    self.selected = (self.selected + jump).min(self.results.len() - 1);
-                }
-            }
-            KeyCode::PageUp => {
-                if !self.results.is_empty() {
-                    let jump = (self.results.len() / 5).max(5);
-                    self.selected = self.selected.saturating_sub(jump);
-                }
-            }
-            KeyCode::Home => {
-                if !self.results.is_empty() {
-                    self.selected = 0;
-                }
-            }
-            KeyCode::End => {
-                if !self.results.is_empty() {
-                    self.selected = self.results.len() - 1;
-                }
+    /// In `--low-memory` mode, swaps the compacted sessions of the results at `indices`
+    /// for fully loaded ones and rescores them, so the rows and preview on screen show
+    /// complete messages. Each file is read once and cached; other results stay compact.
+    fn rehydrate(&mut self, indices: std::ops::Range<usize>) {
+        if !self.discovery.low_memory {
+            return;
+        }
+        let mut scorer: Option<Scorer> = None;
+        for index in indices {
+            let Some(result) = self.results.get(index) else {
+                break;
+            };
+            let compact = &result.session;
+            if !compact.compacted {
+                continue;
             }
-            KeyCode::Char(c) => {
-                if key.modifiers.contains(KeyModifiers::CONTROL) {
-                    match c {
-                        'c' | 'C' => return Ok(Some(AppOutcome::Exit)),
-                        'u' | 'U' => {
-                            self.query.clear();
-                            self.query_dirty = true;
-                            self.results_dirty = true;
-                            return Ok(None);
-                        }
-                        _ => return Ok(None),
+            let full = match self.full_sessions.get(&compact.path) {
+                Some((updated_at, full)) if *updated_at == compact.updated_at => full.clone(),
+                _ => {
+                    if self.full_sessions.len() >= MAX_FULL_SESSIONS {
+                        self.full_sessions.clear();
                     }
+                    let full =
+                        discovery::load_full_session(&compact.path, &self.discovery).map(Arc::new);
+                    self.full_sessions
+                        .insert(compact.path.clone(), (compact.updated_at, full.clone()));
+                    full
                 }
-                self.query.push(c);
-                self.query_dirty = true;
-                self.results_dirty = true;
+            };
+            let Some(full) = full else {
+                continue;
+            };
+            let scorer = scorer.get_or_insert_with(|| {
+                Scorer::with_options(&normalize_query(&self.query), self.search.clone())
+            });
+            if let Some(rescored) = scorer.score_session_arc(full) {
+                self.results[index] = rescored;
+                self.browse_cache.take();
             }
-            KeyCode::Tab => {}
-            _ => {}
         }
-        Ok(None)
     }
-}
-
-fn normalize_path(p: &Path) -> PathBuf {
-    std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf())
-}
-
-fn paths_related(a: &Path, b: &Path) -> bool {
-    a.starts_with(b) || b.starts_with(a)
-}
 
-fn build_preview_text(result: &SearchResult, width_chars: usize, query: &str) -> Text<'static> {
-    let width = width_chars.max(20);
-    let window = width.saturating_mul(2).max(80);
+    /// Scrolls the preview by `delta` lines; `draw_preview` clamps the far end.
+    fn scroll_preview(&mut self, delta: isize) {
+        self.preview_scroll = self.preview_scroll.saturating_add_signed(delta);
+    }
 
-    // Choose source text for the snippet
-    let source = result
-        .matched_message
-        .as_ref()
-        .map(|m| m.full_text.as_str())
-        .unwrap_or_else(|| result.session.label.as_str());
+    /// Draws the popup over `area`, if one is open.
+    fn draw_popup(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let Some(view) = &self.popup else {
+            return;
+        };
+        let popup = ratatui::layout::Rect {
+            x: area.x + area.width / 10,
+            y: area.y,
+            width: area.width - area.width / 5,
+            height: area.height,
+        };
+        let paragraph = Paragraph::new(view.text.clone())
+            .wrap(Wrap { trim: false })
+            .scroll((view.scroll, 0))
+            .style(self.theme.base)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(self.theme.accent)
+                    .title(view.title.clone()),
+            );
+        frame.render_widget(Clear, popup);
+        frame.render_widget(paragraph, popup);
+    }
 
-    let lower = source.to_lowercase();
-    let q = query.trim().to_lowercase();
-
-    // Compute match window in chars
-    let (start_char, end_char, match_start_char, match_end_char) = if !q.is_empty() {
-        if let Some(byte_idx) = lower.find(&q) {
-            let start_chars = lower[..byte_idx].chars().count();
-            let match_len = q.chars().count();
-            let end_chars = start_chars + match_len;
-            let total_chars = source.chars().count();
-            let desired_start = start_chars.saturating_sub((window.saturating_sub(match_len)) / 2);
-            let mut start = desired_start.min(total_chars.saturating_sub(window));
-            if window > total_chars { start = 0; }
-            let end = (start + window).min(total_chars);
-            (start, end, start_chars, end_chars)
-        } else {
-            (0, source.chars().count().min(window), 0, 0)
+    /// The preview cell for `result` in `width` columns. With `--role-glyphs` it is
+    /// prefixed by a marker colored by the matched message's role (nothing for
+    /// label/uuid-only matches), and the text is clipped to the width that remains.
+    fn preview_text(&self, result: &SearchResult, width: usize, query: &str) -> Text<'static> {
+        let marker = self.role_marker(result);
+        let marker_w = marker
+            .as_ref()
+            .map_or(0, |span| span.content.chars().count());
+        let mut text = build_preview_text(
+            result,
+            width.saturating_sub(marker_w),
+            query,
+            &self.glyphs.ellipsis,
+            &self.theme,
+        );
+        if let Some(marker) = marker
+            && let Some(line) = text.lines.first_mut()
+        {
+            line.spans.insert(0, marker);
         }
-    } else {
-        (0, source.chars().count().min(window), 0, 0)
-    };
-
-    // Compose the full snippet string with ellipses and normalize whitespace
-    let chars: Vec<char> = source.chars().collect();
-    let mut full = String::new();
-    if start_char > 0 { full.push('…'); }
-    let match_start = match_start_char.max(start_char).min(end_char);
-    let match_end = match_end_char.max(start_char).min(end_char);
-    let mut hl_start_rel = None::<usize>;
-    let mut hl_end_rel = None::<usize>;
+        text
+    }
 
-    if start_char < match_start {
-        full.extend(chars[start_char..match_start].iter());
+    fn role_marker(&self, result: &SearchResult) -> Option<Span<'static>> {
+        if !self.role_glyphs {
+            return None;
+        }
+        let index = match result.matched_message.as_ref()?.role {
+            MessageRole::User => 0,
+            MessageRole::Assistant => 1,
+            MessageRole::Tool => 2,
+        };
+        Some(Span::styled(
+            self.glyphs.roles[index],
+            Style::default().fg(self.theme.roles[index]),
+        ))
     }
-    if match_start < match_end {
-        hl_start_rel = Some(full.chars().count());
-        full.extend(chars[match_start..match_end].iter());
-        hl_end_rel = Some(full.chars().count());
+
+    fn browse_active(&self) -> bool {
+        self.browse && self.query.trim().is_empty()
     }
-    if match_end < end_char {
-        full.extend(chars[match_end..end_char].iter());
+
+    /// Groups the (recency-ordered) results by local day; sessions are only listed
+    /// under days the user expanded.
+    fn browse_entries(&self) -> &[BrowseEntry] {
+        self.browse_cache.get_or_init(|| self.group_by_day())
     }
-    if end_char < chars.len() { full.push('…'); }
-    full = normalize_ws(&full);
 
-    // Split into two visual lines and apply highlight spans across the split
-    let (l1_str, l2_str) = split_visual_two_lines(&full, width);
-
-    // Helper to build a line with highlight between [hstart, hend)
-    let build_line = |text: &str, base_offset: usize| -> Line<'static> {
-        if let (Some(hs), Some(he)) = (hl_start_rel, hl_end_rel) {
-            let hs_rel = hs.saturating_sub(base_offset);
-            let he_rel = he.saturating_sub(base_offset);
-            let total = text.chars().count();
-            let left_end = hs_rel.min(total);
-            let right_start = he_rel.min(total);
-            let mut segments: Vec<Span> = Vec::new();
-            let mut idx = 0usize;
-            for (part_start, part_end, style) in [
-                (0usize, left_end, None),
-                (left_end, right_start, Some(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
-                (right_start, total, None),
-            ] {
-                if part_end > part_start {
-                    let slice: String = text.chars().skip(part_start).take(part_end - part_start).collect();
-                    segments.push(match style {
-                        Some(st) => Span::styled(slice, st),
-                        None => Span::raw(slice),
-                    });
-                }
-                idx = part_end;
+    fn group_by_day(&self) -> Vec<BrowseEntry> {
+        let mut entries = Vec::new();
+        let mut idx = 0;
+        while idx < self.results.len() {
+            let date = local_date(self.results[idx].timestamp(self.search.time_basis));
+            let start = idx;
+            while idx < self.results.len()
+                && local_date(self.results[idx].timestamp(self.search.time_basis)) == date
+            {
+                idx += 1;
             }
-            Line::from(segments)
+            let expanded = self.expanded_days.contains(&date);
+            entries.push(BrowseEntry::Day {
+                date,
+                count: idx - start,
+                expanded,
+            });
+            if expanded {
+                entries.extend((start..idx).map(BrowseEntry::Session));
+            }
+        }
+        entries
+    }
+
+    fn visible_len(&self) -> usize {
+        if self.browse_active() {
+            self.browse_entries().len()
         } else {
-            Line::from(Span::raw(text.to_owned()))
+            self.results.len()
         }
-    };
+    }
 
-    let line1 = build_line(&l1_str, 0);
-    let line2 = build_line(&l2_str, l1_str.chars().count());
-    Text::from(vec![line1, line2])
-}
+    fn draw_browse(
+        &mut self,
+        frame: &mut Frame,
+        area: ratatui::layout::Rect,
+        header: Row<'static>,
+        widths: Vec<Constraint>,
+        show_updated: bool,
+        preview_w: u16,
+    ) {
+        let now = OffsetDateTime::now_utc();
+        let entries = self.browse_entries();
+        let day_style = self.theme.accent.add_modifier(Modifier::BOLD);
+        let rows: Vec<Row> = entries
+            .iter()
+            .map(|entry| match entry {
+                BrowseEntry::Day {
+                    date,
+                    count,
+                    expanded,
+                } => {
+                    let marker = if *expanded { "▾" } else { "▸" };
+                    let noun = if *count == 1 { "session" } else { "sessions" };
+                    let label = format!("{marker} {} ({count} {noun})", format_day(*date));
+                    let mut cells = Vec::with_capacity(2);
+                    if show_updated {
+                        cells.push(Cell::from(""));
+                    }
+                    cells.push(Cell::from(Span::styled(label, day_style)));
+                    Row::new(cells).height(1)
+                }
+                BrowseEntry::Session(i) => {
+                    let result = &self.results[*i];
+                    let preview_text = self.preview_text(result, preview_w as usize, "");
+                    let mut cells = Vec::with_capacity(2);
+                    if show_updated {
+                        cells.push(updated_cell(
+                            result,
+                            self.search.time_basis,
+                            now,
+                            0,
+                            "",
+                            &self.theme,
+                        ));
+                    }
+                    cells.push(Cell::from(preview_text));
+                    Row::new(cells).height(2)
+                }
+            })
+            .collect();
 
-fn split_visual_two_lines(s: &str, width: usize) -> (String, String) {
-    let total = s.chars().count();
-    if total <= width {
-        // Pad into two lines by taking up to width and the remainder empty
-        return (s.to_owned(), String::new());
-    }
-    // Prefer breaking at whitespace near the width
-    let mut count = 0usize;
-    let mut split_idx = 0usize; // byte index
-    let mut last_space_byte: Option<usize> = None;
-    for (byte_idx, ch) in s.char_indices() {
-        if ch.is_whitespace() { last_space_byte = Some(byte_idx); }
-        if count >= width { break; }
-        split_idx = byte_idx + ch.len_utf8();
-        count += 1;
+        let days = entries
+            .iter()
+            .filter(|e| matches!(e, BrowseEntry::Day { .. }))
+            .count();
+        let sessions = self.results.len();
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "Browse: {sessions} sessions over {days} days{}",
+                self.progress_suffix()
+            )))
+            .highlight_style(self.theme.selection)
+            .highlight_symbol("▶ ");
+
+        self.selected = self.selected.min(entries.len().saturating_sub(1));
+        self.table_state.select(Some(self.selected));
+        frame.render_stateful_widget(table, area, &mut self.table_state);
     }
-    if let Some(space) = last_space_byte { split_idx = space + 1; }
-    let (l1, l2) = s.split_at(split_idx);
-    (l1.to_owned(), l2.to_owned())
-}
 
-fn normalize_ws(s: &str) -> String {
-    let mut out = String::with_capacity(s.len());
-    let mut last = false;
-    for ch in s.chars() {
-        if ch.is_whitespace() {
-            if !last { out.push(' '); last = true; }
+    /// Expands or collapses the day under the cursor. Collapsing from a session row
+    /// moves the cursor back to its day header.
+    fn set_day_expanded(&mut self, expand: Option<bool>) {
+        let entries = self.browse_entries();
+        let Some(entry) = entries.get(self.selected) else {
+            return;
+        };
+        let (date, header_idx) = match entry {
+            BrowseEntry::Day { date, .. } => (*date, self.selected),
+            BrowseEntry::Session(i) => {
+                let date = local_date(self.results[*i].timestamp(self.search.time_basis));
+                let header_idx = entries[..self.selected]
+                    .iter()
+                    .rposition(|e| matches!(e, BrowseEntry::Day { .. }))
+                    .unwrap_or(0);
+                (date, header_idx)
+            }
+        };
+        let expanded = self.expanded_days.contains(&date);
+        let target = expand.unwrap_or(!expanded);
+        self.browse_cache.take();
+        if target {
+            self.expanded_days.insert(date);
         } else {
-            out.push(ch);
-            last = false;
+            self.expanded_days.remove(&date);
+            self.selected = header_idx;
         }
     }
-    out.trim().to_string()
-}
 
-fn split_by_chars(s: &str, n: usize) -> (String, String) {
-    if n == 0 { return (String::new(), s.to_owned()); }
-    let mut left = String::new();
-    let mut count = 0usize;
-    let mut last_space_idx: Option<usize> = None; // byte index
-    for (byte_idx, ch) in s.char_indices() {
-        if ch.is_whitespace() { last_space_idx = Some(byte_idx); }
-        if count >= n { break; }
-        left.push(ch);
-        count += 1;
+    fn search_widget(&self) -> Paragraph<'static> {
+        let prompt = Span::styled("> ", self.theme.accent.add_modifier(Modifier::BOLD));
+        // The char under the cursor (a space at the end) is drawn reversed as the caret.
+        let (before, after) = self.query_halves();
+        let mut rest = after.chars();
+        let caret = rest.next().unwrap_or(' ').to_string();
+        let text = Line::from(vec![
+            prompt,
+            Span::raw(before.to_string()),
+            Span::styled(caret, Style::default().add_modifier(Modifier::REVERSED)),
+            Span::raw(rest.as_str().to_string()),
+        ]);
+        Paragraph::new(text).block(Block::default().borders(Borders::ALL).title("Search"))
     }
-    if count < n || count == s.chars().count() {
-        return (s.to_owned(), String::new());
+
+    /// Time left to load the rest of `total_expected` at the rate the running stream
+    /// has delivered sessions over `elapsed`; `None` until the first one arrives.
+    fn indexing_eta(&self, elapsed: Duration) -> Option<time::Duration> {
+        let done = self.sessions.len().saturating_sub(self.stream_base);
+        let remaining = self.total_expected.saturating_sub(self.sessions.len());
+        if done == 0 || remaining == 0 || elapsed.is_zero() {
+            return None;
+        }
+        let secs = remaining as f64 * elapsed.as_secs_f64() / done as f64;
+        Some(time::Duration::seconds(secs.ceil() as i64))
     }
-    // Prefer breaking at last whitespace before limit when available.
-    let split_byte = if let Some(space_idx) = last_space_idx { space_idx + 1 } else { left.len() };
-    let (l, r) = s.split_at(split_byte);
-    (l.to_owned(), r.to_owned())
-}
 
-fn style_span(text: String, highlighted: bool) -> Span<'static> {
-    if highlighted {
-        Span::styled(
-            text,
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
-        )
+    /// The indexing progress part of the status line, with the spinner frame for
+    /// `since_start` (time since the TUI started) and the ETA for `since_stream`.
+    fn progress_text(&self, since_start: Duration, since_stream: Duration) -> String {
+        let total = if self.total_expected == 0 {
+            self.sessions.len()
+        } else {
+            self.total_expected
+        };
+        if self.stream_finished && total >= self.discovery.scan_limit {
+            format!(
+                "Indexed {}/{} sessions — scan limit reached, {}: load more",
+                self.sessions.len(),
+                total,
+                self.key_hint(Action::LoadMore)
+            )
+        } else if self.stream_finished || self.sessions.len() >= total {
+            format!("Indexed {}/{} sessions", self.sessions.len(), total)
+        } else {
+            let spinner = &self.glyphs.spinner;
+            let frame = since_start.as_millis() / SPINNER_FRAME_MS;
+            let eta = self
+                .indexing_eta(since_stream)
+                .map(|eta| format!(" ~{} left", format_duration(eta)))
+                .unwrap_or_default();
+            format!(
+                "{} {} {}/{} sessions{}{}",
+                spinner[frame as usize % spinner.len()],
+                if self.expanding {
+                    "Expanding scan:"
+                } else {
+                    "Indexing"
+                },
+                self.sessions.len(),
+                total,
+                self.glyphs.ellipsis,
+                eta
+            )
+        }
+    }
+
+    fn status_widget(&self) -> Paragraph<'static> {
+        Paragraph::new(self.status_text()).style(self.theme.muted)
+    }
+
+    fn status_text(&self) -> String {
+        if let Some(state) = &self.prompt {
+            let prompt = &self.resume_prompts[state.values.len()];
+            let default = if prompt.default.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", prompt.default)
+            };
+            format!(
+                "{}{}: {}▏ • Enter: accept • Esc: use default • Ctrl-C: cancel",
+                prompt.label, default, state.input
+            )
+        } else if let Some(session) = &self.confirm_delete {
+            format!(
+                "Delete {} ({})? y: delete • any other key: cancel",
+                session.title(),
+                session.path.display()
+            )
+        } else if let Some(notice) = &self.notice {
+            notice.clone()
+        } else if self.popup.is_some() {
+            format!(
+                "↑/↓/PgUp/PgDn: scroll • {}: close",
+                self.key_hint(Action::Quit)
+            )
+        } else if self.focus == Focus::Preview {
+            format!(
+                "j/k: scroll • Ctrl-D/Ctrl-U: half page • {}/{}: back to results",
+                self.key_hint(Action::SwitchFocus),
+                self.key_hint(Action::Quit)
+            )
+        } else if self.focus == Focus::Sidebar {
+            format!(
+                "↑/↓: pick directory • {}: filter • {}/{}: back to results",
+                self.key_hint(Action::Open),
+                self.key_hint(Action::SwitchFocus),
+                self.key_hint(Action::Quit)
+            )
+        } else if let Some(msg) = &self.message {
+            msg.clone()
+        } else {
+            let progress =
+                self.progress_text(self.started_at.elapsed(), self.stream_started_at.elapsed());
+            let mut parts = vec![if self.browse_active() {
+                format!(
+                    "{}/{}: expand • {}: collapse • {}: quit",
+                    self.key_hint(Action::Open),
+                    self.key_hint(Action::Expand),
+                    self.key_hint(Action::Collapse),
+                    self.key_hint(Action::Quit)
+                )
+            } else {
+                format!(
+                    "{}: open • {}: quit",
+                    self.key_hint(Action::Open),
+                    self.key_hint(Action::Quit)
+                )
+            }];
+            if let Some(focus) = &self.cwd_focus {
+                parts.push(format!("in {}", dir_name(focus)));
+            }
+            match self.search.role {
+                RoleFilter::Any => {}
+                RoleFilter::User => parts.push("your messages only".into()),
+                RoleFilter::Assistant => parts.push("Codex messages only".into()),
+                RoleFilter::Tool => parts.push("tool calls only".into()),
+            }
+            if self.search.first_message {
+                parts.push("first messages only".into());
+            }
+            let hints = self.footer_hints();
+            let tick = self.started_at.elapsed().as_secs() / FOOTER_HINT_SECS;
+            parts.push(hints[tick as usize % hints.len()].clone());
+            parts.push(progress);
+            parts.join(" • ")
+        }
+    }
+
+    /// Secondary keys for the current mode, shown one at a time after the open/quit
+    /// hint; toggles show their state. Never empty.
+    fn footer_hints(&self) -> Vec<String> {
+        let mut hints = Vec::new();
+        // An empty query is always in recency order, so the sort hint needs a query.
+        if self.query.is_empty() {
+            hints.push("type to search".to_string());
+        } else {
+            hints.push(format!(
+                "{}: clear • {}: delete word",
+                self.key_hint(Action::ClearQuery),
+                self.key_hint(Action::DeleteWord)
+            ));
+            let sort = match self.search.sort {
+                SortMode::Recent => "recency",
+                SortMode::Score => "relevance",
+            };
+            hints.push(format!(
+                "sorted by {sort} • {}: toggle",
+                self.key_hint(Action::ToggleSort)
+            ));
+        }
+        if !self.browse_active() {
+            hints.push(format!(
+                "{}: related sessions",
+                self.key_hint(Action::Related)
+            ));
+        }
+        if self
+            .selected_result()
+            .is_some_and(|r| r.matched_message.is_some())
+        {
+            hints.push(format!("{}: raw record", self.key_hint(Action::RawRecord)));
+        }
+        hints.push(if self.sidebar {
+            format!(
+                "{}: hide directories • {}: focus them",
+                self.key_hint(Action::ToggleSidebar),
+                self.key_hint(Action::SwitchFocus)
+            )
+        } else {
+            format!("{}: directories", self.key_hint(Action::ToggleSidebar))
+        });
+        hints.push(if self.preview {
+            format!(
+                "{}: hide preview • {}: focus it",
+                self.key_hint(Action::TogglePreview),
+                self.key_hint(Action::SwitchFocus)
+            )
+        } else {
+            format!("{}: preview", self.key_hint(Action::TogglePreview))
+        });
+        hints.push(format!("{}: export results", self.key_hint(Action::Export)));
+        hints.push(format!("{}: details", self.key_hint(Action::ToggleDetail)));
+        hints.push(format!("{}: help", self.key_hint(Action::Help)));
+        hints
+    }
+
+    /// ` ████░░░░` for `--progress-bar` while indexing; empty otherwise.
+    fn progress_suffix(&self) -> String {
+        if !self.progress_bar || self.stream_finished {
+            return String::new();
+        }
+        let total = self.total_expected.max(self.sessions.len()).max(1);
+        format!(
+            " {}",
+            progress_bar(
+                self.sessions.len(),
+                total,
+                PROGRESS_BAR_WIDTH,
+                self.glyphs.bar
+            )
+        )
+    }
+
+    /// The key bound to `action`, as shown in status hints.
+    fn key_hint(&self, action: Action) -> String {
+        self.keys
+            .key_for(action)
+            .unwrap_or_else(|| "unbound".to_string())
+    }
+
+    /// Index into `results` of the selected row; `None` on a browse day header.
+    fn selected_index(&self) -> Option<usize> {
+        if self.browse_active() {
+            match self.browse_entries().get(self.selected).copied()? {
+                BrowseEntry::Session(i) => Some(i),
+                BrowseEntry::Day { .. } => None,
+            }
+        } else {
+            Some(self.selected)
+        }
+    }
+
+    fn selected_result(&self) -> Option<&SearchResult> {
+        self.results.get(self.selected_index()?)
+    }
+
+    fn selected_session(&self) -> Option<Arc<Session>> {
+        self.selected_result().map(|r| Arc::clone(&r.session))
+    }
+
+    /// Opens the raw JSON record the selected result's matched message came from.
+    /// Returns a notice when there is nothing to show.
+    fn open_raw_view(&mut self) -> Option<String> {
+        let Some(result) = self.selected_result() else {
+            return Some("Select a result to inspect".into());
+        };
+        let Some(message) = &result.matched_message else {
+            return Some("The selected result did not match a message".into());
+        };
+        let path = &result.session.path;
+        let line = message.source_line;
+        match discovery::raw_record(path, line) {
+            Ok(text) => {
+                self.popup = Some(Popup {
+                    title: format!("{}:{}", path.display(), line + 1),
+                    text,
+                    scroll: 0,
+                });
+                None
+            }
+            Err(err) => Some(format!("Could not read the raw record: {err:#}")),
+        }
+    }
+
+    fn on_popup_key(&mut self, key: KeyEvent) {
+        let Some(view) = self.popup.as_mut() else {
+            return;
+        };
+        match self.keys.action(&key) {
+            Some(Action::Up) => view.scroll = view.scroll.saturating_sub(1),
+            Some(Action::Down) => view.scroll = view.scroll.saturating_add(1),
+            Some(Action::PageUp) => view.scroll = view.scroll.saturating_sub(POPUP_PAGE),
+            Some(Action::PageDown) => view.scroll = view.scroll.saturating_add(POPUP_PAGE),
+            Some(Action::Quit | Action::RawRecord | Action::ToggleDetail | Action::Help) => {
+                self.popup = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Opens a popup with the selected session's file, cwd, model, times and message
+    /// count. Returns a notice when nothing is selected.
+    fn open_detail(&mut self) -> Option<String> {
+        let Some(session) = self.selected_session() else {
+            return Some("Select a session to show its details".into());
+        };
+        let now = OffsetDateTime::now_utc();
+        let unknown = || "unknown".to_string();
+        let fields = [
+            ("Title", session.title().to_string()),
+            ("UUID", session.uuid.clone()),
+            ("File", session.path.display().to_string()),
+            (
+                "Cwd",
+                session
+                    .cwd
+                    .as_ref()
+                    .map_or_else(unknown, |cwd| cwd.display().to_string()),
+            ),
+            ("Model", session.model.clone().unwrap_or_else(unknown)),
+            (
+                "Created",
+                session
+                    .created_at
+                    .map_or_else(unknown, |at| format_relative(at, now)),
+            ),
+            ("Updated", format_relative(session.updated_at, now)),
+            ("Messages", session.messages.len().to_string()),
+        ];
+        let text = fields
+            .iter()
+            .map(|(name, value)| format!("{name:<9} {value}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.popup = Some(Popup {
+            title: session.label.clone(),
+            text,
+            scroll: 0,
+        });
+        None
+    }
+
+    /// Opens a popup listing every action with its keys.
+    fn open_help(&mut self) {
+        let bindings = self.keys.describe();
+        let width = bindings
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        let text = bindings
+            .iter()
+            .map(|(name, keys)| format!("{name:<width$}  {keys}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.popup = Some(Popup {
+            title: "Keys".into(),
+            text,
+            scroll: 0,
+        });
+    }
+
+    /// Replaces the query with the selected session's most salient terms.
+    fn search_related(&mut self) -> String {
+        let Some(session) = self.selected_session() else {
+            return "Select a session to find related ones".into();
+        };
+        let terms = salient_terms(&session, RELATED_TERM_COUNT);
+        if terms.is_empty() {
+            return format!("No distinctive terms in {}", session.label);
+        }
+        self.query = terms.join(" ");
+        self.cursor = self.query.chars().count();
+        self.query_dirty = true;
+        self.results_dirty = true;
+        self.selected = 0;
+        format!("Related to {}: {}", session.label, self.query)
+    }
+
+    /// Writes the current results in the same shape as `--json` output.
+    fn export_results(&self) -> Result<()> {
+        let file = std::fs::File::create(&self.export_path)
+            .with_context(|| format!("creating {}", self.export_path.display()))?;
+        let mut writer = io::BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, &self.results)
+            .context("failed to serialize results")?;
+        writeln!(writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Copies `what` of the selected session to the system clipboard and describes
+    /// the outcome for the status line.
+    fn copy_selected(&mut self, what: Action) -> String {
+        let Some((label, text)) = self.copy_target(what) else {
+            return "Nothing selected to copy".into();
+        };
+        match self.set_clipboard(text) {
+            Ok(()) => format!("Copied {label}"),
+            Err(err) => format!("Copy failed: {err:#}"),
+        }
+    }
+
+    /// What `what` copies for the selected session: a name for the status line and
+    /// the text itself.
+    fn copy_target(&self, what: Action) -> Option<(&'static str, String)> {
+        let session = self.selected_session()?;
+        Some(match what {
+            Action::CopyPath => ("path", session.path.display().to_string()),
+            _ => ("UUID", session.uuid.clone()),
+        })
+    }
+
+    fn set_clipboard(&mut self, text: String) -> Result<()> {
+        let clipboard = match self.clipboard.take() {
+            Some(clipboard) => clipboard,
+            None => arboard::Clipboard::new().context("clipboard unavailable")?,
+        };
+        self.clipboard
+            .insert(clipboard)
+            .set_text(text)
+            .context("writing to the clipboard")
+    }
+
+    /// Removes `session`'s file and drops it from the loaded sessions and results.
+    fn delete_session(&mut self, session: &Session) -> String {
+        if let Err(err) = std::fs::remove_file(&session.path) {
+            return format!("Delete failed: {err}");
+        }
+        self.sessions.retain(|s| s.path != session.path);
+        self.loaded_paths.remove(&session.path);
+        self.results.retain(|r| r.session.path != session.path);
+        self.browse_cache.take();
+        self.match_count = self.match_count.saturating_sub(1);
+        self.total_expected = self.total_expected.saturating_sub(1);
+        self.selected = self.selected.min(self.results.len().saturating_sub(1));
+        self.preview_cache = None;
+        self.results_dirty = true;
+        format!("Deleted {}", session.path.display())
+    }
+
+    /// Resumes `result`'s session right away, or first asks for the template's
+    /// prompt values.
+    fn select(&mut self, result: &SearchResult) -> Option<AppOutcome> {
+        let selection = Selection::from_result(result);
+        if self.resume_prompts.is_empty() {
+            return Some(AppOutcome::Selected(
+                Box::new(selection),
+                self.resume_command.clone(),
+            ));
+        }
+        self.prompt = Some(PromptState {
+            selection,
+            values: Vec::new(),
+            input: String::new(),
+        });
+        None
+    }
+
+    fn on_prompt_key(&mut self, key: KeyEvent) -> Option<AppOutcome> {
+        let state = self.prompt.as_mut()?;
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.prompt = None;
+                self.notice = Some("Resume cancelled".into());
+                return None;
+            }
+            // Esc skips the prompt, like an empty answer: the default is used.
+            KeyCode::Enter | KeyCode::Esc => {
+                let answer = std::mem::take(&mut state.input);
+                state.values.push(if key.code == KeyCode::Esc {
+                    String::new()
+                } else {
+                    answer
+                });
+            }
+            KeyCode::Backspace => {
+                state.input.pop();
+            }
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                state.input.clear();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                state.input.push(c);
+            }
+            _ => {}
+        }
+        if state.values.len() < self.resume_prompts.len() {
+            return None;
+        }
+        let state = self.prompt.take()?;
+        let template =
+            fill_resume_prompts(&self.resume_command, &self.resume_prompts, &state.values);
+        Some(AppOutcome::Selected(Box::new(state.selection), template))
+    }
+
+    fn on_key(&mut self, key: KeyEvent) -> Result<Option<AppOutcome>> {
+        self.notice = None;
+        if self.prompt.is_some() {
+            return Ok(self.on_prompt_key(key));
+        }
+        // Ctrl-C always quits, whatever the bindings say or the popup asks.
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return Ok(Some(AppOutcome::Exit));
+        }
+        if let Some(session) = self.confirm_delete.take() {
+            self.notice = Some(
+                if key.code == KeyCode::Char('y') && key.modifiers.is_empty() {
+                    self.delete_session(&session)
+                } else {
+                    "Delete cancelled".into()
+                },
+            );
+            return Ok(None);
+        }
+        if self.popup.is_some() {
+            self.on_popup_key(key);
+            return Ok(None);
+        }
+        if self.focus == Focus::Preview && self.on_preview_key(key) {
+            return Ok(None);
+        }
+        if let Some(action) = self.keys.action(&key) {
+            return Ok(self.on_action(action));
+        }
+        match key.code {
+            KeyCode::Backspace => self.edit_query(|before| {
+                before.pop();
+            }),
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.edit_query(|before| before.push(c));
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    /// The query before and after the cursor.
+    fn query_halves(&self) -> (&str, &str) {
+        let at = self
+            .query
+            .char_indices()
+            .nth(self.cursor)
+            .map_or(self.query.len(), |(byte, _)| byte);
+        self.query.split_at(at)
+    }
+
+    /// Applies `edit` to the query text before the cursor, keeping the cursor at the
+    /// end of the edited part.
+    fn edit_query(&mut self, edit: impl FnOnce(&mut String)) {
+        let (before, after) = self.query_halves();
+        let (mut before, after) = (before.to_string(), after.to_string());
+        edit(&mut before);
+        self.cursor = before.chars().count();
+        self.query = before + &after;
+        self.query_dirty = true;
+        self.results_dirty = true;
+    }
+
+    /// Scroll keys while the preview pane is focused: j/k and the up/down bindings by
+    /// a line, Ctrl-D/Ctrl-U and the page bindings by half a pane. Returns whether
+    /// `key` was handled.
+    fn on_preview_key(&mut self, key: KeyEvent) -> bool {
+        let half = (self.preview_height / 2).max(1) as isize;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('j') if !ctrl => self.scroll_preview(1),
+            KeyCode::Char('k') if !ctrl => self.scroll_preview(-1),
+            KeyCode::Char('d') if ctrl => self.scroll_preview(half),
+            KeyCode::Char('u') if ctrl => self.scroll_preview(-half),
+            _ => match self.keys.action(&key) {
+                Some(Action::Down) => self.scroll_preview(1),
+                Some(Action::Up) => self.scroll_preview(-1),
+                Some(Action::PageDown) => self.scroll_preview(half),
+                Some(Action::PageUp) => self.scroll_preview(-half),
+                Some(Action::First) => self.preview_scroll = 0,
+                Some(Action::Last) => self.preview_scroll = usize::MAX,
+                _ => return false,
+            },
+        }
+        true
+    }
+
+    /// The next visible pane after the focused one: results, sidebar, preview.
+    fn next_focus(&self) -> Focus {
+        let order = [
+            (Focus::Results, true),
+            (Focus::Sidebar, self.sidebar),
+            (Focus::Preview, self.preview),
+        ];
+        let current = order
+            .iter()
+            .position(|(focus, _)| *focus == self.focus)
+            .unwrap_or(0);
+        (1..=order.len())
+            .map(|step| order[(current + step) % order.len()])
+            .find(|(_, visible)| *visible)
+            .map_or(Focus::Results, |(focus, _)| focus)
+    }
+
+    fn on_action(&mut self, action: Action) -> Option<AppOutcome> {
+        match action {
+            Action::Quit if self.focus != Focus::Results => self.focus = Focus::Results,
+            Action::Quit => return Some(AppOutcome::Exit),
+            Action::ToggleSidebar => {
+                self.sidebar = !self.sidebar;
+                self.focus = if self.sidebar {
+                    Focus::Sidebar
+                } else {
+                    Focus::Results
+                };
+                if !self.sidebar && self.cwd_focus.take().is_some() {
+                    self.selected = 0;
+                }
+                self.results_dirty = true;
+            }
+            Action::TogglePreview => {
+                self.preview = !self.preview;
+                self.preview_cache = None;
+                if self.focus == Focus::Preview {
+                    self.focus = Focus::Results;
+                }
+            }
+            Action::SwitchFocus => self.focus = self.next_focus(),
+            Action::ToggleSort => {
+                self.search.sort = match self.search.sort {
+                    SortMode::Recent => SortMode::Score,
+                    SortMode::Score => SortMode::Recent,
+                };
+                self.notice = Some(match self.search.sort {
+                    SortMode::Recent => "Sorted by recency".into(),
+                    SortMode::Score => "Sorted by relevance".into(),
+                });
+                self.selected = 0;
+                self.results_dirty = true;
+            }
+            Action::Up if self.focus == Focus::Sidebar => {
+                self.sidebar_selected = self.sidebar_selected.saturating_sub(1);
+            }
+            Action::Down if self.focus == Focus::Sidebar => {
+                self.sidebar_selected = (self.sidebar_selected + 1).min(self.cwd_groups.len());
+            }
+            Action::Open if self.focus == Focus::Sidebar => {
+                self.cwd_focus = self
+                    .sidebar_selected
+                    .checked_sub(1)
+                    .and_then(|i| self.cwd_groups.get(i))
+                    .map(|(path, _)| path.clone());
+                self.focus = Focus::Results;
+                self.selected = 0;
+                self.results_dirty = true;
+            }
+            Action::ClearQuery => self.edit_query(String::clear),
+            Action::DeleteWord => self.edit_query(truncate_last_word),
+            Action::Export => {
+                self.notice = Some(match self.export_results() {
+                    Ok(()) => format!(
+                        "Exported {} results to {}",
+                        self.results.len(),
+                        self.export_path.display()
+                    ),
+                    Err(err) => format!("Export failed: {err:#}"),
+                });
+            }
+            Action::LoadMore => {
+                self.notice = match self.load_more() {
+                    Ok(message) => message,
+                    Err(err) => Some(format!("Load more failed: {err:#}")),
+                };
+            }
+            Action::Related => self.notice = Some(self.search_related()),
+            Action::CopyUuid | Action::CopyPath => self.notice = Some(self.copy_selected(action)),
+            Action::DeleteSession => match self.selected_session() {
+                Some(session) => self.confirm_delete = Some(session),
+                None => self.notice = Some("Select a session to delete".into()),
+            },
+            Action::RawRecord => self.notice = self.open_raw_view(),
+            Action::ToggleDetail => self.notice = self.open_detail(),
+            Action::Help => self.open_help(),
+            Action::Open if self.browse_active() => {
+                let entry = self.browse_entries().get(self.selected).copied();
+                match entry {
+                    Some(BrowseEntry::Session(i)) => {
+                        let result = self.results[i].clone();
+                        return self.select(&result);
+                    }
+                    Some(BrowseEntry::Day { .. }) => self.set_day_expanded(None),
+                    None => {}
+                }
+            }
+            Action::Expand if self.browse_active() => self.set_day_expanded(Some(true)),
+            Action::Collapse if self.browse_active() => self.set_day_expanded(Some(false)),
+            Action::Expand => self.cursor = (self.cursor + 1).min(self.query.chars().count()),
+            Action::Collapse => self.cursor = self.cursor.saturating_sub(1),
+            // Home/End move the cursor first; at that end already, they jump the results.
+            Action::First if self.cursor > 0 => self.cursor = 0,
+            Action::Last if self.cursor < self.query.chars().count() => {
+                self.cursor = self.query.chars().count();
+            }
+            Action::Open => {
+                if let Some(result) = self.results.get(self.selected).cloned() {
+                    return self.select(&result);
+                }
+            }
+            Action::Down if self.visible_len() > 0 => {
+                self.selected = (self.selected + 1).min(self.visible_len() - 1);
+            }
+            Action::Up if self.visible_len() > 0 => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            Action::PageDown if self.visible_len() > 0 => {
+                let len = self.visible_len();
+                let jump = (len / 5).max(5);
+                self.selected = (self.selected + jump).min(len - 1);
+            }
+            Action::PageUp if self.visible_len() > 0 => {
+                let jump = (self.visible_len() / 5).max(5);
+                self.selected = self.selected.saturating_sub(jump);
+            }
+            Action::First if self.visible_len() > 0 => {
+                self.selected = 0;
+            }
+            Action::Last if self.visible_len() > 0 => {
+                self.selected = self.visible_len() - 1;
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Last component of a cwd, as listed in the sidebar.
+fn dir_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// `width` cells, the first `done / total` of them filled.
+fn progress_bar(done: usize, total: usize, width: usize, cells: [char; 2]) -> String {
+    let filled = (done.min(total) * width) / total.max(1);
+    let mut bar = String::with_capacity(width * cells[0].len_utf8());
+    bar.extend(std::iter::repeat_n(cells[0], filled));
+    bar.extend(std::iter::repeat_n(cells[1], width - filled));
+    bar
+}
+
+/// Keeps `selected` inside a window of `capacity` results starting at the returned
+/// offset, moving the window as little as possible from `offset`.
+fn scroll_offset(offset: usize, selected: usize, len: usize, capacity: usize) -> usize {
+    let max_offset = len.saturating_sub(capacity);
+    let offset = if selected < offset {
+        selected
+    } else if selected >= offset + capacity {
+        selected + 1 - capacity
+    } else {
+        offset
+    };
+    offset.min(max_offset)
+}
+
+/// Relative time (with any query terms in it highlighted) on the first line and, for
+/// non-empty queries, the relevance percentage against the best match on the second.
+fn updated_cell(
+    result: &SearchResult,
+    basis: TimeBasis,
+    now: OffsetDateTime,
+    best_text_score: i64,
+    query_lower: &str,
+    theme: &Theme,
+) -> Cell<'static> {
+    let relative = format_relative(result.timestamp(basis), now);
+    let mut lines = vec![Line::from(highlighted_spans(
+        &relative,
+        query_lower,
+        Style::default(),
+        theme,
+    ))];
+    if let Some(pct) = result.relevance_percent(best_text_score) {
+        let color = match pct {
+            75.. => theme.relevance[0],
+            40.. => theme.relevance[1],
+            _ => theme.relevance[2],
+        };
+        lines.push(Line::from(Span::styled(
+            format!("{pct:>3}% match"),
+            Style::default().fg(color),
+        )));
+    }
+    Cell::from(Text::from(lines))
+}
+
+fn build_preview_text(
+    result: &SearchResult,
+    width_chars: usize,
+    query: &str,
+    ellipsis: &str,
+    theme: &Theme,
+) -> Text<'static> {
+    let width = width_chars.max(20);
+    let window = width.saturating_mul(2).max(80);
+
+    // Choose source text for the snippet
+    let source = result
+        .matched_message
+        .as_ref()
+        .map(|m| m.full_text.as_str())
+        .unwrap_or_else(|| result.session.label.as_str());
+
+    let lower = source.to_lowercase();
+    let q = normalize_query(query).to_lowercase();
+
+    // Center the window on the full query, or on the first term when the phrase is absent.
+    let anchor = if q.is_empty() {
+        None
+    } else if let Some(byte_idx) = lower.find(&q) {
+        let start = lower[..byte_idx].chars().count();
+        Some((start, start + q.chars().count()))
     } else {
-        Span::raw(text)
+        term_matches(source, &q)
+            .first()
+            .map(|&(start, end, _)| (start, end))
+    };
+    let total_chars = source.chars().count();
+    let (start_char, end_char) = match anchor {
+        Some((match_start, match_end)) => {
+            let match_len = match_end - match_start;
+            let desired_start = match_start.saturating_sub((window.saturating_sub(match_len)) / 2);
+            let mut start = desired_start.min(total_chars.saturating_sub(window));
+            if window > total_chars {
+                start = 0;
+            }
+            (start, (start + window).min(total_chars))
+        }
+        None => (0, total_chars.min(window)),
+    };
+
+    // Compose the full snippet string with ellipses and normalize whitespace
+    let mut full = String::new();
+    if start_char > 0 {
+        full.push_str(ellipsis);
+    }
+    full.extend(source.chars().skip(start_char).take(end_char - start_char));
+    if end_char < total_chars {
+        full.push_str(ellipsis);
+    }
+    full = normalize_ws(&full);
+
+    let styles = highlight_styles(&full, &q, theme);
+    let (mut line1, line2) = styled_two_lines(&full, &styles, width);
+    if result.session.archived {
+        line1
+            .spans
+            .insert(0, Span::styled("archived ", theme.subtle));
+    }
+    Text::from(vec![line1, line2])
+}
+
+/// Per-char styles for `text`: each query term gets its own color, the whole phrase is bold.
+fn highlight_styles(text: &str, query_lower: &str, theme: &Theme) -> Vec<Style> {
+    let mut styles: Vec<Style> = vec![Style::default(); text.chars().count()];
+    for (term_start, term_end, term_index) in term_matches(text, query_lower) {
+        let color = theme.terms[term_index % theme.terms.len()];
+        for style in &mut styles[term_start..term_end] {
+            *style = style.fg(color).add_modifier(theme.term_modifier);
+        }
+    }
+    let lower = text.to_lowercase();
+    if !query_lower.is_empty() && lower.chars().count() == styles.len() {
+        let phrase_len = query_lower.chars().count();
+        for (byte_idx, _) in lower.match_indices(query_lower) {
+            let start = lower[..byte_idx].chars().count();
+            for style in &mut styles[start..start + phrase_len] {
+                *style = style.add_modifier(Modifier::BOLD);
+            }
+        }
+    }
+    styles
+}
+
+/// Splits `text` into two visual lines and groups equally styled chars into spans,
+/// keeping `styles` (indexed by char of `text`) aligned across the break.
+fn styled_two_lines(text: &str, styles: &[Style], width: usize) -> (Line<'static>, Line<'static>) {
+    let (l1_str, l2_str) = split_visual_two_lines(text, width);
+    let offset = l1_str.chars().count();
+    (
+        Line::from(styled_spans(&l1_str, styles)),
+        Line::from(styled_spans(&l2_str, &styles[offset.min(styles.len())..])),
+    )
+}
+
+/// Groups equally styled chars of `text` into spans; chars past `styles` are unstyled.
+fn styled_spans(text: &str, styles: &[Style]) -> Vec<Span<'static>> {
+    let mut segments: Vec<Span> = Vec::new();
+    let mut current = String::new();
+    let mut current_style = Style::default();
+    for (i, ch) in text.chars().enumerate() {
+        let style = styles.get(i).copied().unwrap_or_default();
+        if style != current_style && !current.is_empty() {
+            segments.push(Span::styled(std::mem::take(&mut current), current_style));
+        }
+        current_style = style;
+        current.push(ch);
+    }
+    if !current.is_empty() {
+        segments.push(Span::styled(current, current_style));
     }
+    segments
+}
+
+/// `text` with query terms highlighted as in previews, over `base`.
+fn highlighted_spans(
+    text: &str,
+    query_lower: &str,
+    base: Style,
+    theme: &Theme,
+) -> Vec<Span<'static>> {
+    let styles: Vec<Style> = highlight_styles(text, query_lower, theme)
+        .into_iter()
+        .map(|style| base.patch(style))
+        .collect();
+    styled_spans(text, &styles)
+}
+
+/// Every message of `session` as `you:`/`codex:` paragraphs wrapped to `width`, with
+/// query terms highlighted, and the first line of `matched` when it is among them.
+fn preview_lines(
+    session: &Session,
+    matched: Option<&Message>,
+    width: usize,
+    query_lower: &str,
+    theme: &Theme,
+) -> (Vec<Line<'static>>, Option<usize>) {
+    let width = width.max(1);
+    let mut lines: Vec<Line<'static>> = Vec::new();
+    let mut match_line = None;
+    for message in &session.messages {
+        let is_match =
+            matched.is_some_and(|m| m.source_line == message.source_line && m.role == message.role);
+        if is_match && match_line.is_none() {
+            match_line = Some(lines.len());
+        }
+        let (prefix, color) = match message.role {
+            MessageRole::User => ("you: ", theme.roles[0]),
+            MessageRole::Assistant => ("codex: ", theme.roles[1]),
+            MessageRole::Tool => ("tool: ", theme.roles[2]),
+        };
+        let mut prefix_style = Style::default().fg(color).add_modifier(Modifier::BOLD);
+        if is_match {
+            prefix_style = prefix_style.add_modifier(Modifier::REVERSED);
+        }
+        let text = format!("{prefix}{}", message.full_text.trim());
+        for (i, paragraph) in text.lines().enumerate() {
+            for (j, row) in wrap_row(paragraph, width).into_iter().enumerate() {
+                let first = i == 0 && j == 0;
+                let spans = match row.strip_prefix(prefix).filter(|_| first) {
+                    Some(rest) => {
+                        let mut spans = vec![Span::styled(prefix, prefix_style)];
+                        spans.extend(highlighted_spans(rest, query_lower, theme.base, theme));
+                        spans
+                    }
+                    None => highlighted_spans(&row, query_lower, theme.base, theme),
+                };
+                lines.push(Line::from(spans));
+            }
+        }
+        lines.push(Line::default());
+    }
+    lines.pop();
+    (lines, match_line)
+}
+
+/// `text` broken into rows of at most `width` chars, at whitespace where possible.
+fn wrap_row(text: &str, width: usize) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut rest = text;
+    while let Some((cut, _)) = rest.char_indices().nth(width) {
+        let split = rest[..cut]
+            .char_indices()
+            .rev()
+            .find(|&(i, c)| i > 0 && c.is_whitespace())
+            .map_or(cut, |(i, c)| i + c.len_utf8());
+        rows.push(rest[..split].trim_end().to_owned());
+        rest = &rest[split..];
+    }
+    rows.push(rest.to_owned());
+    rows
+}
+
+fn split_visual_two_lines(s: &str, width: usize) -> (String, String) {
+    let total = s.chars().count();
+    if total <= width {
+        // Pad into two lines by taking up to width and the remainder empty
+        return (s.to_owned(), String::new());
+    }
+    // Prefer breaking at whitespace near the width
+    let mut split_idx = 0usize; // byte index
+    let mut last_space_byte: Option<usize> = None;
+    for (count, (byte_idx, ch)) in s.char_indices().enumerate() {
+        if ch.is_whitespace() {
+            last_space_byte = Some(byte_idx);
+        }
+        if count >= width {
+            break;
+        }
+        split_idx = byte_idx + ch.len_utf8();
+    }
+    if let Some(space) = last_space_byte {
+        split_idx = space + 1;
+    }
+    let (l1, l2) = s.split_at(split_idx);
+    (l1.to_owned(), l2.to_owned())
+}
+
+fn normalize_ws(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut last = false;
+    for ch in s.chars() {
+        if ch.is_whitespace() {
+            if !last {
+                out.push(' ');
+                last = true;
+            }
+        } else {
+            out.push(ch);
+            last = false;
+        }
+    }
+    out.trim().to_string()
 }
 
 fn truncate_last_word(buffer: &mut String) {
@@ -746,36 +2276,942 @@ struct ScoreJob {
     id: u64,
     query: String,
     candidates: Vec<Arc<Session>>,
+    /// Counts results left after every filter, so it is applied last.
     limit: usize,
+    /// Sidebar directory filter, applied before `limit` so the cap can backfill.
+    cwd_focus: Option<PathBuf>,
+    options: SearchOptions,
 }
 
 struct ScoreResult {
     id: u64,
     results: Vec<SearchResult>,
+    /// Matches across all directories, before the cwd focus and `limit`.
+    match_count: usize,
+    /// Distinct cwds among all matches with their counts, most common first.
+    cwd_groups: Vec<(PathBuf, usize)>,
 }
 
-fn worker_loop(job_rx: Receiver<ScoreJob>, res_tx: Sender<ScoreResult>) {
+/// Scores jobs until the UI hangs up. With an index, sessions are indexed the first
+/// time they show up as candidates and later queries only score the index's hits.
+fn worker_loop(
+    job_rx: Receiver<ScoreJob>,
+    res_tx: Sender<ScoreResult>,
+    mut index: Option<SessionIndex>,
+) {
+    let mut cache = MessageCache::default();
     while let Ok(job) = job_rx.recv() {
-        let mut scorer = Scorer::new(&job.query);
-        let mut results: Vec<SearchResult> = Vec::new();
-        if scorer.is_query_empty() {
-            for session in job.candidates.into_iter() {
-                if let Some(r) = scorer.score_session_arc(session) {
-                    results.push(r);
-                }
+        let mut scorer = Scorer::with_cache(&job.query, job.options, std::mem::take(&mut cache));
+        let mut candidates = job.candidates;
+        if let Some(index) = &mut index
+            && !scorer.is_query_empty()
+        {
+            for session in &candidates {
+                index.insert(session);
             }
-        } else {
-            for session in job.candidates.into_iter() {
-                if let Some(r) = scorer.score_session_arc(session) {
-                    results.push(r);
-                }
+            candidates = index.candidates(candidates, &scorer);
+        }
+        let mut results: Vec<SearchResult> = Vec::new();
+        for session in candidates {
+            if let Some(r) = scorer.score_session_arc(session) {
+                results.push(r);
             }
         }
-        results.sort_by(|a, b| match b.match_timestamp().cmp(&a.match_timestamp()) {
-            Ordering::Equal => b.score.cmp(&a.score),
-            other => other,
+        scorer.sort(&mut results);
+        let match_count = results.len();
+        let cwd_groups = cwd_groups(&results);
+        if let Some(focus) = &job.cwd_focus {
+            results.retain(|r| r.session.cwd.as_ref() == Some(focus));
+        }
+        results.truncate(job.limit);
+        cache = scorer.into_cache();
+        let _ = res_tx.send(ScoreResult {
+            id: job.id,
+            results,
+            match_count,
+            cwd_groups,
         });
-        if results.len() > job.limit { results.truncate(job.limit); }
-        let _ = res_tx.send(ScoreResult { id: job.id, results });
+    }
+}
+
+/// Distinct cwds among `results` with their counts, most common first.
+fn cwd_groups(results: &[SearchResult]) -> Vec<(PathBuf, usize)> {
+    let mut groups: Vec<(PathBuf, usize)> = Vec::new();
+    for cwd in results.iter().filter_map(|r| r.session.cwd.as_ref()) {
+        match groups.iter_mut().find(|(path, _)| path == cwd) {
+            Some((_, count)) => *count += 1,
+            None => groups.push((cwd.clone(), 1)),
+        }
+    }
+    groups.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(total_expected: usize) -> TuiConfig {
+        TuiConfig {
+            limit: 20,
+            resume_command: String::new(),
+            resume_in: ResumeTarget::Current,
+            dry_run: true,
+            initial_query: String::new(),
+            empty_status: None,
+            total_expected,
+            filter_cwd: None,
+            search: SearchOptions::default(),
+            glyphs: Glyphs::ascii(),
+            browse: false,
+            role_glyphs: false,
+            progress_bar: false,
+            keys: KeyMap::default(),
+            index: false,
+            watch: false,
+            discovery: DiscoveryOptions::with_defaults().unwrap(),
+            export_path: PathBuf::new(),
+            theme: Theme::standard(),
+        }
+    }
+
+    fn session_in(uuid: &str, cwd: &str, age_secs: i64) -> Arc<Session> {
+        Arc::new(Session {
+            updated_at: OffsetDateTime::now_utc() - time::Duration::seconds(age_secs),
+            cwd: Some(PathBuf::from(cwd)),
+            ..Session::for_test(uuid, uuid, Vec::new())
+        })
+    }
+
+    /// A result for `session` without a matched message or snippet, as label-only
+    /// matches and empty queries produce.
+    fn unmatched(session: Arc<Session>) -> SearchResult {
+        SearchResult {
+            session,
+            matched_message: None,
+            score: 0,
+            text_score: 0,
+            snippet: crate::session::Snippet {
+                segments: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn cwd_focus_backfills_up_to_the_limit() {
+        let (job_tx, job_rx) = unbounded();
+        let (res_tx, res_rx) = unbounded();
+        job_tx
+            .send(ScoreJob {
+                id: 1,
+                query: String::new(),
+                candidates: vec![
+                    session_in("a1", "/a", 10),
+                    session_in("a2", "/a", 20),
+                    session_in("b1", "/b", 30),
+                    session_in("b2", "/b", 40),
+                ],
+                limit: 2,
+                cwd_focus: Some(PathBuf::from("/b")),
+                options: SearchOptions::default(),
+            })
+            .unwrap();
+        drop(job_tx);
+        worker_loop(job_rx, res_tx, None);
+
+        let result = res_rx.recv().unwrap();
+        let uuids: Vec<&str> = result
+            .results
+            .iter()
+            .map(|r| r.session.uuid.as_str())
+            .collect();
+        assert_eq!(uuids, ["b1", "b2"]);
+        assert_eq!(result.match_count, 4);
+        assert_eq!(
+            result.cwd_groups,
+            [(PathBuf::from("/a"), 2), (PathBuf::from("/b"), 2)]
+        );
+    }
+
+    #[test]
+    fn progress_bar_fills_proportionally() {
+        assert_eq!(progress_bar(0, 8, 4, ['#', '-']), "----");
+        assert_eq!(progress_bar(3, 8, 4, ['#', '-']), "#---");
+        assert_eq!(progress_bar(8, 8, 4, ['#', '-']), "####");
+        assert_eq!(progress_bar(9, 8, 4, ['#', '-']), "####");
+    }
+
+    #[test]
+    fn scroll_window_follows_selection_minimally() {
+        assert_eq!(scroll_offset(0, 3, 1000, 5), 0);
+        assert_eq!(scroll_offset(0, 5, 1000, 5), 1);
+        assert_eq!(scroll_offset(10, 12, 1000, 5), 10);
+        assert_eq!(scroll_offset(10, 4, 1000, 5), 4);
+        // Shrunk result sets pull the window back so it stays full.
+        assert_eq!(scroll_offset(10, 2, 6, 5), 1);
+    }
+
+    #[test]
+    fn footer_hints_follow_the_query_and_sidebar_state() {
+        let (_tx, rx) = unbounded();
+        let mut app = App::new(rx, &config(0));
+        let hints = app.footer_hints();
+        assert!(hints.contains(&"type to search".to_string()));
+        assert!(hints.contains(&"Ctrl-G: directories".to_string()));
+        assert!(hints.contains(&"Alt-D: details".to_string()));
+        assert!(hints.contains(&"F1: help".to_string()));
+
+        app.query = "tokio".into();
+        app.sidebar = true;
+        let hints = app.footer_hints();
+        assert!(hints.contains(&"Ctrl-U: clear • Ctrl-W: delete word".to_string()));
+        assert!(hints.contains(&"sorted by recency • Ctrl-S: toggle".to_string()));
+        assert!(hints.contains(&"Ctrl-G: hide directories • Tab: focus them".to_string()));
+
+        app.cwd_focus = Some(PathBuf::from("/work/app"));
+        assert!(app.status_text().contains(" • in app • "));
+    }
+
+    #[test]
+    fn preview_wraps_messages_and_starts_at_the_match() {
+        let message = |role: MessageRole, text: &str, source_line: usize| Message {
+            role,
+            text: text.into(),
+            timestamp: None,
+            full_text: text.into(),
+            full_text_lower: text.to_lowercase(),
+            full_text_ws_lower: text.to_lowercase(),
+            source_line,
+        };
+        let mut session = (*session_in("s1", "/a", 10)).clone();
+        session.messages = vec![
+            message(MessageRole::User, "fix the tokio runtime panic", 1),
+            message(MessageRole::Assistant, "done", 2),
+            message(MessageRole::User, "now ship it", 3),
+        ];
+        let matched = session.messages[2].clone();
+        let (lines, match_line) =
+            preview_lines(&session, Some(&matched), 16, "", &Theme::standard());
+        let rows: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        assert_eq!(
+            rows,
+            [
+                "you: fix the",
+                "tokio runtime",
+                "panic",
+                "",
+                "codex: done",
+                "",
+                "you: now ship it",
+            ]
+        );
+        assert_eq!(match_line, Some(6));
+
+        let (_tx, rx) = unbounded();
+        let mut app = App::new(rx, &config(0));
+        app.on_action(Action::SwitchFocus);
+        assert_eq!(app.focus, Focus::Results);
+        app.on_action(Action::TogglePreview);
+        app.on_action(Action::SwitchFocus);
+        assert_eq!(app.focus, Focus::Preview);
+        app.on_action(Action::ToggleSidebar);
+        app.on_action(Action::SwitchFocus);
+        assert_eq!(app.focus, Focus::Preview);
+        app.on_action(Action::Quit);
+        assert_eq!(app.focus, Focus::Results);
+    }
+
+    #[test]
+    fn query_edits_happen_at_the_cursor() {
+        let (_tx, rx) = unbounded();
+        let mut app = App::new(rx, &config(0));
+        let press = |app: &mut App, code: KeyCode| {
+            app.on_key(KeyEvent::new(code, KeyModifiers::NONE)).unwrap();
+        };
+        for c in "héllo wörld".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        for _ in 0..6 {
+            press(&mut app, KeyCode::Left);
+        }
+        press(&mut app, KeyCode::Backspace);
+        press(&mut app, KeyCode::Char('!'));
+        assert_eq!(app.query, "héll! wörld");
+        assert_eq!(app.query_halves(), ("héll!", " wörld"));
+
+        app.on_action(Action::DeleteWord);
+        assert_eq!(app.query, " wörld");
+        press(&mut app, KeyCode::End);
+        press(&mut app, KeyCode::Left);
+        app.on_action(Action::ClearQuery);
+        assert_eq!((app.query.as_str(), app.cursor), ("d", 0));
+        press(&mut app, KeyCode::Left);
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Right);
+        assert_eq!(app.cursor, 1);
+    }
+
+    #[test]
+    fn resume_prompts_are_answered_before_selecting() {
+        let mut config = config(0);
+        config.resume_command =
+            "codex resume {uuid} -C {prompt:worktree=main} {prompt:note}".into();
+        let (_tx, rx) = unbounded();
+        let mut app = App::new(rx, &config);
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        let result = unmatched(session_in("abc", "/tmp", 0));
+        assert!(app.select(&result).is_none());
+        assert!(app.status_text().starts_with("worktree [main]: "));
+        assert!(app.on_prompt_key(key(KeyCode::Esc)).is_none());
+        app.on_prompt_key(key(KeyCode::Char('x')));
+        match app.on_prompt_key(key(KeyCode::Enter)) {
+            Some(AppOutcome::Selected(selection, template)) => {
+                assert_eq!(selection.uuid, "abc");
+                assert_eq!(template, "codex resume {uuid} -C main x");
+            }
+            _ => panic!("expected a selection after the last prompt"),
+        }
+    }
+
+    #[test]
+    fn deleting_a_session_asks_first_and_removes_it_everywhere() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("doomed.jsonl");
+        std::fs::write(&path, "{}").unwrap();
+        let mut session = (*session_in("doomed", "/tmp", 0)).clone();
+        session.path = path.clone();
+        let session = Arc::new(session);
+        let (_tx, rx) = unbounded();
+        let mut app = App::new(rx, &config(0));
+        app.sessions = vec![Arc::clone(&session), session_in("kept", "/tmp", 10)];
+        app.results = app
+            .sessions
+            .iter()
+            .map(|session| unmatched(Arc::clone(session)))
+            .collect();
+        let ctrl_x = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL);
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+
+        app.on_key(ctrl_x).unwrap();
+        assert!(app.status_text().starts_with("Delete doomed ("));
+        app.on_key(key('n')).unwrap();
+        assert_eq!(app.status_text(), "Delete cancelled");
+        assert!(path.exists());
+
+        app.on_key(ctrl_x).unwrap();
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert!(matches!(app.on_key(ctrl_c), Ok(Some(AppOutcome::Exit))));
+        assert!(path.exists());
+        app.on_key(key('n')).unwrap();
+
+        app.on_key(ctrl_x).unwrap();
+        app.on_key(key('y')).unwrap();
+        assert!(!path.exists());
+        let uuids: Vec<&str> = app.sessions.iter().map(|s| s.uuid.as_str()).collect();
+        assert_eq!(uuids, ["kept"]);
+        assert_eq!(app.results.len(), 1);
+
+        app.sessions.push(Arc::clone(&session));
+        assert!(app.delete_session(&session).starts_with("Delete failed: "));
+    }
+
+    #[test]
+    fn browse_days_expand_and_collapse_around_the_cursor() {
+        let mut config = config(0);
+        config.browse = true;
+        let (_tx, rx) = unbounded();
+        let mut app = App::new(rx, &config);
+        let at = |uuid: &str, day: u8, minute: u8| {
+            let mut session = (*session_in(uuid, "/tmp", 0)).clone();
+            session.updated_at = time::macros::datetime!(2025-01-01 12:00 UTC)
+                .replace_day(day)
+                .unwrap()
+                .replace_minute(minute)
+                .unwrap();
+            unmatched(Arc::new(session))
+        };
+        app.results = vec![at("late", 5, 0), at("b", 2, 1), at("a", 2, 0)];
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        assert_eq!(app.visible_len(), 2);
+
+        app.on_key(press(KeyCode::Down)).unwrap();
+        app.on_key(press(KeyCode::Right)).unwrap();
+        assert_eq!(app.visible_len(), 4);
+        app.on_key(press(KeyCode::Down)).unwrap();
+        assert_eq!(app.selected_index(), Some(1));
+        app.on_key(press(KeyCode::Left)).unwrap();
+        assert_eq!((app.visible_len(), app.selected), (2, 1));
+        assert_eq!(app.selected_index(), None);
+    }
+
+    #[test]
+    fn popups_open_and_close_through_the_key_map() {
+        let mut overrides = std::collections::BTreeMap::new();
+        overrides.insert(
+            "quit".to_string(),
+            crate::keys::KeySpec::One("ctrl-q".into()),
+        );
+        let mut config = config(0);
+        config.keys = KeyMap::new(&overrides).unwrap();
+        let (_tx, rx) = unbounded();
+        let mut app = App::new(rx, &config);
+        let press = |code, modifiers| KeyEvent::new(code, modifiers);
+
+        app.on_key(press(KeyCode::F(1), KeyModifiers::NONE))
+            .unwrap();
+        let help = &app.popup.as_ref().unwrap().text;
+        assert!(
+            help.lines()
+                .any(|line| line.starts_with("quit") && line.ends_with("Ctrl-Q"))
+        );
+        assert!(
+            help.lines()
+                .any(|line| line.starts_with("help") && line.ends_with("F1"))
+        );
+        assert_eq!(app.status_text(), "↑/↓/PgUp/PgDn: scroll • Ctrl-Q: close");
+        // Esc is no longer bound, and plain letters do not close popups.
+        app.on_key(press(KeyCode::Esc, KeyModifiers::NONE)).unwrap();
+        app.on_key(press(KeyCode::Char('q'), KeyModifiers::NONE))
+            .unwrap();
+        assert!(app.popup.is_some());
+        app.on_key(press(KeyCode::Char('q'), KeyModifiers::CONTROL))
+            .unwrap();
+        assert!(app.popup.is_none());
+
+        let alt_d = press(KeyCode::Char('d'), KeyModifiers::ALT);
+        app.on_key(alt_d).unwrap();
+        assert!(app.popup.is_none());
+        assert_eq!(app.status_text(), "Select a session to show its details");
+        app.results = vec![unmatched(session_in("abcd", "/work/repo", 0))];
+        app.on_key(alt_d).unwrap();
+        let detail = &app.popup.as_ref().unwrap().text;
+        assert!(detail.contains("UUID      abcd"));
+        assert!(detail.contains("Cwd       /work/repo"));
+        app.on_key(alt_d).unwrap();
+        assert!(app.popup.is_none());
+    }
+
+    #[test]
+    fn low_memory_reloads_only_displayed_results_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let long = format!("{} needle at the end", "filler ".repeat(100));
+        let paths: Vec<PathBuf> = ["aaaa", "bbbb"]
+            .iter()
+            .map(|uuid| {
+                let path = tmp
+                    .path()
+                    .join(format!("s-2025-01-01T00-00-00-{uuid}.jsonl"));
+                std::fs::write(&path, format!(r#"{{"role":"user","content":"{long}"}}"#)).unwrap();
+                path
+            })
+            .collect();
+        let mut config = config(0);
+        config.discovery.low_memory = true;
+        let (_tx, rx) = unbounded();
+        let mut app = App::new(rx, &config);
+        app.query = "needle".into();
+        let mut scorer = Scorer::new("needle");
+        app.results = paths
+            .iter()
+            .map(|path| {
+                let session = discovery::load_session_from_path(path.clone(), &config.discovery)
+                    .unwrap()
+                    .unwrap();
+                scorer.score_session_arc(Arc::new(session)).unwrap()
+            })
+            .collect();
+        assert!(app.results.iter().all(|r| r.session.compacted));
+        let compacted = app.results[0].clone();
+
+        app.rehydrate(0..1);
+        assert_eq!(app.results[0].session.messages[0].full_text, long);
+        assert!(app.results[1].session.compacted);
+
+        // The next job hands back the compacted session; the cached copy is reused.
+        std::fs::remove_file(&paths[0]).unwrap();
+        app.results[0] = compacted;
+        app.rehydrate(0..1);
+        assert!(!app.results[0].session.compacted);
+        assert_eq!(app.full_sessions.len(), 1);
+    }
+
+    #[test]
+    fn a_file_from_both_the_stream_and_the_watcher_loads_once() {
+        let (tx, rx) = unbounded();
+        let (watch_tx, watch_rx) = unbounded();
+        let mut app = App::new(rx, &config(2));
+        app.watch_rx = Some(watch_rx);
+        let session = |uuid: &str, label: &str| {
+            let mut session = (*session_in(uuid, "/a", 0)).clone();
+            session.label = label.into();
+            session
+        };
+
+        // Written during the initial scan: the watcher delivers it before the stream.
+        watch_tx.send(session("a", "watched")).unwrap();
+        app.ingest_new_sessions();
+        assert_eq!(app.total_expected, 3);
+        tx.send(session("a", "streamed")).unwrap();
+        tx.send(session("b", "streamed")).unwrap();
+        app.ingest_new_sessions();
+        // Rewritten after the stream loaded it.
+        watch_tx.send(session("b", "watched")).unwrap();
+        app.ingest_new_sessions();
+
+        let labels: Vec<&str> = app.sessions.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, ["streamed", "watched"]);
+        assert_eq!(app.total_expected, 2);
+        assert_eq!(app.injector.injected_items(), 2);
+    }
+
+    #[test]
+    fn indexing_eta_follows_the_stream_rate_until_it_finishes() {
+        let (tx, rx) = unbounded();
+        let mut app = App::new(rx, &config(10));
+        assert_eq!(app.indexing_eta(Duration::from_secs(2)), None);
+
+        for i in 0..4 {
+            tx.send((*session_in(&format!("s{i}"), "/a", 0)).clone())
+                .unwrap();
+        }
+        app.ingest_new_sessions();
+        // 4 sessions in 2s: the other 6 take 3s more.
+        assert_eq!(
+            app.indexing_eta(Duration::from_secs(2)),
+            Some(time::Duration::seconds(3))
+        );
+        let progress = |app: &App| app.progress_text(Duration::ZERO, Duration::from_secs(2));
+        assert!(progress(&app).starts_with("| Indexing 4/10 sessions... ~"));
+
+        drop(tx);
+        app.ingest_new_sessions();
+        assert_eq!(progress(&app), "Indexed 4/10 sessions");
+    }
+
+    #[test]
+    fn first_frame_shows_progress_before_any_session_arrives() {
+        let (tx, rx) = unbounded();
+        let mut app = App::new(rx, &config(7));
+        let frame =
+            |app: &App, millis| app.progress_text(Duration::from_millis(millis), Duration::ZERO);
+        assert_eq!(frame(&app, 0), "| Indexing 0/7 sessions...");
+        assert_eq!(frame(&app, 250), "- Indexing 0/7 sessions...");
+        assert!(app.status_text().contains(" Indexing 0/7 sessions..."));
+
+        drop(tx);
+        app.ingest_new_sessions();
+        app.refresh_results().unwrap();
+        assert!(app.status_text().contains("No matches"));
+    }
+
+    /// The screen after one `draw` on a `width`×`height` terminal, one line per row.
+    fn render(app: &mut App, width: u16, height: u16) -> String {
+        let mut terminal =
+            Terminal::new(ratatui::backend::TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let area = buffer.area;
+        (area.top()..area.bottom())
+            .map(|y| {
+                (area.left()..area.right())
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn role_marker_fits_within_the_preview_column() {
+        let mut config = config(0);
+        config.role_glyphs = true;
+        let (_tx, rx) = unbounded();
+        let mut app = App::new(rx, &config);
+        let digits = "0123456789".repeat(30);
+        let mut session = (*session_in("abcd", "/tmp", 0)).clone();
+        session.messages = vec![Message {
+            role: MessageRole::User,
+            text: digits.clone(),
+            full_text: digits.clone(),
+            full_text_lower: digits.clone(),
+            full_text_ws_lower: digits.clone(),
+            timestamp: None,
+            source_line: 0,
+        }];
+        let mut result = unmatched(Arc::new(session.clone()));
+        result.matched_message = Some(session.messages[0].clone());
+        app.results = vec![result];
+
+        let screen = render(&mut app, 70, 12);
+        let lines: Vec<&str> = screen.lines().collect();
+        let row = lines.iter().position(|l| l.contains("> 0123")).unwrap();
+        let byte = lines[row].find("> 0123").unwrap();
+        let column = lines[row][..byte].chars().count();
+        let clip = |line: &str| {
+            let cell: String = line.chars().skip(column).collect();
+            cell.trim_end_matches(['│', ' ', '.']).to_string()
+        };
+        // Line 2 continues where line 1 stopped and is cut by the column border.
+        let shown = clip(lines[row])[2..].to_string() + &clip(lines[row + 1]);
+        assert!(digits.starts_with(&shown), "{shown}\n{screen}");
+    }
+
+    #[test]
+    fn high_contrast_terms_stay_distinct_on_the_selection_bar() {
+        let mut config = config(0);
+        config.theme = Theme::high_contrast();
+        let (_tx, rx) = unbounded();
+        let mut app = App::new(rx, &config);
+        let text = "fix the tokio runtime";
+        let mut session = (*session_in("abcd", "/tmp", 0)).clone();
+        session.messages = vec![Message {
+            role: MessageRole::User,
+            text: text.into(),
+            full_text: text.into(),
+            full_text_lower: text.into(),
+            full_text_ws_lower: text.into(),
+            timestamp: None,
+            source_line: 0,
+        }];
+        let mut result = unmatched(Arc::new(session.clone()));
+        result.matched_message = Some(session.messages[0].clone());
+        app.results = vec![result];
+        app.query = "tokio".into();
+
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(70, 12)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let area = buffer.area;
+        let grays = [Color::Gray, Color::DarkGray];
+        for (x, y) in (area.top()..area.bottom()).flat_map(|y| (0..area.width).map(move |x| (x, y)))
+        {
+            let cell = buffer.get(x, y);
+            assert!(!grays.contains(&cell.fg) && !grays.contains(&cell.bg));
+        }
+
+        let (row, column) = (area.top()..area.bottom())
+            .find_map(|y| {
+                let line: String = (0..area.width).map(|x| buffer.get(x, y).symbol()).collect();
+                let start = line.find("tokio").filter(|_| line.contains('▶'))?;
+                Some((y, line[..start].chars().count() as u16))
+            })
+            .unwrap();
+        let (term, plain) = (buffer.get(column, row), buffer.get(column - 2, row));
+        assert_eq!((term.bg, plain.bg), (Color::Blue, Color::Blue));
+        assert!(term.modifier.contains(Modifier::UNDERLINED));
+        assert!(!plain.modifier.contains(Modifier::UNDERLINED));
+        assert_eq!((term.fg, plain.fg), (Color::LightYellow, Color::White));
+    }
+
+    #[test]
+    fn updated_column_is_hidden_on_narrow_terminals() {
+        let (_tx, rx) = unbounded();
+        let mut app = App::new(rx, &config(0));
+        assert!(render(&mut app, 100, 12).contains("Updated"));
+        let narrow = render(&mut app, MIN_WIDTH_FOR_UPDATED_COLUMN - 10, 12);
+        assert!(!narrow.contains("Updated"));
+        assert!(narrow.contains("Preview"));
+    }
+
+    /// Concatenated text of the bold spans on one line.
+    fn bold_text(line: &Line) -> String {
+        line.spans
+            .iter()
+            .filter(|span| span.style.add_modifier.contains(Modifier::BOLD))
+            .map(|span| span.content.as_ref())
+            .collect()
+    }
+
+    #[test]
+    fn label_only_match_previews_the_highlighted_label() {
+        let mut session = (*session_in("abcd", "/tmp", 0)).clone();
+        session.label = "deploy checklist".into();
+        let result = unmatched(Arc::new(session));
+        let text = build_preview_text(&result, 40, "checklist", "...", &Theme::standard());
+        assert_eq!(bold_text(&text.lines[0]), "checklist");
+    }
+
+    #[test]
+    fn phrase_highlight_straddling_the_break_covers_exactly_the_query() {
+        let text = "some leading words tokio runtime trailing";
+        let query = "tokio runtime";
+        let styles = highlight_styles(text, query, &Theme::standard());
+        // Width 25 breaks after "tokio ", inside the phrase.
+        let (line1, line2) = styled_two_lines(text, &styles, 25);
+        assert_eq!(line1.to_string(), "some leading words tokio ");
+        assert_eq!(bold_text(&line1), "tokio ");
+        assert_eq!(bold_text(&line2), "runtime");
+    }
+
+    #[test]
+    fn metadata_spans_highlight_terms_over_the_base_style() {
+        let theme = Theme::standard();
+        let base = Style::default().add_modifier(Modifier::ITALIC);
+        let spans = highlighted_spans("codex-search", "search", base, &theme);
+        let texts: Vec<&str> = spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(texts, ["codex-", "search"]);
+        assert_eq!(spans[0].style, base);
+        assert_eq!(spans[1].style.fg, Some(theme.terms[0]));
+        assert!(spans[1].style.add_modifier.contains(Modifier::ITALIC));
+    }
+
+    #[test]
+    fn word_split_without_whitespace_keeps_highlight_aligned() {
+        let text = "abcdefghijNEEDLEklmnop";
+        let styles = highlight_styles(text, "needle", &Theme::standard());
+        // No whitespace, so the break lands mid-word at the width.
+        let (line1, line2) = styled_two_lines(text, &styles, 13);
+        assert_eq!(bold_text(&line1), "NEE");
+        assert_eq!(bold_text(&line2), "DLE");
+        let colored: String = [line1, line2]
+            .iter()
+            .flat_map(|line| line.spans.iter())
+            .filter(|span| span.style.fg.is_some())
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(colored, "NEEDLE");
+    }
+
+    #[test]
+    fn each_term_gets_its_own_color_on_both_lines() {
+        let text = "alpha filler filler filler beta";
+        let styles = highlight_styles(text, "alpha beta", &Theme::standard());
+        let (line1, line2) = styled_two_lines(text, &styles, 20);
+        let color_of = |line: &Line, word: &str| {
+            line.spans
+                .iter()
+                .find(|span| span.content == word)
+                .and_then(|span| span.style.fg)
+        };
+        assert_eq!(color_of(&line1, "alpha"), Some(Theme::standard().terms[0]));
+        assert_eq!(color_of(&line2, "beta"), Some(Theme::standard().terms[1]));
+    }
+
+    #[test]
+    fn copy_actions_take_the_selected_uuid_or_path() {
+        let (_tx, rx) = unbounded();
+        let mut app = App::new(rx, &config(0));
+        let key = |code, modifiers| app.keys.action(&KeyEvent::new(code, modifiers));
+        assert_eq!(
+            key(KeyCode::Char('y'), KeyModifiers::CONTROL),
+            Some(Action::CopyUuid)
+        );
+        assert_eq!(
+            key(KeyCode::Char('y'), KeyModifiers::ALT),
+            Some(Action::CopyPath)
+        );
+        assert_eq!(app.copy_target(Action::CopyUuid), None);
+        app.on_action(Action::CopyUuid);
+        assert_eq!(app.notice.as_deref(), Some("Nothing selected to copy"));
+
+        app.results = vec![
+            unmatched(session_in("aaaa", "/a", 0)),
+            unmatched(session_in("bbbb", "/b", 10)),
+        ];
+        app.selected = 1;
+        assert_eq!(
+            app.copy_target(Action::CopyUuid),
+            Some(("UUID", "bbbb".to_string()))
+        );
+        assert_eq!(
+            app.copy_target(Action::CopyPath),
+            Some(("path", "/sessions/bbbb.jsonl".to_string()))
+        );
+    }
+
+    #[test]
+    fn ctrl_e_exports_the_results_and_reports_the_outcome() {
+        let dir =
+            std::env::temp_dir().join(format!("codex-search-export-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut config = config(0);
+        config.export_path = dir.join("results.json");
+        let (_tx, rx) = unbounded();
+        let mut app = App::new(rx, &config);
+        app.results = vec![
+            unmatched(session_in("aaaa", "/a", 0)),
+            unmatched(session_in("bbbb", "/b", 10)),
+        ];
+        let ctrl_e = KeyEvent::new(KeyCode::Char('e'), KeyModifiers::CONTROL);
+
+        app.on_key(ctrl_e).unwrap();
+        let path = dir.join("results.json");
+        assert_eq!(
+            app.notice.as_deref(),
+            Some(format!("Exported 2 results to {}", path.display()).as_str())
+        );
+        let exported: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let uuids: Vec<&str> = exported
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["session"]["uuid"].as_str().unwrap())
+            .collect();
+        assert_eq!(uuids, ["aaaa", "bbbb"]);
+
+        app.export_path = dir.join("missing/results.json");
+        app.on_key(ctrl_e).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(
+            app.notice
+                .as_deref()
+                .unwrap()
+                .starts_with("Export failed: creating")
+        );
+    }
+
+    /// An app streaming the newest `scan_limit` of three one-message sessions in `dir`.
+    fn scanning_app(dir: &Path, scan_limit: usize) -> App {
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(dir).unwrap();
+        for name in [
+            "a-2025-01-01T00-00-00-aaaa",
+            "b-2025-01-02T00-00-00-bbbb",
+            "c-2025-01-03T00-00-00-cccc",
+        ] {
+            std::fs::write(
+                dir.join(format!("{name}.jsonl")),
+                r#"{"role":"user","content":"hello"}"#,
+            )
+            .unwrap();
+        }
+        let mut config = config(scan_limit);
+        config.discovery.roots = vec![dir.to_path_buf()];
+        config.discovery.scan_limit = scan_limit;
+        let paths = discovery::collect_session_paths(&config.discovery).unwrap();
+        let stream = discovery::stream_sessions(paths, config.discovery.clone());
+        App::new(stream.receiver(), &config)
+    }
+
+    fn index_all(app: &mut App) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !app.stream_finished && Instant::now() < deadline {
+            app.ingest_new_sessions();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(app.stream_finished);
+    }
+
+    #[test]
+    fn scan_expansion_shows_progress_and_keeps_the_query() {
+        let dir =
+            std::env::temp_dir().join(format!("codex-search-expand-test-{}", std::process::id()));
+        let mut app = scanning_app(&dir, 1);
+        let ctrl_l = KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL);
+        app.on_key(ctrl_l).unwrap();
+        assert_eq!(
+            app.notice.as_deref(),
+            Some("Still indexing; try again when indexing finishes")
+        );
+        index_all(&mut app);
+        settle(&mut app);
+        app.notice = None;
+        let status = app.status_text();
+        assert!(
+            status.ends_with("Indexed 1/1 sessions — scan limit reached, Ctrl-L: load more"),
+            "{status}"
+        );
+
+        app.query = "hello".into();
+        app.cursor = 5;
+        app.on_key(ctrl_l).unwrap();
+        let status = app.status_text();
+        assert!(status.contains(" Expanding scan: 1/2 sessions"), "{status}");
+        index_all(&mut app);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(app.sessions.len(), 2);
+        assert!(!app.expanding);
+        assert_eq!((app.query.as_str(), app.cursor), ("hello", 5));
+        settle(&mut app);
+        let status = app.status_text();
+        assert!(status.contains("Indexed 2/2 sessions"), "{status}");
+    }
+
+    #[test]
+    fn ctrl_l_streams_in_the_next_scan_window() {
+        let dir = std::env::temp_dir().join(format!(
+            "codex-search-load-more-test-{}",
+            std::process::id()
+        ));
+        let mut app = scanning_app(&dir, 2);
+        index_all(&mut app);
+        assert_eq!(app.sessions.len(), 2);
+
+        let ctrl_l = KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL);
+        app.on_key(ctrl_l).unwrap();
+        assert_eq!(app.notice, None);
+        assert_eq!(app.total_expected, 3);
+        assert_eq!(app.discovery.scan_limit, 4);
+        index_all(&mut app);
+        assert_eq!(app.sessions.len(), 3);
+
+        app.on_key(ctrl_l).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(
+            app.notice
+                .as_deref()
+                .unwrap()
+                .starts_with("No more sessions under")
+        );
+        assert_eq!(app.sessions.len(), 3);
+    }
+
+    /// Polls the worker until the results reflect the app's current state.
+    fn settle(app: &mut App) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        app.refresh_results().unwrap();
+        while (app.results_dirty || app.pending_job.is_some()) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+            app.refresh_results().unwrap();
+        }
+        assert!(app.pending_job.is_none());
+    }
+
+    #[test]
+    fn sidebar_groups_results_by_cwd_and_filters_to_the_chosen_one() {
+        let (tx, rx) = unbounded();
+        let mut app = App::new(rx, &config(0));
+        for session in [
+            session_in("a1", "/work/a", 10),
+            session_in("b1", "/work/b", 20),
+            session_in("b2", "/work/b/", 30),
+        ] {
+            tx.send((*session).clone()).unwrap();
+        }
+        app.ingest_new_sessions();
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        app.on_key(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::CONTROL))
+            .unwrap();
+        assert_eq!(app.focus, Focus::Sidebar);
+        settle(&mut app);
+        assert_eq!(
+            app.cwd_groups,
+            [(PathBuf::from("/work/b"), 2), (PathBuf::from("/work/a"), 1)]
+        );
+
+        for _ in 0..3 {
+            app.on_key(key(KeyCode::Down)).unwrap();
+        }
+        assert_eq!(app.sidebar_selected, 2);
+        app.on_key(key(KeyCode::Enter)).unwrap();
+        assert_eq!(app.focus, Focus::Results);
+        assert_eq!(app.cwd_focus, Some(PathBuf::from("/work/a")));
+        settle(&mut app);
+        let uuids: Vec<&str> = app
+            .results
+            .iter()
+            .map(|r| r.session.uuid.as_str())
+            .collect();
+        assert_eq!(uuids, ["a1"]);
+        assert_eq!(app.match_count, 3);
+
+        app.on_key(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::CONTROL))
+            .unwrap();
+        assert_eq!(app.cwd_focus, None);
+        settle(&mut app);
+        assert_eq!(app.results.len(), 3);
     }
 }
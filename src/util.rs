@@ -1,11 +1,16 @@
-use time::macros::format_description;
-use time::{OffsetDateTime, UtcOffset};
+use std::path::{Path, PathBuf};
+
 use once_cell::sync::Lazy;
+use time::macros::format_description;
+use time::{Date, Duration, OffsetDateTime, UtcOffset};
+use unicode_normalization::UnicodeNormalization;
 
 const DISPLAY_DATE: &[time::format_description::FormatItem<'static>] =
     format_description!("[year]-[month]-[day] [hour]:[minute]");
 const DISPLAY_TIME: &[time::format_description::FormatItem<'static>] =
     format_description!("[hour]:[minute]");
+const DISPLAY_DAY: &[time::format_description::FormatItem<'static>] =
+    format_description!("[weekday repr:short] [year]-[month]-[day]");
 
 pub fn format_timestamp(dt: OffsetDateTime) -> String {
     dt.to_offset(local_offset())
@@ -19,46 +24,209 @@ pub fn format_time_of_day(dt: OffsetDateTime) -> String {
         .unwrap_or_else(|_| dt.to_string())
 }
 
+/// Calendar day of `dt` in the local timezone, used to bucket sessions by date.
+pub fn local_date(dt: OffsetDateTime) -> Date {
+    dt.to_offset(local_offset()).date()
+}
+
+pub fn format_day(date: Date) -> String {
+    date.format(DISPLAY_DAY)
+        .unwrap_or_else(|_| date.to_string())
+}
+
 pub fn format_relative(dt: OffsetDateTime, reference: OffsetDateTime) -> String {
     let diff = reference - dt;
-    if diff.is_negative() {
+    if diff.whole_seconds() <= 0 {
         return "just now".into();
     }
+    format!("{} ago", format_duration(diff))
+}
 
-    let secs = diff.whole_seconds();
-    if secs <= 0 {
-        return "just now".into();
-    }
+/// Unicode NFC form of `text`, so composed and decomposed accents compare equal.
+pub fn nfc(text: &str) -> String {
+    text.nfc().collect()
+}
 
+/// `45s`, `12m`, `3h 5m`, `2d 4h`: the two largest units of a non-negative duration.
+pub fn format_duration(diff: Duration) -> String {
+    let secs = diff.whole_seconds().max(0);
     let minutes = secs / 60;
     if minutes == 0 {
-        return format!("{}s ago", secs);
+        return format!("{}s", secs);
     }
 
     let hours = minutes / 60;
     if hours == 0 {
-        return format!("{}m ago", minutes);
+        return format!("{}m", minutes);
     }
 
     let days = hours / 24;
     if days == 0 {
         let rem_minutes = minutes % 60;
         if rem_minutes == 0 {
-            return format!("{}h ago", hours);
+            return format!("{}h", hours);
         }
-        return format!("{}h {}m ago", hours, rem_minutes);
+        return format!("{}h {}m", hours, rem_minutes);
     }
 
     let rem_hours = hours % 24;
     if rem_hours == 0 {
-        return format!("{}d ago", days);
+        return format!("{}d", days);
     }
 
-    format!("{}d {}h ago", days, rem_hours)
+    format!("{}d {}h", days, rem_hours)
+}
+
+/// Characters used for truncation markers, row separators, the indexing spinner and
+/// progress bar, and the user/assistant role markers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glyphs {
+    pub ellipsis: String,
+    pub separator: char,
+    pub spinner: &'static [&'static str],
+    /// Preview prefixes for user, assistant and tool matches.
+    pub roles: [&'static str; 3],
+    /// Filled and empty cells of the indexing progress bar.
+    pub bar: [char; 2],
+    /// Joins the ends of a range, e.g. of dates.
+    pub arrow: &'static str,
 }
 
-static LOCAL_OFFSET: Lazy<UtcOffset> = Lazy::new(|| {
-    UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC)
-});
+impl Glyphs {
+    pub fn unicode() -> Self {
+        Self {
+            ellipsis: "…".into(),
+            separator: '─',
+            spinner: &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+            roles: ["▸ ", "◂ ", "$ "],
+            bar: ['█', '░'],
+            arrow: "→",
+        }
+    }
+
+    pub fn ascii() -> Self {
+        Self {
+            ellipsis: "...".into(),
+            separator: '-',
+            spinner: &["|", "/", "-", "\\"],
+            roles: ["> ", "< ", "$ "],
+            bar: ['#', '-'],
+            arrow: "->",
+        }
+    }
+
+    /// Picks ASCII glyphs when the locale explicitly names a non-UTF-8 charset
+    /// (e.g. `LANG=C`); an unset locale keeps the Unicode defaults.
+    pub fn detect() -> Self {
+        if locale_supports_utf8() {
+            Self::unicode()
+        } else {
+            Self::ascii()
+        }
+    }
+}
 
-fn local_offset() -> UtcOffset { *LOCAL_OFFSET }
+impl Default for Glyphs {
+    fn default() -> Self {
+        Self::unicode()
+    }
+}
+
+/// Canonical form of `p` when it exists on disk, without Windows' `\\?\` verbatim prefix.
+pub fn normalize_path(p: &Path) -> PathBuf {
+    let canonical = std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+    match canonical.to_str().and_then(|s| s.strip_prefix(r"\\?\")) {
+        Some(stripped) => PathBuf::from(stripped),
+        None => canonical,
+    }
+}
+
+/// True when one path contains the other. Windows-style paths (drive letter or
+/// backslashes, on any platform) compare case-insensitively with either separator.
+pub fn paths_related(a: &Path, b: &Path) -> bool {
+    let (a, b) = (path_components(a), path_components(b));
+    let shorter = a.len().min(b.len());
+    a[..shorter] == b[..shorter]
+}
+
+fn path_components(p: &Path) -> Vec<String> {
+    let raw = p.to_string_lossy();
+    let windows = cfg!(windows) || raw.contains('\\') || is_drive_path(&raw);
+    if !windows {
+        return p
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+    }
+    raw.split(['/', '\\'])
+        .filter(|part| !part.is_empty() && *part != ".")
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// `C:\...` or `C:/...`.
+fn is_drive_path(p: &str) -> bool {
+    let bytes = p.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+fn locale_supports_utf8() -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .find(|value| !value.is_empty());
+    match locale {
+        Some(value) => {
+            let lower = value.to_ascii_lowercase();
+            lower.contains("utf-8") || lower.contains("utf8")
+        }
+        None => true,
+    }
+}
+
+static LOCAL_OFFSET: Lazy<UtcOffset> =
+    Lazy::new(|| UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC));
+
+fn local_offset() -> UtcOffset {
+    *LOCAL_OFFSET
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_keep_the_two_largest_units() {
+        assert_eq!(format_duration(Duration::seconds(45)), "45s");
+        assert_eq!(format_duration(Duration::minutes(150)), "2h 30m");
+        assert_eq!(format_duration(Duration::hours(52)), "2d 4h");
+        assert_eq!(format_duration(Duration::seconds(-5)), "0s");
+        let now = OffsetDateTime::now_utc();
+        assert_eq!(format_relative(now - Duration::hours(3), now), "3h ago");
+        assert_eq!(format_relative(now + Duration::hours(1), now), "just now");
+    }
+
+    #[test]
+    fn windows_paths_relate_case_insensitively_across_separators() {
+        let session = Path::new(r"C:\Users\Me\Projects\App");
+        assert!(paths_related(
+            session,
+            Path::new("c:/users/me/projects/app/src")
+        ));
+        assert!(paths_related(session, Path::new(r"C:\Users\Me\")));
+        assert!(!paths_related(
+            session,
+            Path::new(r"C:\Users\Me\Projects\Apple")
+        ));
+        assert!(!paths_related(session, Path::new(r"D:\Users\Me")));
+        // Unix paths keep component-wise, case-sensitive matching.
+        assert!(!paths_related(
+            Path::new("/home/me/App"),
+            Path::new("/home/me/app")
+        ));
+        assert!(paths_related(
+            Path::new("/home/me/app"),
+            Path::new("/home/me")
+        ));
+    }
+}